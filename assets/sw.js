@@ -4,6 +4,9 @@ var filesToCache = [
   './index.html',
   './eframe_template.js',
   './eframe_template_bg.wasm',
+  './worker.js',
+  './worker_bg.wasm',
+  './worker_loader.js',
 ];
 
 /* Start the service worker and cache all of the app's content */
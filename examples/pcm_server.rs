@@ -0,0 +1,140 @@
+//! Reference server for the WebSocket client mode: streams a WAV file, or a
+//! test tone, as framed PCM in real time to every client that connects and
+//! prints the metrics the clients send back.
+//!
+//! `cargo run --example pcm_server -- [FILE.wav] [--port 9002]`
+//!
+//! Then connect from the web app's "WebSocket stream" section or with
+//! `cargo run --bin analyze -- --connect ws://127.0.0.1:9002`.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod server {
+    use std::{
+        io::ErrorKind,
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use eframe_template::{
+        dsp::wav::{self, Wav},
+        remote::{LiveMetrics, PcmFrame},
+    };
+    use tungstenite::{Error, Message};
+
+    /// Audio sent per message.
+    const FRAME_MS: u64 = 20;
+
+    /// Two seconds of a 440 Hz tone on the left and 660 Hz on the right,
+    /// swelling and fading so the meters have something to show.
+    fn tone() -> Wav {
+        let sample_rate = 48000.0;
+        let channel = |frequency: f32| {
+            (0..96000)
+                .map(|n| {
+                    let t = n as f32 / sample_rate;
+                    let envelope = 0.3 + 0.25 * (t * std::f32::consts::PI).sin();
+                    envelope * (t * frequency * std::f32::consts::TAU).sin()
+                })
+                .collect()
+        };
+        Wav {
+            sample_rate,
+            channels: vec![channel(440.0), channel(660.0)],
+        }
+    }
+
+    fn serve(stream: TcpStream, audio: Arc<Wav>) -> Result<(), Error> {
+        let peer = stream.peer_addr()?;
+        let mut socket = tungstenite::accept(stream).map_err(|e| match e {
+            tungstenite::HandshakeError::Failure(e) => e,
+            tungstenite::HandshakeError::Interrupted(_) => Error::ConnectionClosed,
+        })?;
+        // short reads let the loop keep the send schedule
+        socket
+            .get_ref()
+            .set_read_timeout(Some(Duration::from_millis(1)))?;
+        println!("{} connected", peer);
+
+        let frames = (audio.sample_rate as u64 * FRAME_MS / 1000) as usize;
+        let start = Instant::now();
+        let mut position = 0;
+        for sent in 0u32.. {
+            let samples = (position..position + frames)
+                .flat_map(|n| audio.channels.iter().map(move |c| c[n % c.len()]))
+                .collect();
+            position = (position + frames) % audio.frames().max(1);
+            let frame = PcmFrame {
+                sample_rate: audio.sample_rate,
+                channels: audio.channels.len(),
+                samples,
+            };
+            socket.send(Message::Binary(frame.encode()))?;
+
+            let due = start + Duration::from_millis(FRAME_MS) * (sent + 1);
+            while Instant::now() < due {
+                match socket.read() {
+                    Ok(Message::Text(text)) => match serde_json::from_str::<LiveMetrics>(&text) {
+                        Ok(metrics) => println!("{}: {:?}", peer, metrics),
+                        Err(_) => println!("{}: {}", peer, text),
+                    },
+                    Ok(Message::Close(_)) => {
+                        println!("{} disconnected", peer);
+                        return Ok(());
+                    }
+                    Ok(_) => {}
+                    Err(Error::Io(e))
+                        if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn main() -> Result<(), String> {
+        let mut args = std::env::args().skip(1);
+        let mut port = 9002;
+        let mut audio = tone();
+        while let Some(arg) = args.next() {
+            if arg == "--port" {
+                let value = args.next().ok_or("--port needs a value")?;
+                port = value.parse().map_err(|_| format!("bad port {}", value))?;
+            } else {
+                let bytes = std::fs::read(&arg).map_err(|e| format!("{}: {}", arg, e))?;
+                audio = wav::parse(&bytes).map_err(|e| format!("{}: {}", arg, e))?;
+            }
+        }
+        let audio = Arc::new(audio);
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+        println!(
+            "streaming {} channels at {} Hz on ws://127.0.0.1:{}",
+            audio.channels.len(),
+            audio.sample_rate,
+            port
+        );
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let audio = audio.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = serve(stream, audio) {
+                    println!("connection ended: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    if let Err(e) = server::main() {
+        eprintln!("error: {}", e);
+        std::process::exit(2);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,168 @@
+//! Embeds the timeline in a plain eframe app, fed by a synthetic source.
+//!
+//! `cargo run --example timeline`
+
+use eframe::egui;
+use eframe_template::timeline::{
+    Timeline, TimelineEvent, TimelineLane, TimelineMarker, TimelineSource, View,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const FRAMES: usize = 512;
+
+/// Two channels: a warbling tone that swells every second and noise bursts.
+struct Synthetic;
+
+impl Synthetic {
+    fn sample(channel: usize, n: usize) -> f32 {
+        let t = n as f32 / SAMPLE_RATE;
+        match channel {
+            0 => {
+                let frequency = 220.0 + 40.0 * (t * 0.5).sin();
+                let envelope = 0.5 + 0.4 * (t * std::f32::consts::TAU).sin();
+                envelope * (t * frequency * std::f32::consts::TAU).sin()
+            }
+            _ => {
+                // a cheap hash makes deterministic noise
+                let hash = (n as u32).wrapping_mul(2654435761) >> 8;
+                let noise = hash as f32 / (1 << 24) as f32 * 2.0 - 1.0;
+                if (t * 2.0).fract() < 0.2 {
+                    noise * 0.6
+                } else {
+                    noise * 0.02
+                }
+            }
+        }
+    }
+
+    fn chunk(channel: usize, id: usize) -> impl Iterator<Item = f32> {
+        let first = id.saturating_sub(1) * FRAMES;
+        (first..first + FRAMES).map(move |n| Self::sample(channel, n))
+    }
+}
+
+impl TimelineSource for Synthetic {
+    fn chunk_period_ms(&self) -> f32 {
+        FRAMES as f32 / SAMPLE_RATE * 1000.0
+    }
+
+    fn channels(&self) -> Vec<usize> {
+        vec![0, 1]
+    }
+
+    fn samples(&self, view: &View, channel: usize) -> Vec<f32> {
+        (view.start..=view.end)
+            .flat_map(|id| Self::chunk(channel, id))
+            .collect()
+    }
+
+    fn channel_name(&self, channel: usize) -> String {
+        ["tone", "noise"][channel].to_owned()
+    }
+}
+
+/// A lane of our own: RMS of the tone channel per chunk.
+struct EnvelopeLane;
+
+impl TimelineLane for EnvelopeLane {
+    fn key(&self) -> String {
+        "envelope".to_owned()
+    }
+
+    fn title(&self) -> String {
+        "Envelope".to_owned()
+    }
+
+    fn paint(&self, ui: &mut egui::Ui, rect: egui::Rect, view: &View, _cursor: f32) {
+        let points = (view.start..=view.end)
+            .map(|id| {
+                let power = Synthetic::chunk(0, id).map(|v| v * v).sum::<f32>() / FRAMES as f32;
+                let x = rect.min.x + view.fraction(id as f32 + 0.5) * rect.width();
+                egui::pos2(x, rect.max.y - power.sqrt() * rect.height())
+            })
+            .collect();
+        ui.painter_at(rect).add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE),
+        ));
+    }
+}
+
+struct ExampleApp {
+    timeline: Timeline,
+    /// Newest chunk while running.
+    head: usize,
+    cursor: f32,
+    width: usize,
+    running: bool,
+    last_event: Option<TimelineEvent>,
+}
+
+impl Default for ExampleApp {
+    fn default() -> Self {
+        Self {
+            timeline: Timeline::default(),
+            head: 1,
+            cursor: 1.0,
+            width: 40,
+            running: true,
+            last_event: None,
+        }
+    }
+}
+
+impl eframe::App for ExampleApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.running {
+            self.head += 1;
+            self.cursor = self.head as f32;
+            ctx.request_repaint();
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.running, "Running");
+                ui.label("Drag the header to scrub, drag the body to select, ctrl+scroll to zoom.");
+            });
+            self.timeline
+                .set_view(View::centered(self.cursor, self.width));
+            self.timeline.set_cursor(self.cursor);
+            let response = self.timeline.show(ui, &Synthetic, &[&EnvelopeLane]);
+            let view = self.timeline.view();
+            let markers: Vec<_> = (view.start..=view.end)
+                .filter(|id| id % 50 == 0)
+                .map(|id| TimelineMarker {
+                    position: id as f32,
+                    color: egui::Color32::YELLOW,
+                    label: Some(format!("{}", id)),
+                })
+                .collect();
+            self.timeline.draw_markers(ui, &markers);
+            for event in response.events {
+                match event {
+                    TimelineEvent::CursorMoved(position) => {
+                        self.running = false;
+                        self.cursor = position;
+                    }
+                    TimelineEvent::Zoomed(width) => self.width = width,
+                    TimelineEvent::SelectionChanged(_) => {}
+                }
+                self.last_event = Some(event);
+            }
+            if let Some(event) = &self.last_event {
+                ui.monospace(format!("last event: {:?}", event));
+            }
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> eframe::Result<()> {
+    eframe::run_native(
+        "Timeline example",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::<ExampleApp>::default())),
+    )
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
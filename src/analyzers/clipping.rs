@@ -0,0 +1,99 @@
+use egui::Color32;
+
+use super::{Analyzer, AnalyzerOutput};
+use crate::{
+    chunk::{to_amplitude, Chunk},
+    dsp::{fresh_frames, meter::CLIP_LEVEL},
+    timeline::TimelineMarker,
+};
+
+/// Marks runs of clipped samples in the captured audio.
+pub struct ClippingAnalyzer {
+    /// Shorter runs of full-scale samples aren't reported.
+    min_run: usize,
+    last_time: Option<f32>,
+    /// Timeline position and length of every run.
+    runs: Vec<(f32, usize)>,
+}
+
+impl Default for ClippingAnalyzer {
+    fn default() -> Self {
+        Self {
+            min_run: 3,
+            last_time: None,
+            runs: vec![],
+        }
+    }
+}
+
+impl ClippingAnalyzer {
+    /// Older runs are dropped beyond this many.
+    const MAX_RUNS: usize = 1000;
+}
+
+impl Analyzer for ClippingAnalyzer {
+    fn name(&self) -> &'static str {
+        "Clipping"
+    }
+
+    fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        let frames = chunk.frames();
+        let from = frames - fresh;
+        let clipped = |frame: usize| {
+            (0..chunk.channels).any(|channel| {
+                to_amplitude(chunk.data[frame * chunk.channels + channel]).abs() >= CLIP_LEVEL
+            })
+        };
+        let mut start = None;
+        for frame in from..=frames {
+            match (start, frame < frames && clipped(frame)) {
+                (None, true) => start = Some(frame),
+                (Some(s), false) => {
+                    if frame - s >= self.min_run {
+                        let position = chunk.id as f32 + s as f32 / frames.max(1) as f32;
+                        self.runs.push((position, frame - s));
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if self.runs.len() > Self::MAX_RUNS {
+            self.runs.drain(..self.runs.len() - Self::MAX_RUNS);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.last_time = None;
+        self.runs.clear();
+    }
+
+    fn outputs(&self) -> Vec<AnalyzerOutput> {
+        vec![AnalyzerOutput::Markers {
+            label: "Clipping".to_owned(),
+            markers: self
+                .runs
+                .iter()
+                .map(|(position, length)| TimelineMarker {
+                    position: *position,
+                    color: Color32::RED,
+                    label: Some(format!("{}", length)),
+                })
+                .collect(),
+        }]
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, _channels: usize) {
+        ui.horizontal(|ui| {
+            ui.label("Min run");
+            ui.add(
+                egui::DragValue::new(&mut self.min_run)
+                    .range(1..=64)
+                    .suffix(" samples"),
+            );
+            ui.monospace(format!("{} runs", self.runs.len()));
+        });
+    }
+}
@@ -0,0 +1,172 @@
+use egui::{epaint::PathStroke, pos2, vec2, Align2, Color32, ColorImage, FontId, Rect};
+use egui::{TextureHandle, TextureOptions};
+
+use super::{AnalyzerOutput, Image};
+use crate::timeline::{TimelineLane, View};
+
+/// Analyzer outputs draw as lanes whatever their kind.
+impl TimelineLane for AnalyzerOutput {
+    fn key(&self) -> String {
+        self.label().to_owned()
+    }
+
+    fn title(&self) -> String {
+        self.label().to_owned()
+    }
+
+    fn default_height(&self) -> f32 {
+        match self {
+            AnalyzerOutput::Markers { .. } | AnalyzerOutput::Regions { .. } => 16.0,
+            _ => 60.0,
+        }
+    }
+
+    fn paint(&self, ui: &mut egui::Ui, rect: Rect, view: &View, _cursor: f32) {
+        let vis = ui.style().noninteractive();
+        let painter = ui.painter_at(rect);
+        let text_color = vis.text_color();
+        let font_id = FontId::new(10.0, egui::FontFamily::Monospace);
+        let x = |position: f32| rect.min.x + view.fraction(position) * rect.width();
+        let label = match self {
+            AnalyzerOutput::Track(track) => {
+                let (low, high) = track.range;
+                let y = |value: f32| {
+                    rect.max.y - ((value - low) / (high - low)).clamp(0.0, 1.0) * rect.height()
+                };
+                let points: Vec<_> = track
+                    .points
+                    .iter()
+                    .filter(|(position, value)| {
+                        value.is_finite() && (0.0..=1.0).contains(&view.fraction(*position))
+                    })
+                    .map(|(position, value)| pos2(x(*position), y(*value)))
+                    .collect();
+                painter.add(egui::Shape::line(points, PathStroke::new(1.5, track.color)));
+                for (value, align) in [(high, Align2::LEFT_TOP), (low, Align2::LEFT_BOTTOM)] {
+                    painter.text(
+                        pos2(rect.min.x + 2.0, y(value)),
+                        align,
+                        format!("{}", value),
+                        font_id.clone(),
+                        text_color,
+                    );
+                }
+                if track.unit.is_empty() {
+                    track.label.clone()
+                } else {
+                    format!("{} ({})", track.label, track.unit)
+                }
+            }
+            AnalyzerOutput::Markers { label, markers } => {
+                for marker in markers {
+                    if (0.0..=1.0).contains(&view.fraction(marker.position)) {
+                        let x = x(marker.position);
+                        painter.line_segment(
+                            [pos2(x, rect.min.y), pos2(x, rect.max.y)],
+                            PathStroke::new(1.0, marker.color),
+                        );
+                        if let Some(label) = &marker.label {
+                            painter.text(
+                                pos2(x + 2.0, rect.center().y),
+                                Align2::LEFT_CENTER,
+                                label,
+                                font_id.clone(),
+                                marker.color,
+                            );
+                        }
+                    }
+                }
+                format!("{} ({})", label, markers.len())
+            }
+            AnalyzerOutput::Regions {
+                label,
+                regions,
+                color,
+            } => {
+                for (start, end) in regions {
+                    let (left, right) =
+                        (view.fraction(*start).max(0.0), view.fraction(*end).min(1.0));
+                    if left < right {
+                        let span = Rect::from_x_y_ranges(
+                            rect.min.x + left * rect.width()..=rect.min.x + right * rect.width(),
+                            rect.y_range(),
+                        );
+                        painter.rect_filled(span, 0.0, color.gamma_multiply(0.5));
+                    }
+                }
+                format!("{} ({})", label, regions.len())
+            }
+            AnalyzerOutput::Image(image) => {
+                let id = egui::Id::new(("analyzer_image", &image.label));
+                let cached: Option<(u64, TextureHandle)> = ui.ctx().data(|d| d.get_temp(id));
+                let texture = match cached {
+                    Some((generation, texture)) if generation == image.generation => texture,
+                    _ => {
+                        let texture = ui.ctx().load_texture(
+                            image.label.clone(),
+                            color_image(image),
+                            TextureOptions::LINEAR,
+                        );
+                        ui.ctx()
+                            .data_mut(|d| d.insert_temp(id, (image.generation, texture.clone())));
+                        texture
+                    }
+                };
+                // columns are centered on their positions
+                let half = (image.end - image.start) / (2 * image.width.max(2) - 2) as f32;
+                let image_rect = Rect::from_x_y_ranges(
+                    x(image.start - half)..=x(image.end + half),
+                    rect.y_range(),
+                );
+                painter.image(
+                    texture.id(),
+                    image_rect,
+                    Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+                image.label.clone()
+            }
+        };
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            label,
+            font_id,
+            text_color,
+        );
+    }
+}
+
+/// `image` with the bottom row at the bottom, through a black-blue-red-yellow map.
+fn color_image(image: &Image) -> ColorImage {
+    const STOPS: [(f32, [f32; 3]); 5] = [
+        (0.0, [0.0, 0.0, 0.0]),
+        (0.3, [30.0, 30.0, 160.0]),
+        (0.6, [200.0, 40.0, 60.0]),
+        (0.85, [250.0, 200.0, 40.0]),
+        (1.0, [255.0, 255.0, 230.0]),
+    ];
+    let color = |value: f32| {
+        let i = STOPS
+            .iter()
+            .position(|(stop, _)| *stop >= value)
+            .unwrap_or(STOPS.len() - 1)
+            .max(1);
+        let ((a, from), (b, to)) = (STOPS[i - 1], STOPS[i]);
+        let t = ((value - a) / (b - a)).clamp(0.0, 1.0);
+        let mix = |k: usize| (from[k] + t * (to[k] - from[k])) as u8;
+        Color32::from_rgb(mix(0), mix(1), mix(2))
+    };
+    let pixels = (0..image.height)
+        .rev()
+        .flat_map(|row| {
+            image.values[row * image.width..(row + 1) * image.width]
+                .iter()
+                .map(|v| color(*v))
+        })
+        .collect();
+    ColorImage {
+        size: [image.width, image.height],
+        pixels,
+    }
+}
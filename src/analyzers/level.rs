@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use egui::Color32;
+
+use super::{Analyzer, AnalyzerOutput, Track};
+use crate::{
+    chunk::Chunk,
+    dsp::{
+        fresh_frames,
+        meter::{to_db, MIN_DB},
+        mixdown,
+    },
+};
+
+/// RMS and peak level of the fresh audio of every captured chunk.
+#[derive(Default)]
+pub struct LevelAnalyzer {
+    show_peak: bool,
+    last_time: Option<f32>,
+    /// Chunk id to (RMS, peak) in dBFS.
+    levels: BTreeMap<usize, (f32, f32)>,
+}
+
+impl Analyzer for LevelAnalyzer {
+    fn name(&self) -> &'static str {
+        "Level"
+    }
+
+    fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        if fresh == 0 {
+            return;
+        }
+        let channels: Vec<usize> = (0..chunk.channels).collect();
+        let mono = mixdown(chunk, &channels);
+        let fresh = &mono[mono.len() - fresh..];
+        let power = fresh.iter().map(|v| v * v).sum::<f32>() / fresh.len() as f32;
+        let peak = fresh.iter().fold(0.0f32, |acc, v| acc.max(v.abs()));
+        self.levels
+            .insert(chunk.id, (to_db(power.sqrt()), to_db(peak)));
+    }
+
+    fn clear(&mut self) {
+        self.last_time = None;
+        self.levels.clear();
+    }
+
+    fn outputs(&self) -> Vec<AnalyzerOutput> {
+        let track = |label: &str, pick: fn(&(f32, f32)) -> f32, color| {
+            AnalyzerOutput::Track(Track {
+                label: label.to_owned(),
+                points: self
+                    .levels
+                    .iter()
+                    .map(|(id, levels)| (*id as f32 + 0.5, pick(levels)))
+                    .collect(),
+                range: (-60.0, 0.0),
+                unit: "dBFS",
+                color,
+            })
+        };
+        let mut outputs = vec![track("RMS", |l| l.0, Color32::from_rgb(90, 170, 255))];
+        if self.show_peak {
+            outputs.push(track("Peak", |l| l.1, Color32::from_rgb(255, 170, 60)));
+        }
+        outputs
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, _channels: usize) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_peak, "Peak track");
+            if let Some((rms, peak)) = self.levels.values().next_back() {
+                ui.monospace(format!(
+                    "RMS {:.1}  peak {:.1} dBFS",
+                    rms.max(MIN_DB),
+                    peak.max(MIN_DB)
+                ));
+            }
+        });
+    }
+}
@@ -0,0 +1,284 @@
+//! Pluggable chunk-stream analyzers. An analyzer is fed captured chunks as
+//! they arrive and/or loaded ranges on request, keeps its own state and
+//! reports time-aligned outputs that the `Timeline` draws as lanes.
+//!
+//! The spectrogram, level, clipping, onset, silence and trigger features are
+//! analyzers. Others are still wired into `TemplateApp` by hand:
+//!
+//! - the pitch track and the loudness lane predate the trait; they draw their
+//!   own `TimelineLane`, and range queries from JavaScript read their settings;
+//! - the level meters and the RTA, distortion, sweep and room-acoustics panels
+//!   measure rather than produce outputs over time, so they have no lane to
+//!   offer and keep their own panels.
+
+mod clipping;
+mod lane;
+mod level;
+mod onset;
+mod segments;
+mod spectrogram;
+mod triggers;
+
+use std::{any::Any, sync::Arc};
+
+use egui::Color32;
+
+use crate::{bridge::BridgeEvent, buffer::RangeLoad, chunk::Chunk, timeline::TimelineMarker, Log};
+
+pub use clipping::ClippingAnalyzer;
+pub use level::LevelAnalyzer;
+pub use onset::OnsetAnalyzer;
+pub use segments::SegmentAnalyzer;
+pub use spectrogram::SpectrogramAnalyzer;
+pub use triggers::TriggerAnalyzer;
+
+/// Values over time, drawn as a line.
+pub struct Track {
+    pub label: String,
+    /// Timeline position and value.
+    pub points: Vec<(f32, f32)>,
+    /// Values mapped to the bottom and top of the lane.
+    pub range: (f32, f32),
+    pub unit: &'static str,
+    pub color: Color32,
+}
+
+/// A grid of values over time, e.g. a spectrogram.
+#[derive(Clone)]
+pub struct Image {
+    pub label: String,
+    /// Timeline positions of the first and last column.
+    pub start: f32,
+    pub end: f32,
+    pub width: usize,
+    pub height: usize,
+    /// 0..1, row-major with the bottom row first.
+    pub values: Arc<[f32]>,
+    /// Changes whenever `values` do, so renderers can cache the image.
+    pub generation: u64,
+}
+
+pub enum AnalyzerOutput {
+    Track(Track),
+    Markers {
+        label: String,
+        markers: Vec<TimelineMarker>,
+    },
+    Image(Image),
+    /// Spans of timeline positions, e.g. detected activity.
+    Regions {
+        label: String,
+        regions: Vec<(f32, f32)>,
+        color: Color32,
+    },
+}
+
+impl AnalyzerOutput {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Track(track) => &track.label,
+            Self::Markers { label, .. } => label,
+            Self::Image(image) => &image.label,
+            Self::Regions { label, .. } => label,
+        }
+    }
+}
+
+pub trait Analyzer {
+    fn name(&self) -> &'static str;
+
+    /// Whether `analyze` does anything; live-only analyzers leave it out.
+    fn offline(&self) -> bool {
+        false
+    }
+
+    /// Called with every captured chunk while enabled.
+    fn ingest(&mut self, _chunk: &Chunk, _sample_rate: f32) {}
+
+    /// Called with the chunks of a loaded range. Slow analyses should hand
+    /// the work to a `JobHandle` and pick up its results in `poll`.
+    fn analyze(&mut self, _chunks: &[Chunk], _sample_rate: f32) {}
+
+    /// Called every frame, e.g. to collect background results.
+    fn poll(&mut self) {}
+
+    /// Progress of a running background analysis.
+    fn progress(&self) -> Option<f32> {
+        None
+    }
+
+    /// Stops a running background analysis, keeping the results so far.
+    fn cancel(&mut self) {}
+
+    fn clear(&mut self);
+
+    fn outputs(&self) -> Vec<AnalyzerOutput>;
+
+    /// Events for JavaScript since the last call, timed from the chunks'
+    /// capture times.
+    fn take_events(&mut self) -> Vec<BridgeEvent> {
+        vec![]
+    }
+
+    /// Analyzer-specific settings, for a session of `channels` channels.
+    fn settings_ui(&mut self, _ui: &mut egui::Ui, _channels: usize) {}
+
+    /// Analyzers with an API beyond this trait return `Some(self)`, so the
+    /// app can reach it through `AnalyzerRegistry::get`.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+}
+
+struct Slot {
+    analyzer: Box<dyn Analyzer>,
+    enabled: bool,
+    show_lanes: bool,
+    pending: Option<RangeLoad>,
+}
+
+/// The analyzers the app runs, with their enable and lane toggles.
+pub struct AnalyzerRegistry {
+    slots: Vec<Slot>,
+}
+
+impl Log for AnalyzerRegistry {
+    fn name() -> &'static str {
+        "Analyzers"
+    }
+}
+
+impl Default for AnalyzerRegistry {
+    fn default() -> Self {
+        let mut registry = Self { slots: vec![] };
+        registry.register(Box::<LevelAnalyzer>::default());
+        registry.register(Box::<ClippingAnalyzer>::default());
+        registry.register(Box::<SpectrogramAnalyzer>::default());
+        registry.register(Box::<OnsetAnalyzer>::default());
+        registry.register(Box::<SegmentAnalyzer>::default());
+        registry.register(Box::<TriggerAnalyzer>::default());
+        registry
+    }
+}
+
+impl AnalyzerRegistry {
+    pub fn register(&mut self, analyzer: Box<dyn Analyzer>) {
+        Self::log(&format!("registered {}", analyzer.name()));
+        self.slots.push(Slot {
+            analyzer,
+            enabled: false,
+            show_lanes: true,
+            pending: None,
+        });
+    }
+
+    /// The analyzer of type `T` if it is enabled.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.slots
+            .iter()
+            .filter(|s| s.enabled)
+            .find_map(|s| s.analyzer.as_any()?.downcast_ref())
+    }
+
+    /// The analyzer of type `T` if it is enabled.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.slots
+            .iter_mut()
+            .filter(|s| s.enabled)
+            .find_map(|s| s.analyzer.as_any_mut()?.downcast_mut())
+    }
+
+    /// Enables the analyzer of type `T` and returns it.
+    pub fn enable<T: 'static>(&mut self) -> Option<&mut T> {
+        let slot = self
+            .slots
+            .iter_mut()
+            .find(|s| s.analyzer.as_any().map_or(false, |a| a.is::<T>()))?;
+        slot.enabled = true;
+        slot.analyzer.as_any_mut()?.downcast_mut()
+    }
+
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            slot.pending = None;
+            slot.analyzer.clear();
+        }
+    }
+
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        for slot in self.slots.iter_mut().filter(|s| s.enabled) {
+            slot.analyzer.ingest(chunk, sample_rate);
+        }
+    }
+
+    /// Hands loaded ranges to their analyzers and lets them collect
+    /// background results.
+    pub fn poll(&mut self, sample_rate: f32) {
+        for slot in &mut self.slots {
+            if let Some(load) = RangeLoad::take_ready(&mut slot.pending) {
+                slot.analyzer.analyze(&load.chunks(), sample_rate);
+            }
+            slot.analyzer.poll();
+        }
+    }
+
+    /// Events of all analyzers since the last call.
+    pub fn take_events(&mut self) -> Vec<BridgeEvent> {
+        self.slots
+            .iter_mut()
+            .flat_map(|s| s.analyzer.take_events())
+            .collect()
+    }
+
+    /// Whether an analyzer is working in the background.
+    pub fn is_busy(&self) -> bool {
+        self.slots.iter().any(|s| s.analyzer.progress().is_some())
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, channels: usize, load: impl Fn(bool) -> RangeLoad) {
+        for slot in &mut self.slots {
+            let name = slot.analyzer.name();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut slot.enabled, name);
+                ui.add_enabled_ui(slot.enabled, |ui| {
+                    ui.checkbox(&mut slot.show_lanes, "Lanes");
+                    if slot.analyzer.offline() {
+                        if ui.button("Analyze view").clicked() {
+                            slot.pending = Some(load(false));
+                        }
+                        if ui.button("Analyze session").clicked() {
+                            slot.pending = Some(load(true));
+                        }
+                    }
+                    if ui.button("Clear").clicked() {
+                        slot.analyzer.clear();
+                    }
+                });
+                if let Some(pending) = &slot.pending {
+                    ui.add(egui::ProgressBar::new(pending.progress()).desired_width(100.0));
+                } else if let Some(progress) = slot.analyzer.progress() {
+                    ui.add(egui::ProgressBar::new(progress).desired_width(100.0));
+                    if ui.button("Cancel").clicked() {
+                        slot.analyzer.cancel();
+                    }
+                }
+            });
+            if slot.enabled {
+                ui.indent(name, |ui| slot.analyzer.settings_ui(ui, channels));
+            }
+        }
+    }
+
+    /// Outputs of the enabled analyzers that want lanes.
+    pub fn lanes(&self) -> Vec<AnalyzerOutput> {
+        self.slots
+            .iter()
+            .filter(|s| s.enabled && s.show_lanes)
+            .flat_map(|s| s.analyzer.outputs())
+            .collect()
+    }
+}
@@ -0,0 +1,265 @@
+use egui::Color32;
+
+use super::{Analyzer, AnalyzerOutput, Track};
+use crate::{
+    channels::ChannelSelection,
+    chunk::Chunk,
+    dsp::{
+        onset::{OnsetAnalysis, OnsetMethod, OnsetSettings},
+        Stream,
+    },
+    export::Export,
+    jobs::{JobHandle, JobOutput, JobRequest, JobUpdate},
+    timeline::TimelineMarker,
+    Log,
+};
+
+/// An event placed on the timeline.
+#[derive(Clone, Copy, Debug)]
+struct Event {
+    position: f32,
+    time: f32,
+}
+
+#[derive(Default)]
+struct OnsetResult {
+    /// Detection function against timeline position.
+    detection: Vec<(f32, f32)>,
+    onsets: Vec<Event>,
+    beats: Vec<Event>,
+    tempo: Option<f32>,
+}
+
+/// Onset, tempo and beat detection over a loaded range.
+pub struct OnsetAnalyzer {
+    settings: OnsetSettings,
+    channels: ChannelSelection,
+    show_onsets: bool,
+    show_beats: bool,
+    /// Signal of the last analysis, kept to re-run it when settings change.
+    stream: Option<(Stream, f32)>,
+    analyzed_with: Option<OnsetSettings>,
+    job: Option<JobHandle>,
+    result: Option<OnsetResult>,
+}
+
+impl Default for OnsetAnalyzer {
+    fn default() -> Self {
+        Self {
+            settings: OnsetSettings::default(),
+            channels: ChannelSelection::default(),
+            show_onsets: true,
+            show_beats: true,
+            stream: None,
+            analyzed_with: None,
+            job: None,
+            result: None,
+        }
+    }
+}
+
+impl Log for OnsetAnalyzer {
+    fn name() -> &'static str {
+        "Onsets"
+    }
+}
+
+impl OnsetAnalyzer {
+    const ONSET_COLOR: Color32 = Color32::from_rgb(230, 150, 40);
+    const BEAT_COLOR: Color32 = Color32::from_rgb(70, 140, 230);
+    const BEATS_PER_BAR: usize = 4;
+
+    fn to_result(analysis: OnsetAnalysis, stream: &Stream, sample_rate: f32) -> OnsetResult {
+        let event = |sample: &usize| Event {
+            position: stream.position(*sample),
+            time: stream.time(*sample, sample_rate),
+        };
+        OnsetResult {
+            detection: analysis
+                .positions
+                .iter()
+                .zip(&analysis.detection)
+                .map(|(sample, value)| (stream.position(*sample), *value))
+                .collect(),
+            onsets: analysis.onsets.iter().map(event).collect(),
+            beats: analysis.beats.iter().map(event).collect(),
+            tempo: analysis.tempo,
+        }
+    }
+
+    fn to_csv(result: &OnsetResult) -> String {
+        let mut csv = "kind,time_s,chunk_position\n".to_owned();
+        let rows = result
+            .onsets
+            .iter()
+            .map(|e| ("onset", e))
+            .chain(result.beats.iter().map(|e| ("beat", e)));
+        for (kind, event) in rows {
+            csv.push_str(&format!(
+                "{},{:.4},{:.4}\n",
+                kind, event.time, event.position
+            ));
+        }
+        csv
+    }
+}
+
+impl Analyzer for OnsetAnalyzer {
+    fn name(&self) -> &'static str {
+        "Onsets & tempo"
+    }
+
+    fn offline(&self) -> bool {
+        true
+    }
+
+    fn analyze(&mut self, chunks: &[Chunk], sample_rate: f32) {
+        if let (Some(first), Some(last)) = (chunks.first(), chunks.last()) {
+            Self::log(&format!("detecting onsets in {}..={}", first.id, last.id));
+        }
+        let channels = &self.channels;
+        let stream = Stream::from_chunks(chunks, sample_rate, |n| channels.active(n));
+        self.stream = Some((stream, sample_rate));
+        self.analyzed_with = None;
+        self.job = None;
+    }
+
+    /// Collects the running analysis and starts a new one after settings
+    /// changed.
+    fn poll(&mut self) {
+        if let Some(job) = self.job.as_mut() {
+            for update in job.poll() {
+                match update {
+                    JobUpdate::Done(Some(JobOutput::Onsets(analysis))) => {
+                        if let Some((stream, sample_rate)) = &self.stream {
+                            self.result = Some(Self::to_result(analysis, stream, *sample_rate));
+                        }
+                    }
+                    JobUpdate::Failed(e) => Self::warn(&format!("detection failed: {}", e)),
+                    _ => {}
+                }
+            }
+            if job.is_finished() {
+                self.job = None;
+            }
+        }
+        if self.analyzed_with.as_ref() == Some(&self.settings) {
+            return;
+        }
+        let Some((stream, sample_rate)) = &self.stream else {
+            return;
+        };
+        self.job = Some(JobHandle::spawn(JobRequest::Onsets {
+            samples: stream.samples.clone(),
+            sample_rate: *sample_rate,
+            settings: self.settings.clone(),
+        }));
+        self.analyzed_with = Some(self.settings.clone());
+    }
+
+    fn progress(&self) -> Option<f32> {
+        self.job.as_ref().map(|j| j.progress())
+    }
+
+    fn cancel(&mut self) {
+        self.job = None;
+    }
+
+    fn clear(&mut self) {
+        self.stream = None;
+        self.analyzed_with = None;
+        self.job = None;
+        self.result = None;
+    }
+
+    fn outputs(&self) -> Vec<AnalyzerOutput> {
+        let Some(result) = &self.result else {
+            return vec![];
+        };
+        let mut outputs = vec![AnalyzerOutput::Track(Track {
+            label: "Onset detection".to_owned(),
+            points: result.detection.clone(),
+            range: (0.0, 1.0),
+            unit: "",
+            color: Self::ONSET_COLOR,
+        })];
+        if self.show_onsets {
+            outputs.push(AnalyzerOutput::Markers {
+                label: "Onsets".to_owned(),
+                markers: result
+                    .onsets
+                    .iter()
+                    .map(|e| TimelineMarker {
+                        position: e.position,
+                        color: Self::ONSET_COLOR,
+                        label: None,
+                    })
+                    .collect(),
+            });
+        }
+        if self.show_beats {
+            outputs.push(AnalyzerOutput::Markers {
+                label: "Beats".to_owned(),
+                markers: result
+                    .beats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| TimelineMarker {
+                        position: e.position,
+                        color: Self::BEAT_COLOR,
+                        // number the downbeats, assuming 4/4 from the first beat
+                        label: (i % Self::BEATS_PER_BAR == 0)
+                            .then(|| format!("{}", i / Self::BEATS_PER_BAR + 1)),
+                    })
+                    .collect(),
+            });
+        }
+        outputs
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, channels: usize) {
+        ui.horizontal(|ui| {
+            self.channels.picker(ui, "onset_channels", channels);
+            egui::ComboBox::from_id_source("onset_method")
+                .selected_text(self.settings.method.label())
+                .show_ui(ui, |ui| {
+                    for method in OnsetMethod::ALL {
+                        ui.selectable_value(&mut self.settings.method, method, method.label());
+                    }
+                });
+            ui.label("Sensitivity");
+            ui.add(egui::Slider::new(&mut self.settings.sensitivity, 0.0..=1.0));
+        });
+        ui.horizontal(|ui| {
+            let settings = &mut self.settings;
+            ui.label("Min gap");
+            ui.add(
+                egui::DragValue::new(&mut settings.min_gap_ms)
+                    .range(5.0..=1000.0)
+                    .suffix(" ms"),
+            );
+            ui.label("Tempo range");
+            ui.add(egui::DragValue::new(&mut settings.min_bpm).range(20.0..=settings.max_bpm));
+            ui.add(egui::DragValue::new(&mut settings.max_bpm).range(settings.min_bpm..=400.0));
+            ui.label("BPM");
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_onsets, "Onset markers");
+            ui.checkbox(&mut self.show_beats, "Beat grid");
+        });
+        let Some(result) = &self.result else {
+            return;
+        };
+        ui.monospace(format!(
+            "{} onsets  tempo {}  {} beats",
+            result.onsets.len(),
+            result
+                .tempo
+                .map_or("--".to_owned(), |bpm| format!("{:.1} BPM", bpm)),
+            result.beats.len()
+        ));
+        if ui.button("Export markers CSV").clicked() {
+            Export::csv("onsets.csv", &Self::to_csv(result));
+        }
+    }
+}
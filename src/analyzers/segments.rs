@@ -0,0 +1,292 @@
+use std::any::Any;
+
+use egui::Color32;
+
+use super::{Analyzer, AnalyzerOutput, Track};
+use crate::{
+    channels::ChannelSelection,
+    chunk::Chunk,
+    dsp::{
+        vad::{VadAnalysis, VadMethod, VadSettings},
+        wav::Wav,
+        Stream,
+    },
+    export::Export,
+    jobs::{JobHandle, JobOutput, JobRequest, JobUpdate},
+    Log,
+};
+
+#[derive(Default)]
+struct SegmentResult {
+    /// Activity score against timeline position.
+    scores: Vec<(f32, f32)>,
+    /// Active regions as timeline positions.
+    regions: Vec<(f32, f32)>,
+    /// Active regions as sample ranges of the analyzed stream.
+    ranges: Vec<(usize, usize)>,
+}
+
+/// Silence detection over a loaded range, with silence skipping and
+/// splitting into clips.
+#[derive(Default)]
+pub struct SegmentAnalyzer {
+    settings: VadSettings,
+    channels: ChannelSelection,
+    /// Step over silent stretches while scrolling through the session.
+    skip_silence: bool,
+    stream: Option<(Stream, f32)>,
+    analyzed_with: Option<VadSettings>,
+    job: Option<JobHandle>,
+    result: Option<SegmentResult>,
+    play_request: Option<(Vec<f32>, f32)>,
+    split_request: Option<Vec<(f32, f32)>>,
+}
+
+impl Log for SegmentAnalyzer {
+    fn name() -> &'static str {
+        "Segments"
+    }
+}
+
+impl SegmentAnalyzer {
+    const ACTIVE_COLOR: Color32 = Color32::from_rgb(60, 180, 75);
+
+    fn to_result(analysis: VadAnalysis, stream: &Stream) -> SegmentResult {
+        let frame = analysis.frame_size;
+        SegmentResult {
+            scores: analysis
+                .scores
+                .iter()
+                .enumerate()
+                .map(|(i, score)| (stream.position(i * frame + frame / 2), *score))
+                .collect(),
+            regions: analysis
+                .regions
+                .iter()
+                .map(|(start, end)| (stream.position(*start), stream.position(end - 1)))
+                .collect(),
+            ranges: analysis.regions,
+        }
+    }
+
+    /// Active audio to play with the silence cut out, if requested.
+    pub fn take_play_request(&mut self) -> Option<(Vec<f32>, f32)> {
+        self.play_request.take()
+    }
+
+    /// Active regions the user wants as separate sub-sessions.
+    pub fn take_split_request(&mut self) -> Option<Vec<(f32, f32)>> {
+        self.split_request.take()
+    }
+
+    /// Where to move from `position` to step over silence, or `None` if it
+    /// isn't silent or skipping is off.
+    pub fn skip(&self, position: f32, forward: bool) -> Option<f32> {
+        let result = self.result.as_ref().filter(|_| self.skip_silence)?;
+        let regions = &result.regions;
+        if regions.is_empty()
+            || regions
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&position))
+        {
+            return None;
+        }
+        if forward {
+            regions
+                .iter()
+                .map(|(start, _)| *start)
+                .find(|start| *start > position)
+        } else {
+            regions
+                .iter()
+                .rev()
+                .map(|(_, end)| *end)
+                .find(|end| *end < position)
+        }
+    }
+}
+
+impl Analyzer for SegmentAnalyzer {
+    fn name(&self) -> &'static str {
+        "Silence & activity"
+    }
+
+    fn offline(&self) -> bool {
+        true
+    }
+
+    fn analyze(&mut self, chunks: &[Chunk], sample_rate: f32) {
+        if let (Some(first), Some(last)) = (chunks.first(), chunks.last()) {
+            Self::log(&format!("segmenting {}..={}", first.id, last.id));
+        }
+        let channels = &self.channels;
+        let stream = Stream::from_chunks(chunks, sample_rate, |n| channels.active(n));
+        self.stream = Some((stream, sample_rate));
+        self.analyzed_with = None;
+        self.job = None;
+    }
+
+    /// Collects the running detection and starts a new one after settings
+    /// changed.
+    fn poll(&mut self) {
+        if let Some(job) = self.job.as_mut() {
+            for update in job.poll() {
+                match update {
+                    JobUpdate::Done(Some(JobOutput::Activity(analysis))) => {
+                        if let Some((stream, _)) = &self.stream {
+                            self.result = Some(Self::to_result(analysis, stream));
+                        }
+                    }
+                    JobUpdate::Failed(e) => Self::warn(&format!("detection failed: {}", e)),
+                    _ => {}
+                }
+            }
+            if job.is_finished() {
+                self.job = None;
+            }
+        }
+        if self.analyzed_with.as_ref() == Some(&self.settings) {
+            return;
+        }
+        let Some((stream, sample_rate)) = &self.stream else {
+            return;
+        };
+        self.job = Some(JobHandle::spawn(JobRequest::Activity {
+            samples: stream.samples.clone(),
+            sample_rate: *sample_rate,
+            settings: self.settings.clone(),
+        }));
+        self.analyzed_with = Some(self.settings.clone());
+    }
+
+    fn progress(&self) -> Option<f32> {
+        self.job.as_ref().map(|j| j.progress())
+    }
+
+    fn cancel(&mut self) {
+        self.job = None;
+    }
+
+    fn clear(&mut self) {
+        self.stream = None;
+        self.analyzed_with = None;
+        self.job = None;
+        self.result = None;
+    }
+
+    fn outputs(&self) -> Vec<AnalyzerOutput> {
+        let Some(result) = &self.result else {
+            return vec![];
+        };
+        vec![
+            AnalyzerOutput::Track(Track {
+                label: "Activity score".to_owned(),
+                points: result.scores.clone(),
+                range: (0.0, 1.0),
+                unit: "",
+                color: Self::ACTIVE_COLOR,
+            }),
+            AnalyzerOutput::Regions {
+                label: "Activity".to_owned(),
+                regions: result.regions.clone(),
+                color: Self::ACTIVE_COLOR,
+            },
+        ]
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, channels: usize) {
+        ui.horizontal(|ui| {
+            self.channels.picker(ui, "segment_channels", channels);
+            egui::ComboBox::from_id_source("segment_method")
+                .selected_text(self.settings.method.label())
+                .show_ui(ui, |ui| {
+                    for method in VadMethod::ALL {
+                        ui.selectable_value(&mut self.settings.method, method, method.label());
+                    }
+                });
+            let settings = &mut self.settings;
+            match settings.method {
+                VadMethod::Level => {
+                    ui.label("Threshold");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.threshold_db)
+                            .range(-90.0..=0.0)
+                            .suffix(" dBFS"),
+                    );
+                }
+                VadMethod::Spectral => {
+                    ui.label("Margin");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.margin_db)
+                            .range(1.0..=40.0)
+                            .suffix(" dB"),
+                    );
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let settings = &mut self.settings;
+            for (label, value, range) in [
+                ("Frame", &mut settings.frame_ms, 5.0..=100.0),
+                ("Hangover", &mut settings.hangover_ms, 0.0..=2000.0),
+                ("Min silence", &mut settings.min_silence_ms, 0.0..=5000.0),
+                ("Min activity", &mut settings.min_activity_ms, 0.0..=5000.0),
+            ] {
+                ui.label(label);
+                ui.add(egui::DragValue::new(value).range(range).suffix(" ms"));
+            }
+        });
+        ui.checkbox(&mut self.skip_silence, "Skip silence");
+        let (Some(result), Some((stream, sample_rate))) = (&self.result, &self.stream) else {
+            return;
+        };
+        let active: usize = result.ranges.iter().map(|(start, end)| end - start).sum();
+        let total = stream.samples.len().max(1);
+        ui.monospace(format!(
+            "{} regions  {:.1} s active of {:.1} s ({:.0}%)",
+            result.ranges.len(),
+            active as f32 / sample_rate,
+            total as f32 / sample_rate,
+            100.0 * active as f32 / total as f32
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Play without silence").clicked() {
+                let samples = result
+                    .ranges
+                    .iter()
+                    .flat_map(|(start, end)| stream.samples[*start..*end].iter().copied())
+                    .collect();
+                self.play_request = Some((samples, *sample_rate));
+            }
+            if ui.button("Export clips").clicked() {
+                for (i, (start, end)) in result.ranges.iter().enumerate() {
+                    let wav = Wav {
+                        sample_rate: *sample_rate,
+                        channels: vec![stream.samples[*start..*end].to_vec()],
+                    };
+                    let filename = format!(
+                        "clip_{:03}_{:.2}s.wav",
+                        i + 1,
+                        stream.time(*start, *sample_rate)
+                    );
+                    Export::bytes(&filename, "audio/wav", &wav.encode());
+                }
+            }
+            if ui
+                .button("Split into regions")
+                .on_hover_text("Add every active region as an annotation region")
+                .clicked()
+            {
+                self.split_request = Some(result.regions.clone());
+            }
+        });
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -0,0 +1,181 @@
+use super::{Analyzer, AnalyzerOutput, Image};
+use crate::{
+    chunk::Chunk,
+    dsp::Stream,
+    jobs::{JobHandle, JobOutput, JobRequest, JobUpdate, SpectrogramRequest},
+    Log,
+};
+
+/// Short-time spectrum of a loaded range, all channels mixed down. The
+/// columns are computed in the background and fill in as they arrive.
+pub struct SpectrogramAnalyzer {
+    fft_size: usize,
+    /// Levels below this many dBFS are black.
+    floor_db: f32,
+    generation: u64,
+    image: Option<Image>,
+    /// Columns received so far, row-major like `Image::values`.
+    values: Vec<f32>,
+    /// Columns received since `image` was last rebuilt.
+    stale: usize,
+    job: Option<JobHandle>,
+}
+
+impl Default for SpectrogramAnalyzer {
+    fn default() -> Self {
+        Self {
+            fft_size: 1024,
+            floor_db: -100.0,
+            generation: 0,
+            image: None,
+            values: vec![],
+            stale: 0,
+            job: None,
+        }
+    }
+}
+
+impl Log for SpectrogramAnalyzer {
+    fn name() -> &'static str {
+        "Spectrogram"
+    }
+}
+
+impl SpectrogramAnalyzer {
+    /// Long ranges are decimated in time to at most this many columns.
+    const MAX_COLUMNS: usize = 2048;
+    /// New columns collected before the image is rebuilt.
+    const REFRESH_COLUMNS: usize = 128;
+
+    fn refresh_image(&mut self) {
+        if let Some(image) = self.image.as_mut() {
+            self.generation += 1;
+            image.values = self.values.as_slice().into();
+            image.generation = self.generation;
+        }
+        self.stale = 0;
+    }
+}
+
+impl Analyzer for SpectrogramAnalyzer {
+    fn name(&self) -> &'static str {
+        "Spectrogram"
+    }
+
+    fn offline(&self) -> bool {
+        true
+    }
+
+    fn analyze(&mut self, chunks: &[Chunk], sample_rate: f32) {
+        self.job = None;
+        let stream = Stream::from_chunks(chunks, sample_rate, |n| (0..n).collect());
+        let n = self.fft_size;
+        if stream.samples.len() < n {
+            self.image = None;
+            return;
+        }
+        let hop = (n / 2).max((stream.samples.len() - n) / Self::MAX_COLUMNS + 1);
+        let width = (stream.samples.len() - n) / hop + 1;
+        let height = n / 2;
+        let (start, end) = (
+            stream.position(n / 2),
+            stream.position((width - 1) * hop + n / 2),
+        );
+        let request = SpectrogramRequest {
+            samples: stream.samples,
+            fft_size: n,
+            hop,
+            floor_db: self.floor_db,
+        };
+        self.values = vec![0.0; width * height];
+        self.generation += 1;
+        self.image = Some(Image {
+            label: format!("Spectrogram 0..{:.0} Hz", sample_rate / 2.0),
+            start,
+            end,
+            width,
+            height,
+            values: self.values.as_slice().into(),
+            generation: self.generation,
+        });
+        self.stale = 0;
+        self.job = Some(JobHandle::spawn(JobRequest::Spectrogram(request)));
+    }
+
+    fn poll(&mut self) {
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        let Some((width, height)) = self.image.as_ref().map(|i| (i.width, i.height)) else {
+            return;
+        };
+        for update in job.poll() {
+            match update {
+                JobUpdate::Progress {
+                    partial: Some(JobOutput::SpectrogramColumns { first, columns }),
+                    ..
+                } => {
+                    for (i, column) in columns.iter().enumerate() {
+                        for (row, value) in column.iter().take(height).enumerate() {
+                            if let Some(v) = self.values.get_mut(row * width + first + i) {
+                                *v = *value;
+                            }
+                        }
+                    }
+                    self.stale += columns.len();
+                }
+                JobUpdate::Failed(e) => Self::warn(&format!("failed: {}", e)),
+                _ => {}
+            }
+        }
+        if job.is_finished() {
+            self.job = None;
+            self.refresh_image();
+        } else if self.stale >= Self::REFRESH_COLUMNS {
+            self.refresh_image();
+        }
+    }
+
+    fn progress(&self) -> Option<f32> {
+        self.job.as_ref().map(|j| j.progress())
+    }
+
+    fn cancel(&mut self) {
+        self.job = None;
+        self.refresh_image();
+    }
+
+    fn clear(&mut self) {
+        self.job = None;
+        self.image = None;
+        self.values = vec![];
+    }
+
+    fn outputs(&self) -> Vec<AnalyzerOutput> {
+        self.image
+            .iter()
+            .map(|image| AnalyzerOutput::Image(image.clone()))
+            .collect()
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, _channels: usize) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("spectrogram_fft")
+                .selected_text(format!("FFT {}", self.fft_size))
+                .show_ui(ui, |ui| {
+                    for size in [256, 512, 1024, 2048, 4096] {
+                        ui.selectable_value(&mut self.fft_size, size, format!("{}", size));
+                    }
+                });
+            ui.label("Floor");
+            ui.add(
+                egui::DragValue::new(&mut self.floor_db)
+                    .range(-140.0..=-20.0)
+                    .suffix(" dBFS"),
+            );
+            if let Some(image) = &self.image {
+                ui.monospace(format!("{}×{}", image.width, image.height));
+            }
+        });
+    }
+}
@@ -0,0 +1,226 @@
+use std::any::Any;
+
+use egui::Color32;
+
+use super::{Analyzer, AnalyzerOutput};
+use crate::{
+    bridge::BridgeEvent,
+    channels::ChannelSelection,
+    data::{to_amplitude, Chunk},
+    dsp::{fresh_frames, meter::to_db},
+    timeline::TimelineMarker,
+    Log,
+};
+
+enum Hit {
+    Trigger { level: f32 },
+    Alarm { level_db: f32, active: bool },
+}
+
+/// Live level trigger and peak alarm, reported to JavaScript as events.
+pub struct TriggerAnalyzer {
+    channels: ChannelSelection,
+    trigger: bool,
+    /// Amplitude the signal has to rise through, -1..1.
+    level: f32,
+    /// Crossings within this time after a trigger are ignored.
+    holdoff_ms: f32,
+    alarm: bool,
+    /// Peak level in dBFS that raises the alarm.
+    alarm_db: f32,
+    last_time: Option<f32>,
+    previous: f32,
+    /// Frames left until the trigger re-arms.
+    holdoff: usize,
+    alarm_active: bool,
+    /// Positions of the triggers, for the timeline.
+    fired: Vec<f32>,
+    /// Hits not yet reported, with their time in seconds from the session start.
+    hits: Vec<(f32, Hit)>,
+}
+
+impl Default for TriggerAnalyzer {
+    fn default() -> Self {
+        Self {
+            channels: ChannelSelection::default(),
+            trigger: false,
+            level: 0.5,
+            holdoff_ms: 100.0,
+            alarm: false,
+            alarm_db: -6.0,
+            last_time: None,
+            previous: 0.0,
+            holdoff: 0,
+            alarm_active: false,
+            fired: vec![],
+            hits: vec![],
+        }
+    }
+}
+
+impl Log for TriggerAnalyzer {
+    fn name() -> &'static str {
+        "Triggers"
+    }
+}
+
+impl TriggerAnalyzer {
+    const COLOR: Color32 = Color32::from_rgb(80, 200, 200);
+    /// The alarm clears once the peak falls this far below the threshold.
+    const HYSTERESIS_DB: f32 = 3.0;
+    const MAX_MARKERS: usize = 1000;
+
+    /// Fires on rising through `level`, or stops triggering for `None`.
+    pub fn set_trigger(&mut self, level: Option<f32>) {
+        self.trigger = level.is_some();
+        if let Some(level) = level {
+            self.level = level;
+        }
+    }
+
+    /// Raises the alarm above `threshold_db`, or turns it off for `None`.
+    pub fn set_alarm(&mut self, threshold_db: Option<f32>) {
+        self.alarm = threshold_db.is_some();
+        if let Some(threshold_db) = threshold_db {
+            self.alarm_db = threshold_db;
+        } else {
+            // re-enabling reports the level afresh
+            self.alarm_active = false;
+        }
+    }
+}
+
+impl Analyzer for TriggerAnalyzer {
+    fn name(&self) -> &'static str {
+        "Triggers & alarms"
+    }
+
+    fn clear(&mut self) {
+        self.last_time = None;
+        self.previous = 0.0;
+        self.holdoff = 0;
+        self.alarm_active = false;
+        self.fired.clear();
+        self.hits.clear();
+    }
+
+    fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        let frames = chunk.frames();
+        let channels = self.channels.active(chunk.channels);
+        let holdoff = (self.holdoff_ms / 1000.0 * sample_rate) as usize;
+        let mut peak = 0.0f32;
+        for frame in frames - fresh..frames {
+            let value = channels
+                .iter()
+                .map(|channel| to_amplitude(chunk.data[frame * chunk.channels + channel]))
+                .fold(0.0, |a: f32, b| if b.abs() > a.abs() { b } else { a });
+            peak = peak.max(value.abs());
+            self.holdoff = self.holdoff.saturating_sub(1);
+            if self.trigger
+                && self.holdoff == 0
+                && self.previous < self.level
+                && value >= self.level
+            {
+                let position = chunk.id as f32 + frame as f32 / frames.max(1) as f32;
+                // the chunk's time is where its last frame ends, as in `Stream::time`
+                let time_s = chunk.time / 1000.0 - (frames - frame) as f32 / sample_rate;
+                self.hits.push((time_s, Hit::Trigger { level: value }));
+                self.fired.push(position);
+                self.holdoff = holdoff;
+            }
+            self.previous = value;
+        }
+        if self.fired.len() > Self::MAX_MARKERS {
+            self.fired.drain(..self.fired.len() - Self::MAX_MARKERS);
+        }
+        let level_db = to_db(peak);
+        let active = if self.alarm_active {
+            level_db >= self.alarm_db - Self::HYSTERESIS_DB
+        } else {
+            level_db > self.alarm_db
+        };
+        if self.alarm && active != self.alarm_active {
+            Self::log(&format!("alarm {} at {:.1} dBFS", active, level_db));
+            self.hits
+                .push((chunk.time / 1000.0, Hit::Alarm { level_db, active }));
+            self.alarm_active = active;
+        }
+    }
+
+    fn take_events(&mut self) -> Vec<BridgeEvent> {
+        self.hits
+            .drain(..)
+            .map(|(time_s, hit)| match hit {
+                Hit::Trigger { level } => BridgeEvent::TriggerFired { time_s, level },
+                Hit::Alarm { level_db, active } => BridgeEvent::ThresholdAlarm {
+                    time_s,
+                    level_db,
+                    threshold_db: self.alarm_db,
+                    active,
+                },
+            })
+            .collect()
+    }
+
+    fn outputs(&self) -> Vec<AnalyzerOutput> {
+        vec![AnalyzerOutput::Markers {
+            label: "Triggers".to_owned(),
+            markers: self
+                .fired
+                .iter()
+                .map(|position| TimelineMarker {
+                    position: *position,
+                    color: Self::COLOR,
+                    label: None,
+                })
+                .collect(),
+        }]
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, channels: usize) {
+        ui.horizontal(|ui| {
+            self.channels.picker(ui, "trigger_channels", channels);
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.trigger, "Trigger");
+            ui.add(
+                egui::DragValue::new(&mut self.level)
+                    .range(-1.0..=1.0)
+                    .speed(0.01)
+                    .prefix("level "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.holdoff_ms)
+                    .range(0.0..=10_000.0)
+                    .prefix("holdoff ")
+                    .suffix(" ms"),
+            );
+            ui.monospace(format!("{} fired", self.fired.len()));
+        });
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.alarm, "Alarm").changed() && !self.alarm {
+                self.alarm_active = false;
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.alarm_db)
+                    .range(-60.0..=0.0)
+                    .speed(0.5)
+                    .prefix("peak > ")
+                    .suffix(" dBFS"),
+            );
+            if self.alarm && self.alarm_active {
+                ui.colored_label(ui.visuals().error_fg_color, "⚠ above threshold");
+            }
+        });
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -0,0 +1,275 @@
+//! User annotations: point markers and range regions with text, color and
+//! tags, and the CSV, JSON and Audacity label files they are exchanged in.
+
+use crate::summary::Timebase;
+
+/// A point marker or, with an `end`, a region of the session.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub id: u64,
+    /// Chunk id plus the fraction of the chunk, like `TimelineMarker::position`.
+    pub start: f32,
+    pub end: Option<f32>,
+    pub text: String,
+    pub color: [u8; 3],
+    pub tags: Vec<String>,
+}
+
+impl Annotation {
+    pub fn is_region(&self) -> bool {
+        self.end.is_some()
+    }
+
+    /// Converts chunk positions to seconds from the session start.
+    pub fn to_label(&self, timebase: &Timebase<'_>) -> Label {
+        Label {
+            start_s: timebase.seconds(self.start),
+            end_s: self.end.map(|end| timebase.seconds(end)),
+            text: self.text.clone(),
+            color: hex(self.color),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+/// An annotation as stored in files, timed in seconds from the session start.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Label {
+    pub start_s: f32,
+    #[serde(default)]
+    pub end_s: Option<f32>,
+    #[serde(default)]
+    pub text: String,
+    /// `#rrggbb`
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Label {
+    pub fn to_annotation(
+        &self,
+        id: u64,
+        timebase: &Timebase<'_>,
+        default_color: [u8; 3],
+    ) -> Annotation {
+        Annotation {
+            id,
+            start: timebase.position(self.start_s),
+            // Audacity writes points as zero-length regions
+            end: self
+                .end_s
+                .filter(|end| *end > self.start_s)
+                .map(|end| timebase.position(end)),
+            text: self.text.clone(),
+            color: parse_hex(&self.color).unwrap_or(default_color),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+pub fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+pub fn parse_hex(text: &str) -> Option<[u8; 3]> {
+    let digits = text.trim().strip_prefix('#')?;
+    if digits.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum LabelFormat {
+    Csv,
+    Json,
+    /// Tab-separated `start end text` lines, as Audacity's label track export.
+    Audacity,
+}
+
+impl LabelFormat {
+    pub const ALL: [LabelFormat; 3] = [Self::Csv, Self::Json, Self::Audacity];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Csv => "CSV",
+            Self::Json => "JSON",
+            Self::Audacity => "Audacity labels",
+        }
+    }
+
+    pub fn filename(&self) -> &'static str {
+        match self {
+            Self::Csv => "annotations.csv",
+            Self::Json => "annotations.json",
+            Self::Audacity => "labels.txt",
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Json => "application/json",
+            Self::Audacity => "text/plain",
+        }
+    }
+
+    /// File types offered by the picker on import.
+    pub fn accept(&self) -> &'static str {
+        match self {
+            Self::Csv => ".csv,text/csv",
+            Self::Json => ".json,application/json",
+            Self::Audacity => ".txt,text/plain",
+        }
+    }
+
+    pub fn write(&self, labels: &[Label]) -> String {
+        match self {
+            Self::Csv => write_csv(labels),
+            Self::Json => serde_json::to_string_pretty(labels).unwrap_or_default(),
+            Self::Audacity => write_audacity(labels),
+        }
+    }
+
+    pub fn read(&self, text: &str) -> Result<Vec<Label>, String> {
+        match self {
+            Self::Csv => read_csv(text),
+            Self::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            Self::Audacity => read_audacity(text),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "start_s,end_s,text,color,tags";
+
+fn csv_field(text: &str) -> String {
+    // labels are one line each
+    let text = text.replace(['\n', '\r'], " ");
+    if text.contains([',', '"']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+fn write_csv(labels: &[Label]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for label in labels {
+        csv.push_str(&format!(
+            "{:.4},{},{},{},{}\n",
+            label.start_s,
+            label
+                .end_s
+                .map_or(String::new(), |end| format!("{:.4}", end)),
+            csv_field(&label.text),
+            label.color,
+            csv_field(&label.tags.join(";")),
+        ));
+    }
+    csv
+}
+
+/// Splits one CSV line, honoring double-quoted fields.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn parse_seconds(text: &str, line: usize) -> Result<f32, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("line {}: bad time {:?}", line, text))
+}
+
+fn read_csv(text: &str) -> Result<Vec<Label>, String> {
+    let mut labels = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || (i == 0 && line.starts_with("start")) {
+            continue;
+        }
+        let fields = csv_fields(line);
+        let field = |n: usize| fields.get(n).map_or("", |f| f.as_str());
+        let end = field(1).trim();
+        labels.push(Label {
+            start_s: parse_seconds(field(0), i + 1)?,
+            end_s: if end.is_empty() {
+                None
+            } else {
+                Some(parse_seconds(end, i + 1)?)
+            },
+            text: field(2).to_owned(),
+            color: field(3).trim().to_owned(),
+            tags: split_tags(field(4), ';'),
+        });
+    }
+    Ok(labels)
+}
+
+pub fn split_tags(text: &str, separator: char) -> Vec<String> {
+    text.split(separator)
+        .map(|tag| tag.trim().to_owned())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Tags ride along in the label text as `#tag` words.
+fn write_audacity(labels: &[Label]) -> String {
+    let mut text = String::new();
+    for label in labels {
+        let mut name = label.text.replace(['\t', '\n', '\r'], " ");
+        for tag in &label.tags {
+            name.push_str(&format!(" #{}", tag.replace(char::is_whitespace, "_")));
+        }
+        text.push_str(&format!(
+            "{:.6}\t{:.6}\t{}\n",
+            label.start_s,
+            label.end_s.unwrap_or(label.start_s),
+            name.trim()
+        ));
+    }
+    text
+}
+
+fn read_audacity(text: &str) -> Result<Vec<Label>, String> {
+    let mut labels = vec![];
+    for (i, line) in text.lines().enumerate() {
+        // spectral selection lines start with a backslash
+        if line.trim().is_empty() || line.starts_with('\\') {
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let start_s = parse_seconds(fields.next().unwrap_or(""), i + 1)?;
+        let end_s = parse_seconds(fields.next().unwrap_or(""), i + 1)?;
+        let (tags, words): (Vec<&str>, Vec<&str>) = fields
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .partition(|word| word.len() > 1 && word.starts_with('#'));
+        labels.push(Label {
+            start_s,
+            end_s: Some(end_s),
+            text: words.join(" "),
+            color: String::new(),
+            tags: tags.iter().map(|tag| tag[1..].to_owned()).collect(),
+        });
+    }
+    Ok(labels)
+}
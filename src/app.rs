@@ -1,11 +1,35 @@
+use std::time::Duration;
+
 use eframe::App;
-use egui::{epaint::PathStroke, pos2, Color32, Frame, Pos2, Rect, Ui};
-use js_sys::Date;
+use egui::Ui;
 
 use crate::{
-    buffer::Buffer,
-    data::Chunk,
-    widgets::timeline::{Timeline, TimelineApi},
+    analyzers::{AnalyzerOutput, AnalyzerRegistry, SegmentAnalyzer, TriggerAnalyzer},
+    annotations::hex,
+    bridge::{BridgeEvent, Label, RangeQuery, Timebase},
+    buffer::{Buffer, RangeLoad},
+    capture::{CaptureSettings, InputDevice, SessionMetadata},
+    channels::{channel_name, ChannelSelection},
+    clock,
+    data::{to_amplitude, Chunk},
+    dsp::sweep::SweepSettings,
+    error::CaptureError,
+    remote::LiveMetrics,
+    settings::{InputSource, Settings, SettingsPanel},
+    timeline::{Timeline, TimelineEvent, TimelineLane, TimelineMarker, TimelineSource, View},
+    widgets::{
+        acoustics::AcousticsPanel,
+        annotations::AnnotationPanel,
+        diagnostics::DiagnosticsPanel,
+        distortion::DistortionPanel,
+        loudness::LoudnessPanel,
+        meter::LevelMeters,
+        overview::{Overview, OverviewApi},
+        pitch::PitchTrack,
+        remote::RemotePanel,
+        rta::RtaPanel,
+        sweep::SweepPanel,
+    },
     Log,
 };
 
@@ -23,77 +47,56 @@ pub struct TemplateApp {
     cursor_pos: usize,
     cursor_time: f64,
 
-    data: Option<Vec<u8>>,
+    /// Samples of the current view, one vector per channel.
+    data: Option<Vec<Vec<u8>>>,
+
+    channels: ChannelSelection,
+
+    capture: CaptureSettings,
+    #[serde(skip)]
+    capture_request: Option<CaptureSettings>,
+    #[serde(skip)]
+    input_devices: Vec<InputDevice>,
+    /// Why the last capture attempt failed.
+    #[serde(skip)]
+    capture_error: Option<CaptureError>,
+    session: SessionMetadata,
+    remote: RemotePanel,
+
+    pitch: PitchTrack,
+    loudness: LoudnessPanel,
+    meters: LevelMeters,
+    rta: RtaPanel,
+    distortion: DistortionPanel,
+    sweep: SweepPanel,
+    acoustics: AcousticsPanel,
+    annotations: AnnotationPanel,
+    diagnostics: DiagnosticsPanel,
+    timeline: Timeline,
+    #[serde(skip)]
+    analyzers: AnalyzerRegistry,
+    /// Waiting for `take_events`.
+    #[serde(skip)]
+    events: Vec<BridgeEvent>,
 
     recording_start_time: f64,
 
-    chunk_num: usize,
+    settings: Settings,
+    #[serde(skip)]
+    settings_panel: SettingsPanel,
+    #[serde(skip)]
+    show_settings: bool,
+
+    #[serde(skip)]
+    amplitude_hovered: bool,
+    /// Repainted when data arrives from outside the UI.
+    #[serde(skip)]
+    ctx: Option<egui::Context>,
 
     paused: bool, // This how you opt-out of serialization of a field
     value: f32,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
-struct RingBuffer {
-    pub head: usize,
-    pub size: usize,
-    buf: Vec<Vec<u8>>,
-}
-
-#[derive(serde::Deserialize, serde::Serialize, Debug)]
-pub struct View {
-    pub start: usize,
-    pub end: usize,
-}
-
-impl Default for RingBuffer {
-    fn default() -> Self {
-        let mut buf = vec![];
-        for _ in 0..Self::BUF_SIZE {
-            buf.push(vec![]);
-        }
-        Self {
-            head: 0,
-            size: 0,
-            buf,
-        }
-    }
-}
-
-impl RingBuffer {
-    const BUF_SIZE: usize = 20;
-    pub fn push(&mut self, data: Vec<u8>) {
-        let ind = if self.head == 0 {
-            Self::BUF_SIZE - 1
-        } else {
-            self.head
-        };
-        self.buf[ind] = data;
-        self.head += 1;
-        self.head %= Self::BUF_SIZE;
-    }
-    pub fn len(&self) -> usize {
-        Self::BUF_SIZE * self.size
-    }
-    pub fn set_size(&mut self, size: usize) {
-        self.size = size;
-    }
-    pub fn get(&mut self, ind: usize) -> u8 {
-        assert!(self.size > 0);
-        let buf_ind = ind / self.size;
-        let sub_ind = ind % self.size;
-        let buf_ind = (self.head + buf_ind) % Self::BUF_SIZE;
-        while buf_ind >= self.buf.len() {
-            self.buf.push(vec![]);
-        }
-        let selected_buf = &self.buf[buf_ind];
-        if selected_buf.len() == 0 {
-            return 128;
-        }
-        self.buf[buf_ind][sub_ind]
-    }
-}
-
 impl Default for TemplateApp {
     fn default() -> Self {
         Self {
@@ -104,8 +107,31 @@ impl Default for TemplateApp {
             cursor_pos: 1,
             recording_start_time: 0.0,
             cursor_time: 0.0,
-            chunk_num: 10,
+            settings: Settings::default(),
+            settings_panel: SettingsPanel::default(),
+            show_settings: false,
+            amplitude_hovered: false,
+            ctx: None,
             data: None,
+            channels: ChannelSelection::default(),
+            capture: CaptureSettings::default(),
+            capture_request: None,
+            input_devices: vec![],
+            capture_error: None,
+            session: SessionMetadata::default(),
+            remote: RemotePanel::default(),
+            pitch: PitchTrack::default(),
+            loudness: LoudnessPanel::default(),
+            meters: LevelMeters::default(),
+            rta: RtaPanel::default(),
+            distortion: DistortionPanel::default(),
+            sweep: SweepPanel::default(),
+            acoustics: AcousticsPanel::default(),
+            annotations: AnnotationPanel::default(),
+            diagnostics: DiagnosticsPanel::default(),
+            timeline: Timeline::default(),
+            analyzers: AnalyzerRegistry::default(),
+            events: vec![],
             value: 2.7,
             paused: true,
         }
@@ -115,9 +141,12 @@ impl Default for TemplateApp {
 impl App for TemplateApp {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        eframe::set_value(storage, Settings::STORAGE_KEY, &self.settings);
+        eframe::set_value(storage, CaptureSettings::STORAGE_KEY, &self.capture);
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let frame_start = clock::now_ms();
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
 
@@ -134,7 +163,23 @@ impl App for TemplateApp {
                 }
 
                 egui::widgets::global_dark_light_mode_buttons(ui);
+                ui.add_space(16.0);
+                ui.toggle_value(&mut self.show_settings, "Settings");
+            });
+        });
+
+        let mut show_settings = self.show_settings;
+        egui::Window::new("Settings")
+            .open(&mut show_settings)
+            .show(ctx, |ui| {
+                if let Some(settings) = self.settings_panel.ui(ui, &self.settings) {
+                    self.apply_settings(settings);
+                }
             });
+        self.show_settings = show_settings;
+
+        egui::SidePanel::right("meters_panel").show(ctx, |ui| {
+            self.meters.ui(ui);
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -153,11 +198,162 @@ impl App for TemplateApp {
 
             ui.separator();
 
+            self.error_ui(ui);
+
+            egui::CollapsingHeader::new("Input").show(ui, |ui| {
+                if self.capture.ui(ui, &self.input_devices, &self.session) {
+                    self.capture_request = Some(self.capture.clone());
+                }
+            });
+
+            egui::CollapsingHeader::new("WebSocket stream").show(ui, |ui| {
+                self.remote.ui(ui);
+            });
+            self.poll_remote();
+
             self.handle_input(ui);
 
-            let mut timeline = Timeline::new();
-            let body_rect = timeline.show(ui, self);
-            self.draw_line(ui, body_rect);
+            let peak = self.visible_peak();
+            self.timeline.amplitude.controls(ui, peak);
+            self.channels.ui(ui, self.buf.channels());
+
+            Overview::new().show(ui, self);
+
+            self.flush_data();
+            let mut timeline = std::mem::take(&mut self.timeline);
+            timeline.set_view(self.current_view());
+            timeline.set_cursor(self.cursor_position());
+            let outputs = self.analyzers.lanes();
+            let markers = AnalyzerOutput::Markers {
+                label: "Markers".to_owned(),
+                markers: self.markers(),
+            };
+            let mut lanes: Vec<&dyn TimelineLane> = vec![];
+            if self.pitch.show_lane {
+                lanes.push(&self.pitch);
+            }
+            if self.loudness.show_lane {
+                lanes.push(&self.loudness);
+            }
+            lanes.extend(outputs.iter().map(|output| output as &dyn TimelineLane));
+            lanes.push(&markers);
+            let response = timeline.show(ui, &*self, &lanes);
+            self.timeline = timeline;
+            self.amplitude_hovered = response.axis_hovered;
+            self.timeline.draw_regions(ui, &self.annotations.regions());
+            self.timeline.draw_markers(ui, &self.markers());
+            if let Some(position) = response.header_clicked {
+                self.annotations.mark(position);
+            }
+            for event in response.events {
+                match event {
+                    TimelineEvent::CursorMoved(position) => self.jump_to(position as usize),
+                    TimelineEvent::Zoomed(width) => {
+                        self.settings.view_width = width.clamp(2, self.settings.batch_size);
+                    }
+                    TimelineEvent::SelectionChanged(selection) => {
+                        Self::log(&format!("selection: {:?}", selection));
+                        let timebase = self.timebase();
+                        self.events
+                            .push(BridgeEvent::selection(selection, &timebase));
+                    }
+                }
+            }
+
+            let timebase = self.buf.summary().timebase(self.settings.chunk_period_ms());
+            self.annotations.poll(&timebase);
+            egui::CollapsingHeader::new("Annotations").show(ui, |ui| {
+                let cursor = self.cursor_position();
+                let timebase = self.buf.summary().timebase(self.settings.chunk_period_ms());
+                if let Some(position) = self.annotations.ui(ui, cursor, &timebase) {
+                    self.jump_to(position as usize);
+                }
+                if let Some((start, end)) = self.timeline.selection() {
+                    if ui.button("Add selection as region").clicked() {
+                        self.annotations.add_region(start, end);
+                    }
+                }
+            });
+            if let Some(annotations) = self.annotations.take_changed() {
+                self.buf.save_annotations(annotations);
+            }
+
+            egui::CollapsingHeader::new("Pitch").show(ui, |ui| {
+                self.pitch.controls(ui, self.buf.channels());
+            });
+            if !self.paused {
+                self.pitch.tuner(ui);
+            }
+
+            self.loudness.poll(self.sample_rate());
+            egui::CollapsingHeader::new("Loudness").show(ui, |ui| {
+                let load = Self::range_loader(&self.buf, self.current_view(), self.max_id);
+                self.loudness.ui(ui, self.buf.channels(), load);
+            });
+
+            self.rta.poll(self.sample_rate());
+            egui::CollapsingHeader::new("RTA").show(ui, |ui| {
+                let load = Self::range_loader(&self.buf, self.current_view(), self.max_id);
+                self.rta.ui(ui, self.buf.channels(), load);
+            });
+
+            self.distortion.poll(self.sample_rate());
+            egui::CollapsingHeader::new("Distortion").show(ui, |ui| {
+                let load = Self::range_loader(&self.buf, self.current_view(), self.max_id);
+                self.distortion.ui(ui, self.buf.channels(), load);
+            });
+
+            self.sweep.poll(self.sample_rate());
+            egui::CollapsingHeader::new("Frequency response").show(ui, |ui| {
+                let view = self.current_view();
+                let (buf, max_id) = (&self.buf, self.max_id);
+                self.sweep.ui(
+                    ui,
+                    buf.channels(),
+                    self.sample_rate(),
+                    max_id,
+                    !self.paused,
+                    |range| match range {
+                        Some((start, end)) => buf.load_range(start, end),
+                        None => buf.load_range(view.start, view.end),
+                    },
+                );
+            });
+
+            self.acoustics.poll(self.sample_rate());
+            egui::CollapsingHeader::new("Room acoustics").show(ui, |ui| {
+                let view = self.current_view();
+                let buf = &self.buf;
+                self.acoustics
+                    .ui(ui, buf.channels(), self.sweep.impulse(), || {
+                        buf.load_range(view.start, view.end)
+                    });
+            });
+
+            self.analyzers.poll(self.sample_rate());
+            egui::CollapsingHeader::new("Analyzers").show(ui, |ui| {
+                let load = Self::range_loader(&self.buf, self.current_view(), self.max_id);
+                self.analyzers.ui(ui, self.buf.channels(), load);
+            });
+            let split = self.analyzers.get_mut::<SegmentAnalyzer>();
+            if let Some(segments) = split.and_then(SegmentAnalyzer::take_split_request) {
+                self.annotations.add_segments(&segments);
+            }
+
+            egui::CollapsingHeader::new("Diagnostics").show(ui, |ui| {
+                let sample_rate = self.sample_rate();
+                let stats = self.buf.stats();
+                self.diagnostics.ui(ui, &self.settings, sample_rate, &stats);
+            });
+
+            // jobs don't wake the UI, so keep polling while one runs
+            if self.pitch.is_busy()
+                || self.loudness.is_busy()
+                || self.rta.is_busy()
+                || self.analyzers.is_busy()
+            {
+                ctx.request_repaint_after(Self::JOB_POLL);
+            }
 
             ui.add(egui::github_link_file!(
                 "https://github.com/emilk/eframe_template/blob/main/",
@@ -169,8 +365,10 @@ impl App for TemplateApp {
                 egui::warn_if_debug_build(ui);
             });
         });
+        self.diagnostics.frame(frame_start, clock::now_ms());
     }
 
+    #[cfg(target_arch = "wasm32")]
     fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
         Some(self)
     }
@@ -183,6 +381,9 @@ impl Log for TemplateApp {
 }
 
 impl TemplateApp {
+    /// How often background jobs are polled while they run.
+    const JOB_POLL: Duration = Duration::from_millis(50);
+
     /// Called once before the first frame.
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // This is also where you can customize the look and feel of egui using
@@ -194,119 +395,506 @@ impl TemplateApp {
         //     return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
         // }
 
-        Default::default()
+        let mut app = Self {
+            ctx: Some(cc.egui_ctx.clone()),
+            ..Self::default()
+        };
+        app.buf.set_repaint(cc.egui_ctx.clone());
+        // only user preferences are restored, the session itself lives in IndexedDB
+        if let Some(storage) = cc.storage {
+            if let Some(settings) = eframe::get_value::<Settings>(storage, Settings::STORAGE_KEY)
+                .filter(|s| s.validate().is_ok())
+            {
+                app.buf.set_page_size(settings.batch_size);
+                app.settings = settings;
+            }
+            if let Some(capture) = eframe::get_value(storage, CaptureSettings::STORAGE_KEY) {
+                app.capture = capture;
+            }
+        }
+        app
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Repaints after changes from outside the UI: new data, page loads,
+    /// JavaScript calls. Data usually arrives faster than it's worth drawing,
+    /// so frames are at least `Settings::frame_interval` apart.
+    fn request_frame(&self) {
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint_after(self.settings.frame_interval());
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings) {
+        if settings.needs_capture_restart(&self.settings) {
+            self.capture_request = Some(self.capture.clone());
+        }
+        self.buf.set_page_size(settings.batch_size);
+        self.settings = settings;
     }
 
     pub fn clear(&mut self) {
         self.max_id = 1;
         self.buf.clear();
+        self.pitch.clear();
+        self.loudness.clear();
+        self.meters.clear();
+        self.rta.clear();
+        self.distortion.clear();
+        self.sweep.clear();
+        self.acoustics.clear();
+        self.annotations.clear();
+        self.analyzers.clear();
+        self.diagnostics.clear();
+        self.events.clear();
+        self.request_frame();
     }
 
     pub fn is_paused(&self) -> bool {
         self.paused
     }
 
+    /// Starts or stops recording the captured audio.
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused == self.paused {
+            return;
+        }
+        self.paused = paused;
+        Self::log("paused changed");
+        if !self.paused {
+            self.recording_start_time = clock::now_ms();
+            Self::log(&format!("recording time: {}", self.recording_start_time));
+        }
+        self.request_frame();
+    }
+
+    pub fn capture_settings(&self) -> &CaptureSettings {
+        &self.capture
+    }
+
+    /// Sweep to play through the output, if one was requested.
+    pub fn take_sweep_request(&mut self) -> Option<SweepSettings> {
+        self.sweep.take_play_request()
+    }
+
+    /// Recorded audio to play through the output, with its sample rate.
+    pub fn take_playback_request(&mut self) -> Option<(Vec<f32>, f32)> {
+        self.analyzers
+            .get_mut::<SegmentAnalyzer>()
+            .and_then(SegmentAnalyzer::take_play_request)
+    }
+
+    /// Settings the user applied since the last call, if any.
+    pub fn take_capture_request(&mut self) -> Option<CaptureSettings> {
+        self.capture_request.take()
+    }
+
+    pub fn set_input_devices(&mut self, devices: Vec<InputDevice>) {
+        self.input_devices = devices;
+        self.request_frame();
+    }
+
+    pub fn set_capture_error(&mut self, error: Option<CaptureError>) {
+        self.capture_error = error;
+        self.request_frame();
+    }
+
+    pub fn set_session_metadata(&mut self, metadata: SessionMetadata) {
+        Self::log(&format!("capture started: {:?}", metadata));
+        self.buf.save_metadata(&metadata);
+        self.session = metadata;
+        self.request_frame();
+    }
+
     pub fn update(&mut self, data: &[u8]) {
+        self.update_interleaved(data, 1);
+    }
+
+    /// Pushes one block of `channels` interleaved channels.
+    pub fn update_interleaved(&mut self, data: &[u8], channels: usize) {
+        let chunk =
+            Chunk::new_interleaved(self.max_id, Vec::from(data), self.capture_time(), channels);
+        self.ingest(chunk, Some(self.settings.chunk_period_ms()));
+    }
+
+    /// Feeds a captured block to the level meters only, without recording
+    /// it; used while paused so the meters keep moving.
+    pub fn monitor_interleaved(&mut self, data: &[u8], channels: usize) {
+        let chunk =
+            Chunk::new_interleaved(self.max_id, Vec::from(data), self.capture_time(), channels);
+        self.meters.ingest(&chunk, self.sample_rate());
+        self.request_frame();
+    }
+
+    /// Time of a block captured now, in ms of recording.
+    fn capture_time(&self) -> f32 {
         // don't use Date::now(), use calculated time instead
-        let current_time = Date::now();
-        let time = current_time - self.recording_start_time + self.cursor_time;
+        let current_time = clock::now_ms();
         // Self::log(&format!(
         //     "current_time {}, update time {}",
         //     current_time, time
         // ));
-        self.buf
-            .push(Chunk::new(self.max_id, Vec::from(data), time as f32));
+        (current_time - self.recording_start_time + self.cursor_time) as f32
+    }
+
+    /// Connects the WebSocket stream client to `url`.
+    pub fn connect_remote(&mut self, url: &str, ctx: &egui::Context) {
+        self.remote.url = url.to_owned();
+        self.remote.connect(ctx);
+    }
+
+    /// Why the WebSocket stream ended, once it has.
+    pub fn remote_closed(&self) -> Option<&str> {
+        self.remote.closed()
+    }
+
+    /// Ingests the audio streamed since the last call like captured audio,
+    /// and returns the live metrics published meanwhile.
+    pub fn poll_remote(&mut self) -> Vec<LiveMetrics> {
+        let update = self.remote.poll(self.settings.refresh_rate as f32);
+        for block in update.blocks {
+            self.push_samples(
+                &block.samples,
+                block.channels,
+                block.sample_rate,
+                block.timestamp_ms,
+            );
+        }
+        update.metrics
+    }
+
+    /// Pushes a block of `channels` interleaved -1..1 samples from outside the
+    /// capture loop. `timestamp_ms` is the wall clock time of its first sample.
+    /// A block in another format than the session's starts a new session.
+    pub fn push_samples(
+        &mut self,
+        samples: &[f32],
+        channels: usize,
+        sample_rate: f32,
+        timestamp_ms: f64,
+    ) {
+        if self.session.sample_rate != sample_rate || self.session.channels != channels {
+            // a new format is a new session: its clock and ids start over
+            self.clear();
+            let metadata = SessionMetadata {
+                device_label: "external".to_owned(),
+                sample_rate,
+                channels,
+                started_at: timestamp_ms,
+                ..Default::default()
+            };
+            self.set_session_metadata(metadata);
+        }
+        let data = samples
+            .iter()
+            .map(|v| (v * 128.0 + 128.0).round().clamp(0.0, 255.0) as u8)
+            .collect();
+        // like captured chunks, pushed ones are timed by their last sample
+        let duration_ms = (samples.len() / channels.max(1)) as f64 / sample_rate as f64 * 1000.0;
+        let time = timestamp_ms + duration_ms - self.session.started_at;
+        let chunk = Chunk::new_interleaved(self.max_id, data, time as f32, channels.max(1));
+        self.ingest(chunk, None);
+    }
+
+    /// `poll_period` is how often the capture loop delivers chunks, `None`
+    /// for blocks pushed from outside it.
+    fn ingest(&mut self, chunk: Chunk, poll_period: Option<f32>) {
+        self.diagnostics
+            .ingest(&chunk, self.sample_rate(), poll_period);
+        self.pitch.ingest(&chunk, self.sample_rate());
+        self.loudness.ingest(&chunk, self.sample_rate());
+        self.meters.ingest(&chunk, self.sample_rate());
+        self.rta.ingest(&chunk, self.sample_rate());
+        self.distortion.ingest(&chunk, self.sample_rate());
+        self.analyzers.ingest(&chunk, self.sample_rate());
+        self.buf.push(chunk);
         self.buf.set_max_id(self.max_id);
         self.max_id += 1;
         self.cursor_pos = self.max_id;
+        self.request_frame();
+    }
+
+    /// Events for JavaScript since the last call.
+    pub fn take_events(&mut self) -> Vec<BridgeEvent> {
+        let mut events = std::mem::take(&mut self.events);
+        events.extend(self.analyzers.take_events());
+        events
+    }
+
+    /// Shows `start_s..end_s`, as far as the view width allows.
+    pub fn set_view(&mut self, start_s: f32, end_s: f32) {
+        let timebase = self.timebase();
+        let (start, end) = (timebase.position(start_s), timebase.position(end_s));
+        let width = (end - start).round() as usize;
+        self.settings.view_width = width.clamp(2, self.settings.batch_size);
+        self.jump_to(((start + end) / 2.0).max(1.0) as usize);
+        self.request_frame();
+    }
+
+    /// Moves the cursor, and with it the view, to `time_s`.
+    pub fn set_cursor(&mut self, time_s: f32) {
+        let position = self.timebase().position(time_s);
+        self.jump_to(position.max(1.0) as usize);
+        self.request_frame();
+    }
+
+    pub fn set_selection(&mut self, selection: Option<(f32, f32)>) {
+        let timebase = self.buf.summary().timebase(self.settings.chunk_period_ms());
+        self.timeline.set_selection(
+            selection.map(|(start, end)| (timebase.position(start), timebase.position(end))),
+        );
+        self.request_frame();
+    }
+
+    /// Adds a marker, or a region if the label has an end.
+    pub fn add_label(&mut self, label: &Label) {
+        let timebase = self.buf.summary().timebase(self.settings.chunk_period_ms());
+        self.annotations
+            .add_labels(std::slice::from_ref(label), &timebase);
+        self.request_frame();
+    }
+
+    pub fn set_trigger(&mut self, level: Option<f32>) {
+        if let Some(triggers) = self.analyzers.enable::<TriggerAnalyzer>() {
+            triggers.set_trigger(level);
+        }
+        self.request_frame();
+    }
+
+    pub fn set_alarm(&mut self, threshold_db: Option<f32>) {
+        if let Some(triggers) = self.analyzers.enable::<TriggerAnalyzer>() {
+            triggers.set_alarm(threshold_db);
+        }
+        self.request_frame();
+    }
+
+    /// Starts loading `start_s..end_s` for its samples and analysis.
+    pub fn query(&self, start_s: f32, end_s: f32) -> RangeQuery {
+        let timebase = self.timebase();
+        let (start, end) = (timebase.position(start_s), timebase.position(end_s));
+        let load = self
+            .buf
+            .load_range(start.max(1.0) as usize, end.max(1.0).ceil() as usize);
+        let mut markers = self.markers();
+        for output in self.analyzers.lanes() {
+            if let AnalyzerOutput::Markers { markers: found, .. } = output {
+                markers.extend(found);
+            }
+        }
+        let markers = markers
+            .into_iter()
+            .filter(|marker| (start..end).contains(&marker.position))
+            .map(|marker| Label {
+                start_s: timebase.seconds(marker.position),
+                end_s: None,
+                text: marker.label.unwrap_or_default(),
+                color: hex([marker.color.r(), marker.color.g(), marker.color.b()]),
+                tags: vec![],
+            })
+            .collect();
+        RangeQuery {
+            chunk_starts: (load.start..=load.end)
+                .map(|id| timebase.seconds(id as f32))
+                .collect(),
+            load,
+            start,
+            end,
+            start_s,
+            end_s,
+            sample_rate: self.sample_rate(),
+            loudness_channels: self.loudness.channels.clone(),
+            pitch: (self.pitch.yin.clone(), self.pitch.channels.clone()),
+            min_confidence: self.pitch.min_confidence,
+            markers,
+        }
+    }
+
+    /// Converts timeline positions to seconds from the session start and back.
+    fn timebase(&self) -> Timebase<'_> {
+        self.buf.summary().timebase(self.settings.chunk_period_ms())
+    }
+
+    /// Capture and storage failures, with what can be done about them.
+    fn error_ui(&mut self, ui: &mut Ui) {
+        if let Some(error) = &self.capture_error {
+            let (mut retry, mut test_signal) = (false, false);
+            ui.group(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+                ui.label(error.advice());
+                ui.horizontal(|ui| {
+                    retry = ui.button("Retry").clicked();
+                    if self.settings.source != InputSource::TestSine {
+                        test_signal = ui.button("Use test signal").clicked();
+                    }
+                });
+            });
+            if retry {
+                self.capture_error = None;
+                self.capture_request = Some(self.capture.clone());
+            }
+            if test_signal {
+                self.capture_error = None;
+                let settings = Settings {
+                    source: InputSource::TestSine,
+                    ..self.settings.clone()
+                };
+                self.apply_settings(settings);
+            }
+        }
+        if let Some(error) = self.buf.storage_error() {
+            ui.group(|ui| {
+                ui.colored_label(ui.visuals().warn_fg_color, error.to_string());
+                ui.label(error.advice());
+                if ui.button("Retry storage").clicked() {
+                    self.buf.retry_storage();
+                }
+            });
+        }
     }
 
     fn handle_input(&mut self, ui: &mut Ui) {
+        if let Some(position) = self.annotations.handle_keys(ui, self.cursor_position()) {
+            self.jump_to(position as usize);
+        }
         ui.input(|i| {
             if i.key_pressed(egui::Key::Space) {
-                self.paused = !self.paused;
-                Self::log("paused changed");
-                if !self.paused {
-                    self.recording_start_time = Date::now();
-                    Self::log(&format!("recording time: {}", self.recording_start_time));
-                }
+                self.set_paused(!self.paused);
             }
-            if self.paused {
+            if self.paused && !self.amplitude_hovered {
                 let raw_scroll_value = i.raw_scroll_delta.y;
                 if raw_scroll_value < 0.0 {
                     self.cursor_pos = self.max_id.min(self.cursor_pos + 1);
                 } else if raw_scroll_value > 0.0 {
-                    self.cursor_pos = self.cursor_pos.checked_sub(1).unwrap_or(0);
+                    self.cursor_pos = self.cursor_pos.saturating_sub(1);
+                }
+                if raw_scroll_value != 0.0 {
+                    let forward = raw_scroll_value < 0.0;
+                    let cursor = self.cursor_position();
+                    let segments = self.analyzers.get::<SegmentAnalyzer>();
+                    if let Some(position) = segments.and_then(|s| s.skip(cursor, forward)) {
+                        self.jump_to(position as usize);
+                    }
                 }
             }
         });
     }
 
-    fn draw_line(&mut self, ui: &mut Ui, rect: Rect) {
-        let color = if ui.visuals().dark_mode {
-            Color32::from_additive_luminance(196)
-        } else {
-            Color32::from_black_alpha(240)
-        };
-        Frame::canvas(ui.style()).show(ui, |ui| {
-            ui.ctx().request_repaint();
-            let to_screen = egui::emath::RectTransform::from_to(
-                Rect::from_x_y_ranges(0.0..=1.0, -1.0..=1.0),
-                rect,
-            );
-            if let Some(data) = &self.data {
-                let n = data.len();
-                let mut shapes = vec![];
-                let points: Vec<Pos2> = (0..n)
-                    .map(|i| {
-                        let t = i as f64 / (n as f64);
-                        let y = (data[i] as f64 - 128.0) / 128.0;
-                        to_screen * pos2(t as f32, y as f32)
-                    })
-                    .collect();
-                shapes.push(egui::epaint::Shape::line(
-                    points,
-                    PathStroke::new(2.0, color),
-                ));
-                let bar = [to_screen * pos2(0.5, -1.0), to_screen * pos2(0.5, 1.0)];
-                shapes.push(egui::epaint::Shape::line_segment(
-                    bar,
-                    PathStroke::new(1.0, color),
-                ));
-                ui.painter().extend(shapes);
+    /// Loads the current view's samples and keeps the pitch lane up to date
+    /// in the background.
+    fn flush_data(&mut self) {
+        let view = self.current_view();
+        let data = self.buf.get_data(&view);
+        self.data = Some(data);
+        self.pitch.poll();
+        if self.pitch.show_lane {
+            let chunks = self.buf.get_chunks(&view);
+            self.pitch.analyze(&chunks, self.sample_rate());
+        }
+    }
+
+    /// Loads the whole session when asked for `true`, else `view`, for the
+    /// panels' "Analyze view" and "Analyze session" buttons.
+    fn range_loader(buf: &Buffer, view: View, max_id: usize) -> impl Fn(bool) -> RangeLoad + '_ {
+        move |session| {
+            if session {
+                buf.load_range(1, max_id)
+            } else {
+                buf.load_range(view.start, view.end)
             }
-        });
+        }
+    }
+
+    /// The user's annotations to draw over the timeline; analyzer markers get lanes.
+    fn markers(&self) -> Vec<TimelineMarker> {
+        self.annotations.markers()
+    }
+
+    /// Sample rate of the running session, or a typical default before capture started.
+    fn sample_rate(&self) -> f32 {
+        if self.session.sample_rate > 0.0 {
+            self.session.sample_rate
+        } else {
+            48000.0
+        }
+    }
+
+    /// Position of the cursor bar, in the units of `TimelineMarker::position`.
+    fn cursor_position(&self) -> f32 {
+        let view = self.current_view();
+        view.start as f32 + (view.end - view.start + 1) as f32 / 2.0
+    }
+
+    fn visible_peak(&self) -> f32 {
+        self.data.as_ref().map_or(0.0, |data| {
+            self.channels
+                .active(data.len())
+                .into_iter()
+                .flat_map(|channel| data[channel].iter())
+                .map(|v| to_amplitude(*v).abs())
+                .fold(0.0, f32::max)
+        })
     }
 
     fn current_view(&self) -> View {
-        if self.cursor_pos < self.chunk_num + 1 {
+        let width = self.settings.view_width;
+        if self.cursor_pos < width + 1 {
             View {
                 start: 1,
-                end: self.chunk_num,
+                end: width,
             }
         } else {
             View {
-                start: self.cursor_pos - self.chunk_num,
+                start: self.cursor_pos - width,
                 end: self.cursor_pos - 1,
             }
         }
     }
 }
 
-impl TimelineApi for TemplateApp {
-    fn flush_data(&mut self) {
-        let data = self.buf.get_data(&self.current_view());
-        self.data = Some(data);
+impl TimelineSource for TemplateApp {
+    fn chunk_period_ms(&self) -> f32 {
+        self.settings.chunk_period_ms()
     }
 
-    fn time_range_span(&self) -> f32 {
-        self.chunk_num as f32 * 16.67
+    fn channels(&self) -> Vec<usize> {
+        self.channels
+            .active(self.data.as_ref().map_or(0, |data| data.len()))
     }
 
-    fn get_time_range(&self) -> (f32, f32) {
+    /// `data` is always loaded for the current view by `flush_data`.
+    fn samples(&self, _view: &View, channel: usize) -> Vec<f32> {
+        self.data.as_ref().map_or(vec![], |data| {
+            data[channel].iter().map(|v| to_amplitude(*v)).collect()
+        })
+    }
+
+    fn channel_name(&self, channel: usize) -> String {
+        channel_name(channel)
+    }
+}
+
+impl OverviewApi for TemplateApp {
+    fn envelope(&self, buckets: usize) -> Vec<(f32, f32)> {
+        let channels = self.channels.active(self.buf.channels());
+        self.buf.summary().envelope(buckets, &channels)
+    }
+
+    fn session_range(&self) -> Option<(usize, usize)> {
+        self.buf.summary().id_range()
+    }
+
+    fn view_range(&self) -> (usize, usize) {
         let view = self.current_view();
-        let (start, end) = ((view.start - 1) as f32, (view.end - 1) as f32);
-        (start * 17.0, end * 17.0)
+        (view.start, view.end)
+    }
+
+    fn jump_to(&mut self, id: usize) {
+        let width = self.settings.view_width;
+        let cursor_pos = id + width / 2 + 1;
+        self.cursor_pos = cursor_pos.clamp(width + 1, self.max_id.max(width + 1));
     }
 }
 
@@ -0,0 +1,628 @@
+//! Headless analysis for scripts and CI: reads WAV files or PCM on stdin,
+//! runs the analyzers of the GUI and prints a JSON or CSV report. Exits
+//! with 1 when a configured threshold is exceeded, 2 on bad input.
+//!
+//! With `--connect` it is a WebSocket client instead, metering a live PCM
+//! stream as described in `eframe_template::remote`.
+
+use std::{io::Read, process::ExitCode};
+
+use eframe_template::dsp::{
+    loudness::LoudnessMeter,
+    pitch::{note, Yin},
+    spectrum::{average_spectrum, peaks, SpectrumPeak},
+    stats::{stats, SignalStats},
+    vad::{self, VadMethod, VadSettings},
+    wav::{self, RawFormat, Wav},
+};
+
+const USAGE: &str = "\
+Usage: analyze [OPTIONS] [FILE.wav ...]
+
+Reads stdin when no file (or `-`) is given: a WAV stream, or headerless
+PCM with --raw.
+
+Input:
+  --raw FORMAT           stdin is raw PCM: u8, s16le, s24le, s32le, f32le, f64le
+  --rate HZ              sample rate of raw PCM [48000]
+  --channels N           channels of raw PCM [1]
+Analysis:
+  --peaks N              spectrum peaks to report [5]
+  --fft N                spectrum frame size, a power of two [8192]
+  --vad level|spectral   silence detector [level]
+  --silence-threshold DB level gate threshold in dBFS [-45]
+Streaming:
+  --connect URL          meter framed PCM from a WebSocket server, printing
+                         the live metrics as JSON lines and sending them back
+Output:
+  --format json|csv      report format [json]
+  --help                 print this help
+Thresholds (exit code 1 when exceeded):
+  --max-peak DBFS        sample peak of any channel
+  --max-true-peak DBTP
+  --max-clipped N        clipped samples over all channels
+  --min-loudness LUFS    integrated loudness
+  --max-loudness LUFS
+  --max-silence PERCENT  share of the signal without activity
+";
+
+#[derive(Default)]
+struct Thresholds {
+    max_peak: Option<f32>,
+    max_true_peak: Option<f32>,
+    max_clipped: Option<usize>,
+    min_loudness: Option<f32>,
+    max_loudness: Option<f32>,
+    max_silence: Option<f32>,
+}
+
+struct Options {
+    files: Vec<String>,
+    raw: Option<RawFormat>,
+    rate: f32,
+    channels: usize,
+    peaks: usize,
+    fft: usize,
+    vad: VadSettings,
+    csv: bool,
+    thresholds: Thresholds,
+    connect: Option<String>,
+    help: bool,
+}
+
+fn number<T: std::str::FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: not a number: {}", arg, value))
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            files: vec![],
+            raw: None,
+            rate: 48000.0,
+            channels: 1,
+            peaks: 5,
+            fft: 8192,
+            vad: VadSettings::default(),
+            csv: false,
+            thresholds: Thresholds::default(),
+            connect: None,
+            help: false,
+        };
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                options.files.push(arg);
+                continue;
+            }
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            let t = &mut options.thresholds;
+            match arg.as_str() {
+                "--help" => options.help = true,
+                "--raw" => {
+                    let name = value()?;
+                    options.raw = Some(
+                        RawFormat::from_name(&name)
+                            .ok_or(format!("unknown raw format {}", name))?,
+                    );
+                }
+                "--rate" => {
+                    options.rate = number(&arg, value()?)?;
+                    if !(options.rate.is_finite() && options.rate > 0.0) {
+                        return Err(format!("--rate must be positive, not {}", options.rate));
+                    }
+                }
+                "--channels" => {
+                    options.channels = number(&arg, value()?)?;
+                    if options.channels == 0 {
+                        return Err("--channels must be at least 1".to_owned());
+                    }
+                }
+                "--peaks" => options.peaks = number(&arg, value()?)?,
+                "--fft" => {
+                    options.fft = number(&arg, value()?)?;
+                    if options.fft < 2 || !options.fft.is_power_of_two() {
+                        return Err(format!(
+                            "--fft must be a power of two of at least 2, not {}",
+                            options.fft
+                        ));
+                    }
+                }
+                "--vad" => {
+                    options.vad.method = match value()?.as_str() {
+                        "level" => VadMethod::Level,
+                        "spectral" => VadMethod::Spectral,
+                        other => return Err(format!("unknown detector {}", other)),
+                    }
+                }
+                "--connect" => options.connect = Some(value()?),
+                "--silence-threshold" => options.vad.threshold_db = number(&arg, value()?)?,
+                "--format" => {
+                    options.csv = match value()?.as_str() {
+                        "json" => false,
+                        "csv" => true,
+                        other => return Err(format!("unknown format {}", other)),
+                    }
+                }
+                "--max-peak" => t.max_peak = Some(number(&arg, value()?)?),
+                "--max-true-peak" => t.max_true_peak = Some(number(&arg, value()?)?),
+                "--max-clipped" => t.max_clipped = Some(number(&arg, value()?)?),
+                "--min-loudness" => t.min_loudness = Some(number(&arg, value()?)?),
+                "--max-loudness" => t.max_loudness = Some(number(&arg, value()?)?),
+                "--max-silence" => t.max_silence = Some(number(&arg, value()?)?),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+        if options.files.is_empty() {
+            options.files.push("-".to_owned());
+        }
+        Ok(options)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct LoudnessSummary {
+    integrated_lufs: Option<f32>,
+    loudness_range_lu: Option<f32>,
+    max_momentary_lufs: Option<f32>,
+    max_short_term_lufs: Option<f32>,
+    true_peak_dbtp: Option<f32>,
+}
+
+#[derive(serde::Serialize)]
+struct PitchSummary {
+    frames: usize,
+    voiced_frames: usize,
+    median_hz: Option<f32>,
+    median_note: Option<String>,
+    min_hz: Option<f32>,
+    max_hz: Option<f32>,
+}
+
+#[derive(serde::Serialize)]
+struct Region {
+    start_s: f32,
+    end_s: f32,
+}
+
+#[derive(serde::Serialize)]
+struct SilenceSummary {
+    detector: &'static str,
+    active_percent: f32,
+    silence_percent: f32,
+    regions: Vec<Region>,
+}
+
+#[derive(serde::Serialize)]
+struct Report {
+    file: String,
+    sample_rate: f32,
+    channels: usize,
+    duration_s: f32,
+    stats: Vec<SignalStats>,
+    spectrum_peaks: Vec<SpectrumPeak>,
+    loudness: LoudnessSummary,
+    pitch: PitchSummary,
+    silence: SilenceSummary,
+    violations: Vec<String>,
+}
+
+fn read_input(file: &str, options: &Options) -> Result<Wav, String> {
+    let mut bytes = vec![];
+    if file == "-" {
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("stdin: {}", e))?;
+    } else {
+        bytes = std::fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+    }
+    match options.raw {
+        Some(format) if file == "-" => {
+            wav::parse_raw(&bytes, format, options.channels, options.rate)
+        }
+        _ => wav::parse(&bytes),
+    }
+    .map_err(|e| format!("{}: {}", file, e))
+}
+
+fn pitch_summary(mono: &[f32], sample_rate: f32) -> PitchSummary {
+    const FRAME: usize = 2048;
+    let yin = Yin::default();
+    let mut frames = 0;
+    let mut voiced: Vec<f32> = mono
+        .chunks_exact(FRAME)
+        .inspect(|_| frames += 1)
+        .filter_map(|frame| yin.estimate(frame, sample_rate))
+        .map(|estimate| estimate.frequency)
+        .collect();
+    voiced.sort_by(f32::total_cmp);
+    let median = voiced.get(voiced.len() / 2).copied();
+    PitchSummary {
+        frames,
+        voiced_frames: voiced.len(),
+        median_hz: median,
+        median_note: median.map(|f| note(f).0),
+        min_hz: voiced.first().copied(),
+        max_hz: voiced.last().copied(),
+    }
+}
+
+fn analyze(file: &str, wav: &Wav, options: &Options) -> Report {
+    let sample_rate = wav.sample_rate;
+    let mono = wav.mono();
+    let stats: Vec<SignalStats> = wav.channels.iter().map(|c| stats(c)).collect();
+
+    let spectrum = average_spectrum(&mono, options.fft);
+    let bin_hz = sample_rate / options.fft as f32;
+    let spectrum_peaks = peaks(&spectrum, bin_hz, options.peaks, -100.0);
+
+    let mut meter = LoudnessMeter::new(sample_rate, wav.channels.len());
+    meter.process(&wav.channels);
+    let loudness = meter.report();
+
+    let activity = vad::analyze(&mono, sample_rate, &options.vad);
+    let active: usize = activity
+        .regions
+        .iter()
+        .map(|(start, end)| end - start)
+        .sum();
+    let active_percent = 100.0 * active as f32 / mono.len().max(1) as f32;
+
+    let mut report = Report {
+        file: file.to_owned(),
+        sample_rate,
+        channels: wav.channels.len(),
+        duration_s: wav.frames() as f32 / sample_rate,
+        stats,
+        spectrum_peaks,
+        loudness: LoudnessSummary {
+            integrated_lufs: loudness.integrated,
+            loudness_range_lu: loudness.loudness_range,
+            max_momentary_lufs: loudness.max_momentary,
+            max_short_term_lufs: loudness.max_short_term,
+            true_peak_dbtp: loudness.true_peak,
+        },
+        pitch: pitch_summary(&mono, sample_rate),
+        silence: SilenceSummary {
+            detector: options.vad.method.label(),
+            active_percent,
+            silence_percent: 100.0 - active_percent,
+            regions: activity
+                .regions
+                .iter()
+                .map(|(start, end)| Region {
+                    start_s: *start as f32 / sample_rate,
+                    end_s: *end as f32 / sample_rate,
+                })
+                .collect(),
+        },
+        violations: vec![],
+    };
+    report.violations = violations(&report, &options.thresholds);
+    report
+}
+
+fn violations(report: &Report, t: &Thresholds) -> Vec<String> {
+    let mut violations = vec![];
+    let peak = report
+        .stats
+        .iter()
+        .map(|s| s.peak_dbfs)
+        .fold(f32::NEG_INFINITY, f32::max);
+    if let Some(max) = t.max_peak.filter(|max| peak > *max) {
+        violations.push(format!("peak {:.2} dBFS above {} dBFS", peak, max));
+    }
+    let true_peak = report.loudness.true_peak_dbtp;
+    if let (Some(max), Some(value)) = (t.max_true_peak, true_peak) {
+        if value > max {
+            violations.push(format!("true peak {:.2} dBTP above {} dBTP", value, max));
+        }
+    }
+    let clipped: usize = report.stats.iter().map(|s| s.clipped_samples).sum();
+    if let Some(max) = t.max_clipped.filter(|max| clipped > *max) {
+        violations.push(format!("{} clipped samples, more than {}", clipped, max));
+    }
+    let integrated = report.loudness.integrated_lufs;
+    if t.min_loudness.is_some() || t.max_loudness.is_some() {
+        match integrated {
+            None => violations.push("integrated loudness unavailable".to_owned()),
+            Some(value) => {
+                if let Some(min) = t.min_loudness.filter(|min| value < *min) {
+                    violations.push(format!("loudness {:.1} LUFS below {} LUFS", value, min));
+                }
+                if let Some(max) = t.max_loudness.filter(|max| value > *max) {
+                    violations.push(format!("loudness {:.1} LUFS above {} LUFS", value, max));
+                }
+            }
+        }
+    }
+    let silence = report.silence.silence_percent;
+    if let Some(max) = t.max_silence.filter(|max| silence > *max) {
+        violations.push(format!("{:.1}% silence, more than {}%", silence, max));
+    }
+    violations
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn to_csv(reports: &[Report]) -> String {
+    let optional = |v: Option<f32>| v.map_or(String::new(), |v| format!("{:.2}", v));
+    let mut csv = "file,sample_rate,channels,duration_s,peak_dbfs,rms_dbfs,clipped_samples,\
+                   integrated_lufs,loudness_range_lu,true_peak_dbtp,pitch_median_hz,\
+                   active_percent,top_peak_hz,top_peak_db,violations\n"
+        .to_owned();
+    for r in reports {
+        let peak = r
+            .stats
+            .iter()
+            .map(|s| s.peak_dbfs)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let rms = r
+            .stats
+            .iter()
+            .map(|s| s.rms_dbfs)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let top = r.spectrum_peaks.first();
+        csv.push_str(&format!(
+            "{},{},{},{:.3},{:.2},{:.2},{},{},{},{},{},{:.1},{},{},{}\n",
+            csv_field(&r.file),
+            r.sample_rate,
+            r.channels,
+            r.duration_s,
+            peak,
+            rms,
+            r.stats.iter().map(|s| s.clipped_samples).sum::<usize>(),
+            optional(r.loudness.integrated_lufs),
+            optional(r.loudness.loudness_range_lu),
+            optional(r.loudness.true_peak_dbtp),
+            optional(r.pitch.median_hz),
+            r.silence.active_percent,
+            optional(top.map(|p| p.frequency)),
+            optional(top.map(|p| p.level_db)),
+            csv_field(&r.violations.join("; ")),
+        ));
+    }
+    csv
+}
+
+/// Meters the stream of `url` through the app's ingest path until the
+/// server closes it.
+#[cfg(not(target_arch = "wasm32"))]
+fn connect(url: &str) -> Result<(), String> {
+    use eframe_template::TemplateApp;
+
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+    let mut app = TemplateApp::default();
+    app.connect_remote(url, &egui::Context::default());
+    let mut received = false;
+    loop {
+        for metrics in app.poll_remote() {
+            received = true;
+            let json = serde_json::to_string(&metrics).map_err(|e| e.to_string())?;
+            println!("{}", json);
+        }
+        if let Some(reason) = app.remote_closed() {
+            return match received {
+                true => Ok(()),
+                false => Err(format!("{}: {}", url, reason)),
+            };
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn connect(_url: &str) -> Result<(), String> {
+    Err("--connect needs a native build".to_owned())
+}
+
+fn main() -> ExitCode {
+    #[cfg(not(target_arch = "wasm32"))]
+    env_logger::init();
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("error: {}\n", error);
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if let Some(url) = &options.connect {
+        return match connect(url) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                ExitCode::from(2)
+            }
+        };
+    }
+    let mut reports = vec![];
+    for file in &options.files {
+        match read_input(file, &options) {
+            Ok(wav) => reports.push(analyze(file, &wav, &options)),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if options.csv {
+        print!("{}", to_csv(&reports));
+    } else {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let mut failed = false;
+    for report in &reports {
+        for violation in &report.violations {
+            eprintln!("{}: {}", report.file, violation);
+            failed = true;
+        }
+    }
+    ExitCode::from(failed as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Stereo 1 kHz sine at `amplitude` for `seconds`, then `silence` seconds.
+    fn tone(amplitude: f32, seconds: f32, silence: f32) -> Wav {
+        let channel: Vec<f32> = (0..((seconds + silence) * SAMPLE_RATE) as usize)
+            .map(|n| {
+                let t = n as f32 / SAMPLE_RATE;
+                match t < seconds {
+                    true => amplitude * (2.0 * std::f32::consts::PI * 1000.0 * t).sin(),
+                    false => 0.0,
+                }
+            })
+            .collect();
+        Wav {
+            sample_rate: SAMPLE_RATE,
+            channels: vec![channel.clone(), channel],
+        }
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse(&[
+            "--raw",
+            "s24le",
+            "--rate",
+            "44100",
+            "--channels",
+            "2",
+            "--fft",
+            "1024",
+            "--vad",
+            "spectral",
+            "--format",
+            "csv",
+            "--max-peak",
+            "-1",
+            "--max-clipped",
+            "0",
+            "a.wav",
+            "b.wav",
+        ])
+        .unwrap();
+        assert_eq!(options.raw, Some(RawFormat::S24));
+        assert_eq!(options.rate, 44100.0);
+        assert_eq!(options.channels, 2);
+        assert_eq!(options.fft, 1024);
+        assert_eq!(options.vad.method, VadMethod::Spectral);
+        assert!(options.csv);
+        assert_eq!(options.thresholds.max_peak, Some(-1.0));
+        assert_eq!(options.thresholds.max_clipped, Some(0));
+        assert_eq!(options.thresholds.max_loudness, None);
+        assert_eq!(options.files, ["a.wav", "b.wav"]);
+        assert!(!options.help);
+
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.files, ["-"]);
+        assert!(parse(&["--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        for args in [
+            &["--rate", "0"][..],
+            &["--rate", "-44100"],
+            &["--rate", "NaN"],
+            &["--channels", "0"],
+            &["--channels", "two"],
+            &["--fft", "1000"],
+            &["--raw", "s12le"],
+            &["--vad", "neural"],
+            &["--format", "xml"],
+            &["--max-peak"],
+            &["--verbose"],
+        ] {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn reports_exceeded_thresholds() {
+        // about -3 dBFS peak, -6 LUFS and 25 % silence
+        let wav = tone(0.7, 3.0, 1.0);
+        let options = parse(&["--max-peak", "0", "--max-silence", "50"]).unwrap();
+        assert!(analyze("tone.wav", &wav, &options).violations.is_empty());
+
+        let options = parse(&[
+            "--max-peak",
+            "-6",
+            "--max-true-peak",
+            "-6",
+            "--max-loudness",
+            "-23",
+            "--max-silence",
+            "10",
+        ])
+        .unwrap();
+        let report = analyze("tone.wav", &wav, &options);
+        assert_eq!(report.violations.len(), 4, "{:?}", report.violations);
+        assert!(report.violations[0].starts_with("peak -3.10 dBFS above -6"));
+        assert!(report.violations[3].contains("% silence, more than 10%"));
+
+        let options = parse(&["--max-clipped", "10", "--min-loudness", "10"]).unwrap();
+        let report = analyze("loud.wav", &tone(1.5, 1.0, 0.0), &options);
+        assert!(report.violations[0].ends_with("clipped samples, more than 10"));
+        assert!(report.violations[1].ends_with("below 10 LUFS"));
+
+        let report = analyze("silence.wav", &tone(0.0, 0.0, 1.0), &options);
+        assert_eq!(report.violations, ["integrated loudness unavailable"]);
+    }
+
+    #[test]
+    fn writes_csv_and_json_reports() {
+        let options = parse(&["--max-peak", "-6"]).unwrap();
+        let reports = [
+            analyze("tone.wav", &tone(0.7, 1.0, 0.0), &options),
+            analyze("with, comma.wav", &tone(0.0, 0.0, 1.0), &options),
+        ];
+        let csv = to_csv(&reports);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("file,sample_rate,channels,duration_s,peak_dbfs,"));
+        assert!(lines[1].starts_with("tone.wav,48000,2,1.000,-3.10,-6.11,0,"));
+        assert!(lines[1].ends_with(",100.0,999.98,-3.08,peak -3.10 dBFS above -6 dBFS"));
+        // silence has no loudness, pitch or spectrum peak
+        assert_eq!(
+            lines[2],
+            "\"with, comma.wav\",48000,2,1.000,-120.00,-120.00,0,,,-200.00,,0.0,,,"
+        );
+
+        let json = serde_json::to_value(&reports).unwrap();
+        assert_eq!(json[0]["file"], "tone.wav");
+        assert_eq!(json[0]["channels"], 2);
+        assert_eq!(json[0]["stats"].as_array().unwrap().len(), 2);
+        assert!(json[0]["pitch"]["median_hz"].as_f64().unwrap() > 990.0);
+        assert_eq!(json[0]["pitch"]["median_note"], "B5");
+        assert!(json[1]["loudness"]["integrated_lufs"].is_null());
+        assert_eq!(json[1]["violations"].as_array().unwrap().len(), 0);
+    }
+}
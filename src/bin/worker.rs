@@ -0,0 +1,48 @@
+//! Web Worker that runs background analysis jobs for the web app, see
+//! `eframe_template::jobs`. Trunk builds it next to the app.
+//!
+//! Natively it runs one job on a thread the way the app does: it reads a
+//! `JobRequest` as JSON on stdin and prints every `JobUpdate` as a JSON line.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    eframe_template::jobs::run_worker();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::process::ExitCode {
+    use std::{io::Read, process::ExitCode, thread, time::Duration};
+
+    use eframe_template::jobs::{JobHandle, JobRequest, JobUpdate};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("error: {}", e);
+        return ExitCode::from(2);
+    }
+    let request: JobRequest = match serde_json::from_str(&input) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("error: not a job request: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let mut job = JobHandle::spawn(request);
+    let mut failed = false;
+    while !job.is_finished() {
+        for update in job.poll() {
+            failed |= matches!(update, JobUpdate::Failed(_));
+            match serde_json::to_string(&update) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("error: {}", e),
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
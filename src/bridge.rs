@@ -0,0 +1,147 @@
+//! What the page embedding the analyzer exchanges with it through `WebHandle`:
+//! events for JavaScript callbacks and range queries. Times are seconds from
+//! the session start, like the annotation label files.
+
+use crate::{
+    buffer::RangeLoad,
+    channels::ChannelSelection,
+    dsp::{
+        loudness::LoudnessReport,
+        mixdown,
+        pitch::Yin,
+        stats::{stats, SignalStats},
+        Stream,
+    },
+    widgets::loudness::measure,
+};
+
+pub use crate::{annotations::Label, summary::Timebase};
+
+/// Something JavaScript can subscribe to with `WebHandle::on`.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeEvent {
+    /// The user selected a range on the timeline, or cleared it.
+    SelectionChanged {
+        start_s: Option<f32>,
+        end_s: Option<f32>,
+    },
+    /// The signal rose through the trigger level.
+    TriggerFired { time_s: f32, level: f32 },
+    /// The peak level went above the alarm threshold, or fell back below it.
+    ThresholdAlarm {
+        time_s: f32,
+        level_db: f32,
+        threshold_db: f32,
+        active: bool,
+    },
+}
+
+impl BridgeEvent {
+    /// Name callbacks are registered under.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SelectionChanged { .. } => "selection",
+            Self::TriggerFired { .. } => "trigger",
+            Self::ThresholdAlarm { .. } => "alarm",
+        }
+    }
+
+    pub fn selection(selection: Option<(f32, f32)>, timebase: &Timebase<'_>) -> Self {
+        Self::SelectionChanged {
+            start_s: selection.map(|(start, _)| timebase.seconds(start)),
+            end_s: selection.map(|(_, end)| timebase.seconds(end)),
+        }
+    }
+}
+
+/// Pitch of one chunk in a range query.
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+pub struct PitchPoint {
+    pub time_s: f32,
+    pub frequency: f32,
+    pub confidence: f32,
+}
+
+/// Analysis of a time range, serialized for JavaScript.
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct RangeResults {
+    pub start_s: f32,
+    pub end_s: f32,
+    /// Levels of all channels mixed down.
+    pub stats: SignalStats,
+    pub loudness: LoudnessReport,
+    pub pitch: Vec<PitchPoint>,
+    /// Annotations and detected events in the range.
+    pub markers: Vec<Label>,
+}
+
+/// Samples and analysis of a time range that is being loaded from the buffer.
+pub struct RangeQuery {
+    pub(crate) load: RangeLoad,
+    pub(crate) start: f32,
+    pub(crate) end: f32,
+    pub(crate) start_s: f32,
+    pub(crate) end_s: f32,
+    /// Seconds at the start of each chunk of the load.
+    pub(crate) chunk_starts: Vec<f32>,
+    pub(crate) sample_rate: f32,
+    pub(crate) loudness_channels: ChannelSelection,
+    pub(crate) pitch: (Yin, ChannelSelection),
+    pub(crate) min_confidence: f32,
+    pub(crate) markers: Vec<Label>,
+}
+
+impl RangeQuery {
+    pub fn is_ready(&self) -> bool {
+        self.load.is_ready()
+    }
+
+    /// Samples of `channel` in the range; all channels mixed down if `None`.
+    pub fn samples(&self, channel: Option<usize>) -> Vec<f32> {
+        let stream =
+            Stream::from_chunks(&self.load.chunks(), self.sample_rate, |n| match channel {
+                Some(channel) => vec![channel],
+                None => (0..n).collect(),
+            });
+        self.trim(&stream)
+    }
+
+    fn trim(&self, stream: &Stream) -> Vec<f32> {
+        stream
+            .samples
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (self.start..self.end).contains(&stream.position(*i)))
+            .map(|(_, v)| *v)
+            .collect()
+    }
+
+    pub fn results(&self) -> RangeResults {
+        let chunks = self.load.chunks();
+        let stream = Stream::from_chunks(&chunks, self.sample_rate, |n| (0..n).collect());
+        let (yin, channels) = &self.pitch;
+        let pitch = chunks
+            .iter()
+            .filter_map(|chunk| {
+                let estimate = yin.estimate(
+                    &mixdown(chunk, &channels.active(chunk.channels)),
+                    self.sample_rate,
+                )?;
+                (estimate.confidence >= self.min_confidence).then_some(PitchPoint {
+                    time_s: self.chunk_starts[chunk.id - self.load.start],
+                    frequency: estimate.frequency,
+                    confidence: estimate.confidence,
+                })
+            })
+            .collect();
+        RangeResults {
+            start_s: self.start_s,
+            end_s: self.end_s,
+            stats: stats(&self.trim(&stream)),
+            loudness: measure(&chunks, &self.loudness_channels, self.sample_rate),
+            pitch,
+            markers: self.markers.clone(),
+        }
+    }
+}
@@ -4,16 +4,26 @@ use std::{
 };
 
 use crate::{
-    app::View,
-    data::{Chunk, Data},
+    annotations::Annotation,
+    capture::SessionMetadata,
+    data::{Chunk, Data, Page, PageState, StorageStats},
+    error::StorageError,
+    summary::Summary,
+    timeline::View,
     Log,
 };
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct Buffer {
-    buf: LinkedList<Arc<Mutex<Vec<Chunk>>>>,
+    buf: LinkedList<Arc<Mutex<Page>>>,
     db: Data,
+    summary: Summary,
     max_id: usize,
+    channels: usize,
+    /// Samples per channel of the latest chunk, used to pad views past the end.
+    frames: usize,
+    /// Page size requested while a session was running; applied on `clear`.
+    pending_page_size: Option<usize>,
 }
 
 impl Log for Buffer {
@@ -22,9 +32,14 @@ impl Log for Buffer {
     }
 }
 
-pub struct DataRange {
-    pub data: Vec<u8>,
-    pub time_range: Option<(f32, f32)>,
+/// Pages held for the view, and how storage is doing.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BufferStats {
+    pub loaded_pages: usize,
+    /// Pages requested from IndexedDB that haven't arrived.
+    pub loading_pages: usize,
+    pub cached_chunks: usize,
+    pub storage: StorageStats,
 }
 
 impl Buffer {
@@ -32,56 +47,134 @@ impl Buffer {
         Self {
             buf: LinkedList::new(),
             db: Data::default(),
+            summary: Summary::default(),
             max_id: 0,
+            channels: 1,
+            frames: 1024,
+            pending_page_size: None,
         }
     }
 
     pub fn clear(&mut self) {
         self.buf.clear();
         self.db.clear();
+        self.summary.clear();
         self.max_id = 0;
+        self.channels = 1;
+        if let Some(size) = self.pending_page_size.take() {
+            self.db.page_size = size;
+        }
     }
 
     pub fn push(&mut self, chunk: Chunk) {
+        self.summary.push(&chunk);
+        self.channels = self.channels.max(chunk.channels);
+        self.frames = chunk.frames();
         self.db.push(chunk.clone());
-        if self.buf.len() == 0 {
-            self.buf.push_back(Arc::new(Mutex::new(vec![])));
+        if self.db.current_chunks.is_empty() {
+            // a page was just written, keep the summary in step with it
+            self.db.save_summary(&self.summary);
+        }
+        if self.buf.is_empty() {
+            self.buf
+                .push_back(Arc::new(Mutex::new(Page::loaded(vec![]))));
         }
         let back = self.buf.back().unwrap().clone();
         let mut back = back.lock().unwrap();
-        if back.len() == Data::MAX_SIZE {
-            let c = Arc::new(Mutex::new(vec![chunk]));
+        if back.chunks.len() == self.db.page_size {
+            let c = Arc::new(Mutex::new(Page::loaded(vec![chunk])));
             self.buf.push_back(c);
             return;
         }
-        back.push(chunk);
+        back.chunks.push(chunk);
+    }
+
+    /// Repaints `ctx` whenever a page finishes loading.
+    pub fn set_repaint(&mut self, ctx: egui::Context) {
+        self.db.repaint = Some(ctx);
+    }
+
+    /// Why the session is only kept in memory, if it is.
+    pub fn storage_error(&self) -> Option<StorageError> {
+        self.db.storage_error()
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        let mut stats = BufferStats {
+            storage: self.db.stats(),
+            ..Default::default()
+        };
+        for page in &self.buf {
+            let page = page.lock().unwrap();
+            match page.state {
+                PageState::Loading => stats.loading_pages += 1,
+                PageState::Loaded => {
+                    stats.loaded_pages += 1;
+                    stats.cached_chunks += page.chunks.len();
+                }
+                PageState::Failed => {}
+            }
+        }
+        stats
+    }
+
+    pub fn retry_storage(&self) {
+        self.db.retry_storage();
+    }
+
+    pub fn save_metadata(&self, metadata: &SessionMetadata) {
+        self.db.save_metadata(metadata);
+    }
+
+    pub fn save_annotations(&self, annotations: &[Annotation]) {
+        self.db.save_annotations(annotations);
     }
 
     pub fn set_max_id(&mut self, id: usize) {
         self.max_id = id;
     }
 
+    /// Changes the IndexedDB page size. Pages of a running session must all
+    /// have the same size, so the change waits for the next `clear` unless
+    /// the buffer is still empty.
+    pub fn set_page_size(&mut self, size: usize) {
+        if self.max_id == 0 {
+            self.db.page_size = size;
+        } else if size != self.db.page_size {
+            self.pending_page_size = Some(size);
+        }
+    }
+
+    /// Largest channel count pushed since the last clear.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
     fn front(&self) -> usize {
-        if self.buf.len() == 0 {
+        if self.buf.is_empty() {
             return 1;
         }
         let front = self.buf.front().expect("don't have data");
         let front = front.lock().unwrap();
-        if front.len() == 0 {
-            return 1;
+        match front.chunks.first() {
+            Some(chunk) => chunk.id,
+            None => 1,
         }
-        front[0].id
     }
     fn end(&self) -> usize {
-        if self.buf.len() == 0 {
+        if self.buf.is_empty() {
             return 1;
         }
         let end = self.buf.back().expect("don't have data");
         let end = end.lock().unwrap();
-        if end.len() == 0 {
-            return 1;
+        match end.chunks.last() {
+            Some(chunk) => chunk.id,
+            None => 1,
         }
-        end[end.len() - 1].id
     }
 
     fn fetch_data(&mut self, id: usize) {
@@ -94,33 +187,35 @@ impl Buffer {
         if id < self.front() {
             let mut fetch_id = self.front() - 1;
             while fetch_id > id {
-                let c = Arc::new(Mutex::new(vec![]));
+                let c = Arc::new(Mutex::new(Page::default()));
                 self.buf.push_front(c);
                 self.db
                     .get_from_db(fetch_id, self.buf.front().unwrap().clone());
-                fetch_id -= Data::MAX_SIZE;
+                fetch_id -= self.db.page_size;
             }
         } else if id > self.end() {
-            let mut fetch_id = self.end() + Data::MAX_SIZE;
+            let mut fetch_id = self.end() + self.db.page_size;
             if fetch_id > self.max_id {
                 return;
             }
             while fetch_id < id {
-                let c = Arc::new(Mutex::new(vec![]));
+                let c = Arc::new(Mutex::new(Page::default()));
                 self.buf.push_back(c);
                 self.db
                     .get_from_db(fetch_id, self.buf.back().unwrap().clone());
-                fetch_id += Data::MAX_SIZE;
+                fetch_id += self.db.page_size;
             }
         } else {
             // no need for fetch data
         }
     }
 
-    pub fn get_data(&mut self, view: &View) -> Vec<u8> {
+    /// Samples in `view`, one planar vector per channel.
+    pub fn get_data(&mut self, view: &View) -> Vec<Vec<u8>> {
         assert!(view.end > view.start);
         // Self::log(&format!("get data {:?}", view));
-        let mut res = vec![];
+        let channels = self.channels;
+        let mut res = vec![vec![]; channels];
         let (start, end) = (view.start, view.end);
         self.fetch_data(view.start);
         self.fetch_data(self.max_id.min(view.end));
@@ -129,45 +224,119 @@ impl Buffer {
         //     i, start, start_id, size
         // ));
         // Self::log(&format!("buf_len {}", self.buf.len()));
-        while let Some(id) = self.get_first_chunk_last_id() {
-            if id < start {
-                self.buf.pop_front();
-            } else {
-                break;
-            }
+        while self.is_front_page_stale(start) {
+            self.buf.pop_front();
         }
-        let data = self.buf.iter().fold(vec![], |mut acc, value| {
+        for value in self.buf.iter() {
             let data = value.lock().unwrap();
-            let data = data.iter().fold(vec![], |mut acc, value| {
-                if value.id >= start && value.id <= end {
-                    acc.extend(value.data.clone());
+            for chunk in data.chunks.iter() {
+                if chunk.id >= start && chunk.id <= end {
+                    for (channel, samples) in res.iter_mut().enumerate() {
+                        samples.extend(chunk.channel(channel));
+                    }
                 }
-                acc
-            });
-            acc.extend(data);
-            acc
-        });
-        res.extend(data);
+            }
+        }
         if self.max_id < view.end {
-            res.extend(vec![128; Data::CHUNK_SIZE * (view.end - self.max_id)]);
+            for samples in res.iter_mut() {
+                samples.extend(vec![128; self.frames * (view.end - self.max_id)]);
+            }
         }
         // Self::log(&format!("data_len {}", res.len()));
         // Self::log(&format!("buf_len {}", self.buf.len()));
         res
     }
 
-    fn get_first_chunk_last_id(&self) -> Option<usize> {
-        if let Some(v) = self.buf.front() {
-            let v = v.lock().unwrap();
-            if v.len() == 0 {
-                // is waiting for data
-                return None;
-            }
-            // assert!(v.len() > 0);
-            let last = &v[v.len() - 1];
-            Some(last.id)
-        } else {
-            None
+    /// Starts loading every chunk from `start` to `end` without touching the
+    /// pages cached for the view.
+    pub fn load_range(&self, start: usize, end: usize) -> RangeLoad {
+        let page_size = self.db.page_size;
+        let end = end.min(self.max_id).max(start);
+        let first_key = start.div_ceil(page_size) * page_size;
+        let last_key = end.div_ceil(page_size) * page_size;
+        let pages = (first_key..=last_key)
+            .step_by(page_size)
+            .map(|key| {
+                let page = Arc::new(Mutex::new(Page::default()));
+                self.db.get_from_db(key, page.clone());
+                page
+            })
+            .collect();
+        RangeLoad { start, end, pages }
+    }
+
+    /// Chunks of `view` that are already loaded; call after `get_data` fetched them.
+    pub fn get_chunks(&self, view: &View) -> Vec<Chunk> {
+        let mut res = vec![];
+        for value in self.buf.iter() {
+            let data = value.lock().unwrap();
+            res.extend(
+                data.chunks
+                    .iter()
+                    .filter(|chunk| chunk.id >= view.start && chunk.id <= view.end)
+                    .cloned(),
+            );
+        }
+        res
+    }
+
+    /// True when the front page ends before `start`, or settled without chunks.
+    fn is_front_page_stale(&self, start: usize) -> bool {
+        let Some(page) = self.buf.front() else {
+            return false;
+        };
+        let page = page.lock().unwrap();
+        match page.chunks.last() {
+            Some(last) => last.id < start,
+            // still waiting for data
+            None => page.is_settled(),
         }
     }
 }
+
+/// Chunks of an id range that is being loaded from IndexedDB page by page.
+pub struct RangeLoad {
+    pub start: usize,
+    pub end: usize,
+    pages: Vec<Arc<Mutex<Page>>>,
+}
+
+impl RangeLoad {
+    /// True once every requested page was loaded or failed to load.
+    pub fn is_ready(&self) -> bool {
+        self.pages
+            .iter()
+            .all(|page| page.lock().unwrap().is_settled())
+    }
+
+    /// Takes the load out of `pending` once it is ready.
+    pub fn take_ready(pending: &mut Option<RangeLoad>) -> Option<RangeLoad> {
+        match pending {
+            Some(load) if load.is_ready() => pending.take(),
+            _ => None,
+        }
+    }
+
+    /// Fraction of pages that were loaded or failed to load.
+    pub fn progress(&self) -> f32 {
+        let ready = self
+            .pages
+            .iter()
+            .filter(|page| page.lock().unwrap().is_settled())
+            .count();
+        ready as f32 / self.pages.len().max(1) as f32
+    }
+
+    /// Loaded chunks of the range in id order.
+    pub fn chunks(&self) -> Vec<Chunk> {
+        let mut chunks: Vec<Chunk> = self
+            .pages
+            .iter()
+            .flat_map(|page| page.lock().unwrap().chunks.clone())
+            .filter(|chunk| chunk.id >= self.start && chunk.id <= self.end)
+            .collect();
+        chunks.sort_by_key(|chunk| chunk.id);
+        chunks.dedup_by_key(|chunk| chunk.id);
+        chunks
+    }
+}
@@ -0,0 +1,254 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::JsValue;
+use web_sys::{
+    AudioContextOptions, MediaStream, MediaStreamConstraints, MediaStreamTrack,
+    MediaTrackConstraints,
+};
+
+use crate::Log;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq, Debug)]
+pub struct InputDevice {
+    pub id: String,
+    pub label: String,
+}
+
+/// What `getUserMedia` is asked for. The browser's speech processing is off
+/// by default because it distorts measurements.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct CaptureSettings {
+    /// `None` picks the browser default input.
+    pub device_id: Option<String>,
+    pub echo_cancellation: bool,
+    pub noise_suppression: bool,
+    pub auto_gain_control: bool,
+    /// `None` keeps the device's native rate.
+    pub sample_rate: Option<u32>,
+    /// `None` keeps the device's native channel count.
+    pub channel_count: Option<u32>,
+}
+
+/// Settings the capture actually ended up with, stored next to the session data.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct SessionMetadata {
+    pub device_id: String,
+    pub device_label: String,
+    pub sample_rate: f32,
+    pub channels: usize,
+    pub echo_cancellation: Option<bool>,
+    pub noise_suppression: Option<bool>,
+    pub auto_gain_control: Option<bool>,
+    /// `clock::now_ms()` when capture started.
+    pub started_at: f64,
+}
+
+impl Log for CaptureSettings {
+    fn name() -> &'static str {
+        "Capture"
+    }
+}
+
+impl CaptureSettings {
+    pub const STORAGE_KEY: &'static str = "capture_settings";
+    const SAMPLE_RATES: [u32; 4] = [44100, 48000, 88200, 96000];
+
+    pub fn constraints(&self) -> MediaStreamConstraints {
+        let mut audio = MediaTrackConstraints::new();
+        audio
+            .echo_cancellation(&JsValue::from_bool(self.echo_cancellation))
+            .noise_suppression(&JsValue::from_bool(self.noise_suppression))
+            .auto_gain_control(&JsValue::from_bool(self.auto_gain_control));
+        if let Some(id) = &self.device_id {
+            audio.device_id(&exact(&JsValue::from_str(id)));
+        }
+        if let Some(channels) = self.channel_count {
+            audio.channel_count(&JsValue::from_f64(channels as f64));
+        }
+        if let Some(rate) = self.sample_rate {
+            // `sampleRate` isn't generated for `MediaTrackConstraints`
+            let _ = Reflect::set(
+                &audio,
+                &JsValue::from_str("sampleRate"),
+                &JsValue::from_f64(rate as f64),
+            );
+        }
+        let mut constraints = MediaStreamConstraints::new();
+        constraints.audio(&audio);
+        constraints
+    }
+
+    pub fn context_options(&self) -> AudioContextOptions {
+        let mut options = AudioContextOptions::new();
+        if let Some(rate) = self.sample_rate {
+            options.sample_rate(rate as f32);
+        }
+        options
+    }
+
+    /// Returns true when the user asked to apply changed settings.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        devices: &[InputDevice],
+        active: &SessionMetadata,
+    ) -> bool {
+        let mut apply = false;
+        ui.horizontal(|ui| {
+            let selected = match &self.device_id {
+                Some(id) => devices
+                    .iter()
+                    .find(|d| &d.id == id)
+                    .map_or(id.clone(), |d| d.label.clone()),
+                None => "Default input".to_owned(),
+            };
+            egui::ComboBox::from_id_source("input_device")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.device_id, None, "Default input");
+                    for device in devices {
+                        ui.selectable_value(
+                            &mut self.device_id,
+                            Some(device.id.clone()),
+                            &device.label,
+                        );
+                    }
+                });
+            let rate_text = self
+                .sample_rate
+                .map_or("Native rate".to_owned(), |r| format!("{} Hz", r));
+            egui::ComboBox::from_id_source("input_sample_rate")
+                .selected_text(rate_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.sample_rate, None, "Native rate");
+                    for rate in Self::SAMPLE_RATES {
+                        ui.selectable_value(
+                            &mut self.sample_rate,
+                            Some(rate),
+                            format!("{} Hz", rate),
+                        );
+                    }
+                });
+            let channels_text = self
+                .channel_count
+                .map_or("Native channels".to_owned(), |c| format!("{} ch", c));
+            egui::ComboBox::from_id_source("input_channels")
+                .selected_text(channels_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.channel_count, None, "Native channels");
+                    for channels in 1..=8 {
+                        ui.selectable_value(
+                            &mut self.channel_count,
+                            Some(channels),
+                            format!("{} ch", channels),
+                        );
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.echo_cancellation, "Echo cancellation");
+            ui.checkbox(&mut self.noise_suppression, "Noise suppression");
+            ui.checkbox(&mut self.auto_gain_control, "Auto gain");
+            apply = ui.button("Apply").clicked();
+        });
+        if active.sample_rate > 0.0 {
+            ui.weak(format!(
+                "Active: {} · {} Hz · {} ch",
+                active.device_label, active.sample_rate, active.channels
+            ));
+        }
+        apply
+    }
+}
+
+fn exact(value: &JsValue) -> JsValue {
+    let constraint = Object::new();
+    let _ = Reflect::set(&constraint, &JsValue::from_str("exact"), value);
+    constraint.into()
+}
+
+/// Lists audio inputs. Labels are empty until the user granted microphone access.
+#[cfg(target_arch = "wasm32")]
+pub async fn enumerate_inputs() -> Vec<InputDevice> {
+    use js_sys::Array;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{MediaDeviceInfo, MediaDeviceKind};
+
+    let Some(devices) = web_sys::window().and_then(|w| w.navigator().media_devices().ok()) else {
+        return vec![];
+    };
+    let Ok(promise) = devices.enumerate_devices() else {
+        return vec![];
+    };
+    let Ok(list) = JsFuture::from(promise).await else {
+        return vec![];
+    };
+    Array::from(&list)
+        .iter()
+        .filter_map(|device| device.dyn_into::<MediaDeviceInfo>().ok())
+        .filter(|device| device.kind() == MediaDeviceKind::Audioinput)
+        .enumerate()
+        .map(|(i, device)| {
+            let label = device.label();
+            InputDevice {
+                id: device.device_id(),
+                label: if label.is_empty() {
+                    format!("Input {}", i + 1)
+                } else {
+                    label
+                },
+            }
+        })
+        .collect()
+}
+
+/// Reads back what the browser actually applied to the first audio track.
+pub fn session_metadata(
+    media_stream: &MediaStream,
+    sample_rate: f32,
+    channels: usize,
+    started_at: f64,
+) -> SessionMetadata {
+    let mut metadata = SessionMetadata {
+        sample_rate,
+        channels,
+        started_at,
+        ..Default::default()
+    };
+    let track = media_stream.get_audio_tracks().get(0);
+    if track.is_undefined() {
+        return metadata;
+    }
+    let track = MediaStreamTrack::from(track);
+    metadata.device_label = track.label();
+    let settings = track.get_settings();
+    let get = |key: &str| Reflect::get(&settings, &JsValue::from_str(key)).ok();
+    metadata.device_id = get("deviceId")
+        .and_then(|v| v.as_string())
+        .unwrap_or_default();
+    metadata.echo_cancellation = get("echoCancellation").and_then(|v| v.as_bool());
+    metadata.noise_suppression = get("noiseSuppression").and_then(|v| v.as_bool());
+    metadata.auto_gain_control = get("autoGainControl").and_then(|v| v.as_bool());
+    metadata
+}
+
+/// Channel count the browser actually delivers for the first audio track.
+pub fn stream_channel_count(media_stream: &MediaStream) -> Option<u32> {
+    let track = media_stream.get_audio_tracks().get(0);
+    if track.is_undefined() {
+        return None;
+    }
+    let settings = MediaStreamTrack::from(track).get_settings();
+    Reflect::get(&settings, &JsValue::from_str("channelCount"))
+        .ok()
+        .and_then(|v| v.as_f64())
+        .map(|v| v as u32)
+}
+
+pub fn stop_stream(media_stream: &MediaStream) {
+    for track in media_stream.get_tracks().iter() {
+        MediaStreamTrack::from(track).stop();
+    }
+}
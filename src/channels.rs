@@ -0,0 +1,90 @@
+/// Which channels of a multi-channel session are shown or analyzed.
+///
+/// A soloed channel overrides the hidden flags; channels the selection has
+/// never seen are visible by default.
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone)]
+#[serde(default)]
+pub struct ChannelSelection {
+    hidden: Vec<bool>,
+    solo: Option<usize>,
+}
+
+pub fn channel_name(channel: usize) -> String {
+    format!("Ch {}", channel + 1)
+}
+
+impl ChannelSelection {
+    pub fn is_active(&self, channel: usize) -> bool {
+        match self.solo {
+            Some(solo) => solo == channel,
+            None => !self.hidden.get(channel).copied().unwrap_or(false),
+        }
+    }
+
+    /// Active channels out of `channels`.
+    pub fn active(&self, channels: usize) -> Vec<usize> {
+        (0..channels).filter(|c| self.is_active(*c)).collect()
+    }
+
+    pub fn set_hidden(&mut self, channel: usize, hidden: bool) {
+        if self.hidden.len() <= channel {
+            self.hidden.resize(channel + 1, false);
+        }
+        self.hidden[channel] = hidden;
+    }
+
+    pub fn toggle_solo(&mut self, channel: usize) {
+        self.solo = if self.solo == Some(channel) {
+            None
+        } else {
+            Some(channel)
+        };
+    }
+
+    /// Per-channel solo/hide toggles for `channels` channels.
+    pub fn ui(&mut self, ui: &mut egui::Ui, channels: usize) {
+        ui.horizontal(|ui| {
+            for channel in 0..channels {
+                ui.group(|ui| {
+                    ui.label(channel_name(channel));
+                    let mut visible = !self.hidden.get(channel).copied().unwrap_or(false);
+                    if ui.checkbox(&mut visible, "Show").changed() {
+                        self.set_hidden(channel, !visible);
+                    }
+                    if ui
+                        .selectable_label(self.solo == Some(channel), "S")
+                        .on_hover_text("Solo")
+                        .clicked()
+                    {
+                        self.toggle_solo(channel);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Compact picker for analysis and export tools.
+    pub fn picker(&mut self, ui: &mut egui::Ui, id_source: &str, channels: usize) {
+        let active = self.active(channels);
+        let text = if active.len() == channels {
+            "All channels".to_owned()
+        } else {
+            active
+                .iter()
+                .map(|c| channel_name(*c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        egui::ComboBox::from_id_source(id_source)
+            .selected_text(text)
+            .show_ui(ui, |ui| {
+                for channel in 0..channels {
+                    let mut selected = self.is_active(channel);
+                    if ui.checkbox(&mut selected, channel_name(channel)).changed() {
+                        self.solo = None;
+                        self.set_hidden(channel, !selected);
+                    }
+                }
+            });
+    }
+}
@@ -0,0 +1,51 @@
+/// One capture block. Multi-channel blocks store their samples interleaved.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Chunk {
+    pub id: usize,
+    pub data: Vec<u8>,
+    pub time: f32,
+    #[serde(default = "Chunk::default_channels")]
+    pub channels: usize,
+}
+
+impl Chunk {
+    pub fn new(id: usize, data: Vec<u8>, time: f32) -> Self {
+        Self::new_interleaved(id, data, time, 1)
+    }
+
+    pub fn new_interleaved(id: usize, data: Vec<u8>, time: f32, channels: usize) -> Self {
+        assert!(channels > 0);
+        Self {
+            id,
+            data,
+            time,
+            channels,
+        }
+    }
+
+    fn default_channels() -> usize {
+        1
+    }
+
+    /// Number of samples per channel.
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.channels
+    }
+
+    /// Samples of one channel; channels the chunk doesn't have read as silence.
+    pub fn channel(&self, channel: usize) -> impl Iterator<Item = u8> + '_ {
+        let frames = self.frames();
+        (0..frames).map(move |i| {
+            if channel < self.channels {
+                self.data[i * self.channels + channel]
+            } else {
+                128
+            }
+        })
+    }
+}
+
+/// Converts an unsigned byte sample from the analyser node to -1..1.
+pub fn to_amplitude(v: u8) -> f32 {
+    (v as f32 - 128.0) / 128.0
+}
@@ -0,0 +1,46 @@
+//! Wall clock time on every target: `Date` in the browser, `SystemTime`
+//! natively, where calling into JavaScript would panic.
+
+/// Milliseconds since the Unix epoch.
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Milliseconds since the Unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
+
+/// `HH:MM:SS.mmm` of `time_ms` from `now_ms`, in local time in the browser
+/// and in UTC natively.
+pub fn time_of_day(time_ms: f64) -> String {
+    let (hours, minutes, seconds, millis) = hms(time_ms);
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn hms(time_ms: f64) -> (u32, u32, u32, u32) {
+    let time = js_sys::Date::new(&time_ms.into());
+    (
+        time.get_hours(),
+        time.get_minutes(),
+        time.get_seconds(),
+        time.get_milliseconds(),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn hms(time_ms: f64) -> (u32, u32, u32, u32) {
+    let millis = time_ms.max(0.0) as u64 % (24 * 3600 * 1000);
+    let seconds = (millis / 1000) as u32;
+    (
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        (millis % 1000) as u32,
+    )
+}
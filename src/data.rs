@@ -1,28 +1,86 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
-use js_sys::Array;
+use js_sys::{Array, Reflect};
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+use web_sys::{IdbDatabase, IdbObjectStore, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
 
-use crate::Log;
+pub use crate::chunk::{to_amplitude, Chunk};
+use crate::{
+    annotations::Annotation, capture::SessionMetadata, error::StorageError, summary::Summary, Log,
+};
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct Data {
     pub current_chunks: Vec<Chunk>,
+    /// Chunks written to IndexedDB as one page.
+    pub page_size: usize,
+    /// Woken up when a page arrives.
+    #[serde(skip)]
+    pub repaint: Option<egui::Context>,
+    #[serde(skip)]
+    storage: Arc<Mutex<Storage>>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone)]
-pub struct Chunk {
-    pub id: usize,
-    pub data: Vec<u8>,
-    pub time: f32,
+/// Chunks of one IndexedDB page, filled in by the load callbacks.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+pub struct Page {
+    pub chunks: Vec<Chunk>,
+    pub state: PageState,
 }
 
-impl Chunk {
-    pub fn new(id: usize, data: Vec<u8>, time: f32) -> Self {
-        Self { id, data, time }
+/// Whether a page's load settled. An empty page may be loaded too: missing
+/// keys and empty sessions have no chunks.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Default, PartialEq, Debug)]
+pub enum PageState {
+    #[default]
+    Loading,
+    Loaded,
+    Failed,
+}
+
+impl Page {
+    /// A page that is filled by pushing, not by a load.
+    pub fn loaded(chunks: Vec<Chunk>) -> Self {
+        Self {
+            chunks,
+            state: PageState::Loaded,
+        }
     }
+
+    pub fn is_settled(&self) -> bool {
+        self.state != PageState::Loading
+    }
+}
+
+/// What the IndexedDB callbacks report back.
+#[derive(Default)]
+struct Storage {
+    /// Set once a write failed; pages stay in `memory` from then on.
+    error: Option<StorageError>,
+    /// Pages that aren't in IndexedDB, by key.
+    memory: BTreeMap<usize, Vec<Chunk>>,
+    pending_writes: usize,
+    failed_writes: usize,
+    /// Set when `clear` couldn't reach IndexedDB, whose audio store then
+    /// still holds the pages of an earlier session.
+    uncleared: bool,
+}
+
+/// How the IndexedDB writes are doing.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StorageStats {
+    /// Chunks of the page that isn't full yet.
+    pub unwritten_chunks: usize,
+    /// Page writes that haven't committed yet.
+    pub pending_writes: usize,
+    pub failed_writes: usize,
+    /// Pages kept in memory because IndexedDB failed.
+    pub memory_pages: usize,
 }
 
 impl Log for Data {
@@ -35,101 +93,342 @@ impl Default for Data {
     fn default() -> Self {
         Self {
             current_chunks: Default::default(),
+            page_size: Self::DEFAULT_PAGE_SIZE,
+            repaint: None,
+            storage: Default::default(),
         }
     }
 }
 
 impl Data {
-    pub const MAX_SIZE: usize = 100;
-    pub const CHUNK_SIZE: usize = 1024;
+    pub const DEFAULT_PAGE_SIZE: usize = 100;
+    /// IndexedDB only exists in the browser; natively every page stays in memory.
+    const HAS_DB: bool = cfg!(target_arch = "wasm32");
+    /// Chunk pages, keyed by the id of their last chunk.
+    const AUDIO_STORE: &'static str = "audio_store";
+    /// Records about the session, which `clear` keeps.
+    const SESSION_STORE: &'static str = "session_store";
+    const METADATA_KEY: &'static str = "session";
+    const ANNOTATIONS_KEY: &'static str = "annotations";
+    const SUMMARY_KEY: &'static str = "summary";
     pub fn clear(&mut self) {
         self.current_chunks.clear();
-        Self::request_db(|store| {
-            store.clear().unwrap();
-        })
+        let mut storage = self.storage.lock().unwrap();
+        storage.memory.clear();
+        storage.uncleared = storage.error.is_some();
+        let clear = Self::HAS_DB && !storage.uncleared;
+        drop(storage);
+        if clear {
+            self.request_db(Self::AUDIO_STORE, |store| store.clear());
+        }
     }
     pub fn push(&mut self, data: Chunk) {
         let id = data.id;
         self.current_chunks.push(data);
-        if self.current_chunks.len() == Self::MAX_SIZE {
-            // Self::log(&format!("data_id: {}", id));
-            let chunks = self.current_chunks.clone();
-            Self::request_db(move |store| {
-                let value = serde_wasm_bindgen::to_value(&chunks).unwrap();
-                if let Ok(_) = store.add_with_key(&value, &JsValue::from_f64(id as f64)) {
-                    Self::log("write to indexedDB success");
+        if self.current_chunks.len() == self.page_size {
+            let chunks = std::mem::take(&mut self.current_chunks);
+            self.write_page(id, chunks);
+        }
+    }
+
+    /// Why pages are kept in memory instead of IndexedDB, if they are.
+    pub fn storage_error(&self) -> Option<StorageError> {
+        self.storage.lock().unwrap().error.clone()
+    }
+
+    pub fn stats(&self) -> StorageStats {
+        let storage = self.storage.lock().unwrap();
+        StorageStats {
+            unwritten_chunks: self.current_chunks.len(),
+            pending_writes: storage.pending_writes,
+            failed_writes: storage.failed_writes,
+            memory_pages: storage.memory.len(),
+        }
+    }
+
+    /// Tries IndexedDB again for the next pages. Pages already kept in
+    /// memory stay there; pages of an earlier session that `clear` missed
+    /// are removed first.
+    pub fn retry_storage(&self) {
+        Self::log("retrying IndexedDB");
+        let mut storage = self.storage.lock().unwrap();
+        storage.error = None;
+        if std::mem::take(&mut storage.uncleared) {
+            drop(storage);
+            self.request_db(Self::AUDIO_STORE, |store| store.clear());
+        }
+    }
+
+    fn write_page(&self, key: usize, chunks: Vec<Chunk>) {
+        if !Self::HAS_DB || self.storage_error().is_some() {
+            self.storage.lock().unwrap().memory.insert(key, chunks);
+            return;
+        }
+        let value = match serde_wasm_bindgen::to_value(&chunks) {
+            Ok(value) => value,
+            Err(e) => {
+                Self::warn(&format!("cannot serialize page {}: {}", key, e));
+                self.storage.lock().unwrap().memory.insert(key, chunks);
+                return;
+            }
+        };
+        self.storage.lock().unwrap().pending_writes += 1;
+        // errors may be reported more than once, and after the request succeeded
+        let settled = Rc::new(Cell::new(false));
+        let (committed, done) = (self.storage.clone(), settled.clone());
+        let storage = self.storage.clone();
+        Self::try_request_db(
+            Self::AUDIO_STORE,
+            move |store| {
+                let request = store.put_with_key(&value, &JsValue::from_f64(key as f64))?;
+                let on_complete = Closure::once(move |_: web_sys::Event| {
+                    if !done.replace(true) {
+                        committed.lock().unwrap().pending_writes -= 1;
+                    }
+                });
+                store
+                    .transaction()
+                    .set_oncomplete(Some(on_complete.as_ref().unchecked_ref()));
+                on_complete.forget();
+                Ok(request)
+            },
+            move |error| {
+                let mut storage = storage.lock().unwrap();
+                if !settled.replace(true) {
+                    storage.pending_writes -= 1;
+                    storage.failed_writes += 1;
                 }
-            });
-            self.current_chunks.clear();
+                storage.error = Some(error);
+                storage.memory.insert(key, chunks.clone());
+            },
+        );
+    }
+
+    /// Stores the capture settings of the session in the session store.
+    pub fn save_metadata(&self, metadata: &SessionMetadata) {
+        if !Self::HAS_DB {
+            return;
         }
+        let Ok(value) = serde_wasm_bindgen::to_value(metadata) else {
+            return;
+        };
+        self.request_db(Self::SESSION_STORE, move |store| {
+            store.put_with_key(&value, &JsValue::from_str(Self::METADATA_KEY))
+        });
     }
 
-    pub fn get_from_db(&self, id: usize, container: Arc<Mutex<Vec<Chunk>>>) {
-        if self.current_chunks.len() > 0 && self.current_chunks[0].id <= id {
-            let mut a = container.lock().unwrap();
-            *a = self.current_chunks.clone();
+    /// Replaces the stored user annotations of the session.
+    pub fn save_annotations(&self, annotations: &[Annotation]) {
+        if !Self::HAS_DB {
             return;
         }
-        Self::request_db(move |store| {
-            let request = store
-                .get_all_with_key(&JsValue::from_f64(id as f64))
-                .unwrap();
-            let container = container.clone();
-            let on_success = Closure::wrap(Box::new(move |event: web_sys::Event| {
-                let target = event.target().unwrap();
-                let request = target.dyn_into::<IdbRequest>().unwrap();
-                let res = request.result().unwrap();
-                // Self::log(&format!("request id {}, value: {:?}", id, res));
-                let arr = Array::from(&res);
-                let arr = Array::from(&arr.at(0));
-                let chunk = arr
-                    .to_vec()
-                    .iter()
-                    .map(|n| {
-                        // Self::log(&format!("js_value: {:?}", n));
-                        return serde_wasm_bindgen::from_value(n.clone()).unwrap();
-                    })
-                    .collect();
-                // let chunk: Vec<Chunk> = serde_wasm_bindgen::from_value(res).unwrap();
-                let mut a = container.lock().unwrap();
-                *a = chunk;
-            }) as Box<dyn FnMut(_)>);
-
-            request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
-            on_success.forget();
+        let Ok(value) = serde_wasm_bindgen::to_value(annotations) else {
+            return;
+        };
+        self.request_db(Self::SESSION_STORE, move |store| {
+            store.put_with_key(&value, &JsValue::from_str(Self::ANNOTATIONS_KEY))
         });
     }
 
-    fn request_db(f: impl Fn(IdbObjectStore) + 'static) {
-        let window = web_sys::window().unwrap();
-        let db = window.indexed_db().unwrap().unwrap();
-        let request = db.open("audio_db").unwrap();
+    /// Replaces the stored overview summary of the session.
+    pub fn save_summary(&self, summary: &Summary) {
+        if !Self::HAS_DB {
+            return;
+        }
+        let Ok(value) = serde_wasm_bindgen::to_value(summary) else {
+            return;
+        };
+        self.request_db(Self::SESSION_STORE, move |store| {
+            store.put_with_key(&value, &JsValue::from_str(Self::SUMMARY_KEY))
+        });
+    }
 
-        let on_success = Closure::wrap(Box::new(move |event: JsValue| {
-            let target = JsValue::from_str("target");
-            let result = JsValue::from_str("result");
-            let target = js_sys::Reflect::get(&event, &target).unwrap();
-            let db = js_sys::Reflect::get(&target, &result).unwrap();
-            let db = IdbDatabase::from(db);
-
-            let transaction = db
-                .transaction_with_str_and_mode("audio_store", IdbTransactionMode::Readwrite)
-                .unwrap();
-            let store = transaction.object_store("audio_store").unwrap();
-            f(store);
-        }) as Box<dyn FnMut(JsValue)>);
+    /// Loads page `id` into `container` and settles its state.
+    pub fn get_from_db(&self, id: usize, container: Arc<Mutex<Page>>) {
+        let cached = if !self.current_chunks.is_empty() && self.current_chunks[0].id <= id {
+            Some(self.current_chunks.clone())
+        } else {
+            self.storage.lock().unwrap().memory.get(&id).cloned()
+        };
+        if let Some(chunks) = cached {
+            *container.lock().unwrap() = Page::loaded(chunks);
+            // the caller polls for it on the next frame
+            if let Some(ctx) = &self.repaint {
+                ctx.request_repaint();
+            }
+            return;
+        }
+        if !Self::HAS_DB {
+            // never written, so there's nothing to load
+            *container.lock().unwrap() = Page::loaded(vec![]);
+            return;
+        }
+        let repaint = self.repaint.clone();
+        let failed = container.clone();
+        Self::try_request_db(
+            Self::AUDIO_STORE,
+            move |store| {
+                let request = store.get_all_with_key(&JsValue::from_f64(id as f64))?;
+                let on_success = Closure::once(move |event: web_sys::Event| {
+                    let result = event
+                        .target()
+                        .and_then(|target| target.dyn_into::<IdbRequest>().ok())
+                        .and_then(|request| request.result().ok());
+                    let mut page = container.lock().unwrap();
+                    match result {
+                        // a key that was never written is an empty result
+                        Some(result) => {
+                            let arr = Array::from(&result);
+                            page.chunks = if arr.length() == 0 {
+                                vec![]
+                            } else {
+                                Array::from(&arr.at(0))
+                                    .iter()
+                                    .filter_map(|n| serde_wasm_bindgen::from_value(n).ok())
+                                    .collect()
+                            };
+                            page.state = PageState::Loaded;
+                        }
+                        None => page.state = PageState::Failed,
+                    }
+                    if let Some(ctx) = &repaint {
+                        ctx.request_repaint();
+                    }
+                });
+                request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+                on_success.forget();
+                Ok(request)
+            },
+            move |error| {
+                Self::warn(&format!("reading page {} failed: {}", id, error));
+                let mut page = failed.lock().unwrap();
+                if !page.is_settled() {
+                    page.state = PageState::Failed;
+                }
+            },
+        );
+    }
+
+    /// Runs `f` in a read-write transaction on `store`; a failure switches to
+    /// memory-only storage.
+    fn request_db(
+        &self,
+        store: &'static str,
+        f: impl FnOnce(IdbObjectStore) -> Result<IdbRequest, JsValue> + 'static,
+    ) {
+        let storage = self.storage.clone();
+        Self::try_request_db(store, f, move |error| {
+            storage.lock().unwrap().error = Some(error);
+        });
+    }
+
+    /// Runs `f` in a read-write transaction on `store`. Whatever goes wrong
+    /// on the way, from opening the database to `f`'s request, ends up in
+    /// `on_error`, possibly more than once.
+    fn try_request_db(
+        store: &'static str,
+        f: impl FnOnce(IdbObjectStore) -> Result<IdbRequest, JsValue> + 'static,
+        on_error: impl Fn(StorageError) + Clone + 'static,
+    ) {
+        let request = match Self::open_db() {
+            Ok(request) => request,
+            Err(error) => {
+                Self::warn(&format!("cannot open IndexedDB: {}", error));
+                on_error(error);
+                return;
+            }
+        };
+
+        let failed = on_error.clone();
+        let on_success = Closure::once(move |event: web_sys::Event| {
+            let run = || -> Result<(), JsValue> {
+                let target = event.target().ok_or("no target")?;
+                let db: IdbDatabase = Reflect::get(&target, &JsValue::from_str("result"))?.into();
+                let transaction =
+                    db.transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)?;
+                let request = f(transaction.object_store(store)?)?;
+
+                let on_request_error = Self::error_handler(failed.clone());
+                request.set_onerror(Some(on_request_error.as_ref().unchecked_ref()));
+                on_request_error.forget();
+                // running out of quota may only show when the transaction aborts
+                let on_abort = Self::error_handler(failed.clone());
+                transaction.set_onabort(Some(on_abort.as_ref().unchecked_ref()));
+                on_abort.forget();
+                Ok(())
+            };
+            if let Err(e) = run() {
+                failed(StorageError::from_js(&e));
+            }
+        });
         let on_upgrade = Closure::wrap(Box::new(move |event: JsValue| {
             Self::log("on upgrade");
             let target = JsValue::from_str("target");
             let result = JsValue::from_str("result");
-            let target = js_sys::Reflect::get(&event, &target).unwrap();
-            let db = js_sys::Reflect::get(&target, &result).unwrap();
-            let db = IdbDatabase::from(db);
-            db.create_object_store("audio_store").unwrap();
+            let db =
+                Reflect::get(&event, &target).and_then(|target| Reflect::get(&target, &result));
+            let Ok(db) = db.map(IdbDatabase::from) else {
+                return;
+            };
+            // databases of version 1 already have the audio store
+            for name in [Self::AUDIO_STORE, Self::SESSION_STORE] {
+                if db.object_store_names().contains(name) {
+                    continue;
+                }
+                if let Err(e) = db.create_object_store(name) {
+                    Self::warn(&format!("cannot create the {}: {:?}", name, e));
+                }
+            }
         }) as Box<dyn FnMut(JsValue)>);
+        let on_open_error = Self::error_handler(on_error);
         request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
         request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+        request.set_onerror(Some(on_open_error.as_ref().unchecked_ref()));
         on_success.forget();
         on_upgrade.forget();
+        on_open_error.forget();
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn open_db() -> Result<IdbOpenDbRequest, StorageError> {
+        // version 2 moved the session records out of the audio store
+        const VERSION: u32 = 2;
+        // failing to even open the database means there's no storage at all
+        let unavailable = |e: JsValue| match StorageError::from_js(&e) {
+            StorageError::Failed(message) => StorageError::Unavailable(message),
+            error => error,
+        };
+        web_sys::window()
+            .ok_or_else(|| StorageError::Unavailable("no window".to_owned()))?
+            .indexed_db()
+            .map_err(unavailable)?
+            .ok_or_else(|| StorageError::Unavailable(String::new()))?
+            .open_with_u32("audio_db", VERSION)
+            .map_err(unavailable)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_db() -> Result<IdbOpenDbRequest, StorageError> {
+        Err(StorageError::Unavailable(
+            "no IndexedDB outside the browser".to_owned(),
+        ))
+    }
+
+    /// Handler for the `error` events of requests and transactions, which
+    /// carry the `DOMException` in their target's `error`.
+    fn error_handler(
+        on_error: impl Fn(StorageError) + 'static,
+    ) -> Closure<dyn FnMut(web_sys::Event)> {
+        Closure::once(move |event: web_sys::Event| {
+            // keeps the error from showing up as uncaught
+            event.prevent_default();
+            let error = event
+                .target()
+                .and_then(|target| Reflect::get(&target, &JsValue::from_str("error")).ok())
+                .unwrap_or(JsValue::UNDEFINED);
+            let error = StorageError::from_js(&error);
+            Self::warn(&format!("IndexedDB error: {}", error));
+            on_error(error);
+        })
     }
 }
@@ -2,6 +2,8 @@ use js_sys::Math::sin;
 use wasm_bindgen::JsValue;
 use web_sys::{AudioBuffer, AudioContext};
 
+use crate::dsp::sweep::{exponential_sweep, SweepSettings};
+
 pub fn sine_buffer(audio_ctx: &AudioContext) -> Result<AudioBuffer, JsValue> {
     // let sample_rate = audio_ctx.sample_rate();
     let sample_rate = 96000.0;
@@ -33,3 +35,32 @@ pub fn line_buffer(audio_ctx: &AudioContext) -> Result<AudioBuffer, JsValue> {
 
     Ok(buffer)
 }
+/// Mono buffer holding `samples`, e.g. recorded audio to play back.
+pub fn samples_buffer(
+    audio_ctx: &AudioContext,
+    samples: &[f32],
+    sample_rate: f32,
+) -> Result<AudioBuffer, JsValue> {
+    let buffer = audio_ctx.create_buffer(1, samples.len().max(1) as u32, sample_rate)?;
+    buffer.copy_to_channel(samples, 0)?;
+
+    Ok(buffer)
+}
+
+/// Exponential sine sweep followed by `tail_s` of silence, for response
+/// measurements.
+pub fn sweep_buffer(
+    audio_ctx: &AudioContext,
+    settings: &SweepSettings,
+) -> Result<AudioBuffer, JsValue> {
+    let sample_rate = audio_ctx.sample_rate();
+    let mut buffer_data = exponential_sweep(settings, sample_rate);
+    buffer_data.resize(
+        buffer_data.len() + (settings.tail_s * sample_rate as f64) as usize,
+        0.0,
+    );
+    let buffer = audio_ctx.create_buffer(1, buffer_data.len() as u32, sample_rate)?;
+    buffer.copy_to_channel(&buffer_data, 0)?;
+
+    Ok(buffer)
+}
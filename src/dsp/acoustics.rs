@@ -0,0 +1,299 @@
+//! Room-acoustic parameters from an impulse response after ISO 3382-1:
+//! Schroeder decay, EDT, T20, T30, C50, C80 and D50, broadband and per
+//! octave band.
+
+use super::octave::{band_filter, band_label};
+
+/// Nominal octave bands reported besides broadband.
+const OCTAVE_CENTERS: [f64; 8] = [63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0];
+/// Window used to find where the decay sinks into the noise.
+const NOISE_WINDOW_S: f64 = 0.01;
+
+/// Least-squares line through part of a decay curve.
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+pub struct DecayFit {
+    /// Extrapolated time for 60 dB of decay.
+    pub decay_time: f32,
+    pub slope_db_per_s: f32,
+    pub intercept_db: f32,
+    /// Correlation coefficient; close to -1 for a straight decay.
+    pub correlation: f32,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct BandAcoustics {
+    pub band: String,
+    /// None for broadband.
+    pub center: Option<f64>,
+    pub edt: Option<DecayFit>,
+    pub t20: Option<DecayFit>,
+    pub t30: Option<DecayFit>,
+    /// Non-linearity `1000 * (T30 / T20 - 1)`; above 100 ‰ the decay is
+    /// noticeably curved.
+    pub curvature_permille: Option<f32>,
+    pub c50: f32,
+    pub c80: f32,
+    /// Definition, in percent.
+    pub d50: f32,
+    /// Peak-to-noise range of the response.
+    pub dynamic_range_db: f32,
+    /// Schroeder curve in dB from the onset, for display.
+    #[serde(skip)]
+    pub decay: Vec<f32>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct RoomReport {
+    pub sample_rate: f32,
+    pub bands: Vec<BandAcoustics>,
+}
+
+fn to_db(ratio: f64) -> f32 {
+    (10.0 * ratio.max(1e-20).log10()) as f32
+}
+
+/// First sample within 20 dB of the peak, the start of the direct sound.
+fn onset(ir: &[f64]) -> usize {
+    let peak = ir.iter().fold(0.0f64, |acc, v| acc.max(v.abs()));
+    ir.iter().position(|v| v.abs() >= peak * 0.1).unwrap_or(0)
+}
+
+/// Where the response meets the noise floor, and the noise floor relative
+/// to the peak energy. The floor is the mean energy of the last 10 %.
+fn truncation(energy: &[f64], sample_rate: f32) -> (usize, f32) {
+    let tail = &energy[energy.len() * 9 / 10..];
+    let noise = tail.iter().sum::<f64>() / tail.len().max(1) as f64;
+    let peak = energy.iter().copied().fold(0.0, f64::max);
+    let window = ((NOISE_WINDOW_S * sample_rate as f64) as usize).max(1);
+    // last window more than 5 dB above the noise
+    let threshold = noise * 10f64.powf(0.5);
+    let end = energy
+        .chunks(window)
+        .enumerate()
+        .filter(|(_, w)| w.iter().sum::<f64>() / w.len() as f64 > threshold)
+        .map(|(i, w)| i * window + w.len())
+        .last()
+        .unwrap_or(energy.len());
+    (end.max(1), to_db(peak / noise.max(1e-30)))
+}
+
+/// Schroeder backward integration of `ir[..end]`, normalized to 0 dB.
+fn schroeder(energy: &[f64]) -> Vec<f32> {
+    let total: f64 = energy.iter().sum();
+    let mut remaining = total;
+    energy
+        .iter()
+        .map(|e| {
+            let db = to_db(remaining / total.max(1e-30));
+            remaining -= e;
+            db
+        })
+        .collect()
+}
+
+/// Fits the part of `decay` between `from_db` and `to_db` (both negative).
+/// None if the curve doesn't get that low.
+pub fn fit(decay: &[f32], sample_rate: f32, from_db: f32, to_db: f32) -> Option<DecayFit> {
+    let start = decay.iter().position(|v| *v <= from_db)?;
+    let end = start + decay[start..].iter().position(|v| *v <= to_db)?;
+    if end <= start + 1 {
+        return None;
+    }
+    let n = (end - start) as f64;
+    let (mut sx, mut sy, mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (i, y) in decay[start..end].iter().enumerate() {
+        let x = (start + i) as f64 / sample_rate as f64;
+        let y = *y as f64;
+        sx += x;
+        sy += y;
+        sxx += x * x;
+        sxy += x * y;
+        syy += y * y;
+    }
+    let covariance = n * sxy - sx * sy;
+    let slope = covariance / (n * sxx - sx * sx);
+    let correlation = covariance / ((n * sxx - sx * sx) * (n * syy - sy * sy)).sqrt();
+    (slope < 0.0).then(|| DecayFit {
+        decay_time: (-60.0 / slope) as f32,
+        slope_db_per_s: slope as f32,
+        intercept_db: ((sy - slope * sx) / n) as f32,
+        correlation: correlation as f32,
+    })
+}
+
+/// Parameters of one (already band-filtered) impulse response.
+pub fn analyze_band(
+    ir: &[f64],
+    sample_rate: f32,
+    band: String,
+    center: Option<f64>,
+) -> BandAcoustics {
+    let start = onset(ir);
+    let energy: Vec<f64> = ir[start..].iter().map(|v| v * v).collect();
+    let (end, dynamic_range_db) = truncation(&energy, sample_rate);
+    let energy = &energy[..end.min(energy.len())];
+    let decay = schroeder(energy);
+
+    let early = |ms: f64| -> f64 {
+        let split = ((ms / 1000.0 * sample_rate as f64) as usize).min(energy.len());
+        energy[..split].iter().sum()
+    };
+    let total: f64 = energy.iter().sum();
+    let clarity = |ms: f64| {
+        let e = early(ms);
+        to_db(e / (total - e).max(1e-30))
+    };
+
+    let edt = fit(&decay, sample_rate, 0.0, -10.0);
+    let t20 = fit(&decay, sample_rate, -5.0, -25.0);
+    let t30 = fit(&decay, sample_rate, -5.0, -35.0);
+    BandAcoustics {
+        band,
+        center,
+        edt,
+        t20,
+        t30,
+        curvature_permille: t20
+            .zip(t30)
+            .map(|(t20, t30)| 1000.0 * (t30.decay_time / t20.decay_time - 1.0)),
+        c50: clarity(50.0),
+        c80: clarity(80.0),
+        d50: (100.0 * early(50.0) / total.max(1e-30)) as f32,
+        dynamic_range_db,
+        decay,
+    }
+}
+
+/// Broadband and octave-band parameters of `ir`.
+pub fn analyze(ir: &[f32], sample_rate: f32) -> Result<RoomReport, String> {
+    if ir.len() < (0.05 * sample_rate) as usize {
+        return Err("impulse response is shorter than 50 ms".to_owned());
+    }
+    if ir.iter().all(|v| *v == 0.0) {
+        return Err("impulse response is silent".to_owned());
+    }
+    let ir: Vec<f64> = ir.iter().map(|v| *v as f64).collect();
+    let mut bands = vec![analyze_band(&ir, sample_rate, "broadband".to_owned(), None)];
+    for center in OCTAVE_CENTERS {
+        if center > 0.45 * sample_rate as f64 / std::f64::consts::SQRT_2 {
+            break;
+        }
+        let mut filters = band_filter(center, 1, sample_rate as f64);
+        let filtered: Vec<f64> = ir
+            .iter()
+            .map(|x| filters.iter_mut().fold(*x, |acc, f| f.process(acc)))
+            .collect();
+        bands.push(analyze_band(
+            &filtered,
+            sample_rate,
+            band_label(center),
+            Some(center),
+        ));
+    }
+    Ok(RoomReport { sample_rate, bands })
+}
+
+impl RoomReport {
+    /// Per-band table as CSV; times in seconds, empty where the decay was
+    /// too short to fit.
+    pub fn to_csv(&self) -> String {
+        let time =
+            |fit: &Option<DecayFit>| fit.map_or(String::new(), |f| format!("{:.3}", f.decay_time));
+        let correlation =
+            |fit: &Option<DecayFit>| fit.map_or(String::new(), |f| format!("{:.4}", f.correlation));
+        let mut csv = "band,edt_s,t20_s,t20_r,t30_s,t30_r,curvature_permille,c50_db,c80_db,d50_percent,dynamic_range_db\n".to_owned();
+        for band in &self.bands {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.2},{:.2},{:.1},{:.1}\n",
+                band.band,
+                time(&band.edt),
+                time(&band.t20),
+                correlation(&band.t20),
+                time(&band.t30),
+                correlation(&band.t30),
+                band.curvature_permille
+                    .map_or(String::new(), |c| format!("{:.0}", c)),
+                band.c50,
+                band.c80,
+                band.d50,
+                band.dynamic_range_db
+            ));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// White noise decaying by 60 dB in `t60` seconds over a noise floor
+    /// `floor_db` below the start.
+    fn exponential_decay(t60: f64, seconds: f64, floor_db: f64) -> Vec<f32> {
+        let fs = SAMPLE_RATE as f64;
+        let rate = 60.0 / 20.0 / t60 * std::f64::consts::LN_10;
+        let floor = 10f64.powf(floor_db / 20.0);
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut uniform = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state as f64 / u64::MAX as f64 * 2.0 - 1.0) * 3f64.sqrt()
+        };
+        (0..(seconds * fs) as usize)
+            .map(|n| {
+                let envelope = (-rate * n as f64 / fs).exp();
+                (uniform() * envelope + uniform() * floor) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decay_times_and_clarity_match_an_exponential_decay() {
+        for t60 in [0.3, 0.5, 1.2] {
+            let ir = exponential_decay(t60, 3.0 * t60, -80.0);
+            let report = analyze(&ir, SAMPLE_RATE).unwrap();
+            let broadband = &report.bands[0];
+            assert_eq!(broadband.center, None);
+            for fit in [broadband.edt, broadband.t20, broadband.t30] {
+                let fit = fit.unwrap();
+                assert!(
+                    (fit.decay_time as f64 / t60 - 1.0).abs() < 0.05,
+                    "{} vs {}",
+                    fit.decay_time,
+                    t60
+                );
+                assert!(fit.correlation < -0.99);
+            }
+            assert!(broadband.curvature_permille.unwrap().abs() < 50.0);
+            // energy before t of an exponential decay: 1 - 10^(-6t/T60)
+            let clarity = |t: f64| 10.0 * (10f64.powf(6.0 * t / t60) - 1.0).log10();
+            assert!((broadband.c50 as f64 - clarity(0.05)).abs() < 0.5);
+            assert!((broadband.c80 as f64 - clarity(0.08)).abs() < 0.5);
+            let d50 = 100.0 * (1.0 - 10f64.powf(-6.0 * 0.05 / t60));
+            assert!((broadband.d50 as f64 - d50).abs() < 2.0);
+            // the peak sample of uniform noise is 4.8 dB above its mean
+            assert!(
+                (broadband.dynamic_range_db - 84.8).abs() < 1.0,
+                "{}",
+                broadband.dynamic_range_db
+            );
+
+            let band = report
+                .bands
+                .iter()
+                .find(|band| band.center == Some(1000.0))
+                .unwrap();
+            let t30 = band.t30.unwrap().decay_time as f64;
+            assert!((t30 / t60 - 1.0).abs() < 0.1, "{} vs {}", t30, t60);
+        }
+    }
+
+    #[test]
+    fn rejects_short_or_silent_responses() {
+        assert!(analyze(&[1.0; 100], SAMPLE_RATE).is_err());
+        assert!(analyze(&[0.0; 48000], SAMPLE_RATE).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+//! Single-tone distortion and noise measurement: THD, THD+N, SNR, SINAD and
+//! ENOB from a Blackman-Harris windowed spectrum.
+
+use std::f64::consts::PI;
+
+use super::fft::{blackman_harris, real_fft};
+
+/// Half width, in bins, of a windowed tone's main lobe.
+const LOBE: usize = 5;
+const MAX_FRAMES: usize = 65536;
+pub const MIN_FRAMES: usize = 1024;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct DistortionSettings {
+    /// Highest harmonic included in THD.
+    pub max_harmonic: usize,
+    /// Measurement bandwidth.
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+}
+
+impl Default for DistortionSettings {
+    fn default() -> Self {
+        Self {
+            max_harmonic: 10,
+            min_frequency: 20.0,
+            max_frequency: 20000.0,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Harmonic {
+    pub order: usize,
+    pub frequency: f64,
+    pub level_dbfs: f32,
+    /// Relative to the fundamental.
+    pub level_dbc: f32,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DistortionReport {
+    pub sample_rate: f32,
+    pub frames: usize,
+    pub fundamental: f64,
+    /// Fundamental level, 0 dBFS being a full-scale sine.
+    pub fundamental_dbfs: f32,
+    pub thd_percent: f32,
+    pub thd_db: f32,
+    pub thd_n_percent: f32,
+    pub thd_n_db: f32,
+    pub snr_db: f32,
+    pub sinad_db: f32,
+    pub enob: f32,
+    pub harmonics: Vec<Harmonic>,
+    /// Power spectrum in dBFS, one value per bin up to Nyquist.
+    #[serde(skip)]
+    pub spectrum: Vec<f32>,
+    #[serde(skip)]
+    pub bin_hz: f64,
+    /// Input with the fitted fundamental and DC removed.
+    #[serde(skip)]
+    pub residual: Vec<f32>,
+}
+
+fn power_db(power: f64) -> f32 {
+    (10.0 * power.max(1e-20).log10()) as f32
+}
+
+/// Least-squares fit of DC plus a sine at `frequency`; returns what's left.
+fn notch(samples: &[f64], frequency: f64, sample_rate: f64) -> Vec<f32> {
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    let w = 2.0 * PI * frequency / sample_rate;
+    let (mut cc, mut cs, mut ss, mut xc, mut xs) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (i, x) in samples.iter().enumerate() {
+        let (s, c) = (w * i as f64).sin_cos();
+        let x = x - mean;
+        cc += c * c;
+        cs += c * s;
+        ss += s * s;
+        xc += x * c;
+        xs += x * s;
+    }
+    let det = cc * ss - cs * cs;
+    let (a, b) = if det.abs() > f64::EPSILON {
+        ((xc * ss - xs * cs) / det, (xs * cc - xc * cs) / det)
+    } else {
+        (0.0, 0.0)
+    };
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let (s, c) = (w * i as f64).sin_cos();
+            (x - mean - a * c - b * s) as f32
+        })
+        .collect()
+}
+
+/// Measures the strongest tone in `samples`. Uses the largest power of two
+/// frames that fit, up to 65536.
+pub fn analyze(
+    samples: &[f32],
+    sample_rate: f32,
+    settings: &DistortionSettings,
+) -> Result<DistortionReport, String> {
+    if samples.len() < MIN_FRAMES {
+        return Err(format!("need at least {} samples", MIN_FRAMES));
+    }
+    let n = (1usize << (usize::BITS - 1 - samples.len().leading_zeros())).min(MAX_FRAMES);
+    let samples: Vec<f64> = samples[samples.len() - n..]
+        .iter()
+        .map(|v| *v as f64)
+        .collect();
+    let fs = sample_rate as f64;
+    let bin_hz = fs / n as f64;
+
+    let window = blackman_harris(n);
+    let mean = samples.iter().sum::<f64>() / n as f64;
+    let windowed: Vec<f64> = samples
+        .iter()
+        .zip(&window)
+        .map(|(x, w)| (x - mean) * w)
+        .collect();
+    // one-sided mean square per bin; a full-scale sine sums to 0.5
+    let norm = 2.0 / (n as f64 * window.iter().map(|w| w * w).sum::<f64>());
+    let power: Vec<f64> = real_fft(&windowed, n)[..n / 2]
+        .iter()
+        .map(|v| v.norm_sqr() * norm)
+        .collect();
+
+    let low = ((settings.min_frequency / bin_hz).ceil() as usize).max(LOBE + 1);
+    let high = ((settings.max_frequency / bin_hz).floor() as usize).min(n / 2 - 1);
+    if high <= low + 2 * LOBE {
+        return Err("measurement bandwidth is too narrow".to_owned());
+    }
+    let peak = (low..=high)
+        .max_by(|a, b| power[*a].total_cmp(&power[*b]))
+        .unwrap();
+    if power[peak] <= 1e-12 {
+        return Err("no signal".to_owned());
+    }
+    if peak < 3 * LOBE {
+        return Err("fundamental too low for this block length".to_owned());
+    }
+    // Gaussian interpolation between bins
+    let (l, c, r) = (
+        power[peak - 1].max(1e-30).ln(),
+        power[peak].ln(),
+        power[peak + 1].max(1e-30).ln(),
+    );
+    let denominator = l - 2.0 * c + r;
+    let offset = if denominator.abs() > f64::EPSILON {
+        (0.5 * (l - r) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let fundamental = (peak as f64 + offset) * bin_hz;
+
+    let tone_power = |center: usize| -> f64 {
+        power[center.saturating_sub(LOBE)..=(center + LOBE).min(n / 2 - 1)]
+            .iter()
+            .sum()
+    };
+    let total: f64 = power[low..=high].iter().sum();
+    let fundamental_power = tone_power(peak);
+
+    let mut harmonics = vec![];
+    let mut harmonic_power = 0.0;
+    for order in 2..=settings.max_harmonic {
+        let expected = fundamental * order as f64;
+        let bin = (expected / bin_hz).round() as usize;
+        if bin + LOBE > high {
+            break;
+        }
+        let center = (bin - 2..=bin + 2)
+            .max_by(|a, b| power[*a].total_cmp(&power[*b]))
+            .unwrap();
+        let p = tone_power(center);
+        harmonic_power += p;
+        harmonics.push(Harmonic {
+            order,
+            frequency: expected,
+            level_dbfs: power_db(2.0 * p),
+            level_dbc: power_db(p / fundamental_power),
+        });
+    }
+    let distortion_noise = (total - fundamental_power).max(1e-20);
+    let noise = (distortion_noise - harmonic_power).max(1e-20);
+    let thd = (harmonic_power / fundamental_power).sqrt();
+    let thd_n = (distortion_noise / fundamental_power).sqrt();
+    let sinad = power_db(fundamental_power / distortion_noise);
+
+    Ok(DistortionReport {
+        sample_rate,
+        frames: n,
+        fundamental,
+        fundamental_dbfs: power_db(2.0 * fundamental_power),
+        thd_percent: (thd * 100.0) as f32,
+        thd_db: power_db(thd * thd),
+        thd_n_percent: (thd_n * 100.0) as f32,
+        thd_n_db: power_db(thd_n * thd_n),
+        snr_db: power_db(fundamental_power / noise),
+        sinad_db: sinad,
+        enob: (sinad - 1.76) / 6.02,
+        harmonics,
+        spectrum: power.iter().map(|p| power_db(2.0 * p)).collect(),
+        bin_hz,
+        residual: notch(&samples, fundamental, fs),
+    })
+}
+
+impl DistortionReport {
+    /// Harmonic table as CSV.
+    pub fn harmonics_csv(&self) -> String {
+        let mut csv = "order,frequency_hz,level_dbfs,level_dbc\n".to_owned();
+        csv.push_str(&format!(
+            "1,{:.2},{:.2},0.00\n",
+            self.fundamental, self.fundamental_dbfs
+        ));
+        for h in &self.harmonics {
+            csv.push_str(&format!(
+                "{},{:.2},{:.2},{:.2}\n",
+                h.order, h.frequency, h.level_dbfs, h.level_dbc
+            ));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// `frames` of a sine at `amplitude` plus harmonics given as (order, dBc),
+    /// and uniform white noise with RMS `noise`.
+    fn tone(
+        frames: usize,
+        frequency: f64,
+        amplitude: f64,
+        harmonics: &[(usize, f64)],
+        noise: f64,
+    ) -> Vec<f32> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..frames)
+            .map(|n| {
+                let phase = 2.0 * PI * frequency * n as f64 / SAMPLE_RATE as f64;
+                let mut v = amplitude * phase.sin();
+                for (order, dbc) in harmonics {
+                    v += amplitude * 10f64.powf(dbc / 20.0) * (*order as f64 * phase).sin();
+                }
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let uniform = state as f64 / u64::MAX as f64 * 2.0 - 1.0;
+                (v + uniform * noise * 3f64.sqrt()) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measures_known_harmonics_and_noise() {
+        // harmonics at -40 and -50 dBc; noise 70 dB below the fundamental
+        // within the default 20 Hz - 20 kHz band
+        let amplitude = 0.5;
+        let band = (20000.0 - 20.0) / (SAMPLE_RATE as f64 / 2.0);
+        let noise = (amplitude * amplitude / 2.0 * 1e-7 / band).sqrt();
+        let samples = tone(65536, 1000.0, amplitude, &[(2, -40.0), (3, -50.0)], noise);
+        let report = analyze(&samples, SAMPLE_RATE, &DistortionSettings::default()).unwrap();
+
+        let distortion: f32 = 1e-4 + 1e-5;
+        let thd_n = distortion + 1e-7;
+        let sinad = -10.0 * thd_n.log10();
+        assert_eq!(report.frames, 65536);
+        assert!((report.fundamental - 1000.0).abs() < 0.05);
+        assert!((report.fundamental_dbfs + 6.02).abs() < 0.05);
+        assert!((report.thd_percent - distortion.sqrt() * 100.0).abs() < 0.01);
+        assert!((report.thd_db - 10.0 * distortion.log10()).abs() < 0.1);
+        assert!((report.thd_n_percent - thd_n.sqrt() * 100.0).abs() < 0.01);
+        assert!((report.snr_db - 70.0).abs() < 0.5, "{}", report.snr_db);
+        assert!((report.sinad_db - sinad).abs() < 0.1);
+        assert!((report.enob - (sinad - 1.76) / 6.02).abs() < 0.02);
+        assert!((report.harmonics[0].level_dbc + 40.0).abs() < 0.1);
+        assert!((report.harmonics[1].level_dbc + 50.0).abs() < 0.1);
+        assert!(report.harmonics[2].level_dbc < -90.0);
+    }
+
+    #[test]
+    fn a_clean_sine_has_no_distortion() {
+        let samples = tone(16384, 1000.0, 1.0, &[], 0.0);
+        let report = analyze(&samples, SAMPLE_RATE, &DistortionSettings::default()).unwrap();
+        assert_eq!(report.frames, 16384);
+        assert!(report.fundamental_dbfs.abs() < 0.05);
+        assert!(report.thd_db < -100.0, "{}", report.thd_db);
+        // the window's -92 dB side lobes bound the noise floor
+        assert!(report.sinad_db > 85.0, "{}", report.sinad_db);
+    }
+
+    #[test]
+    fn rejects_unmeasurable_input() {
+        let settings = DistortionSettings::default();
+        let short = tone(MIN_FRAMES - 1, 1000.0, 0.5, &[], 0.0);
+        assert!(analyze(&short, SAMPLE_RATE, &settings).is_err());
+
+        let narrow = DistortionSettings {
+            min_frequency: 1000.0,
+            max_frequency: 1100.0,
+            ..settings.clone()
+        };
+        let samples = tone(4096, 1000.0, 0.5, &[], 0.0);
+        assert_eq!(
+            analyze(&samples, SAMPLE_RATE, &narrow).unwrap_err(),
+            "measurement bandwidth is too narrow"
+        );
+
+        assert_eq!(
+            analyze(&[0.0; 4096], SAMPLE_RATE, &settings).unwrap_err(),
+            "no signal"
+        );
+
+        // 300 Hz is within three main lobes of DC in a 1024-frame block
+        let low = DistortionSettings {
+            min_frequency: 0.0,
+            ..settings
+        };
+        let samples = tone(1024, 300.0, 0.5, &[], 0.0);
+        assert_eq!(
+            analyze(&samples, SAMPLE_RATE, &low).unwrap_err(),
+            "fundamental too low for this block length"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Radix-2 FFT and analysis windows.
+
+use std::f64::consts::PI;
+
+use super::complex::Complex;
+
+/// In-place forward transform; `data.len()` must be a power of two.
+pub fn fft(data: &mut [Complex]) {
+    transform(data, -1.0);
+}
+
+/// In-place inverse transform, scaled by `1 / n`.
+pub fn ifft(data: &mut [Complex]) {
+    transform(data, 1.0);
+    let scale = 1.0 / data.len() as f64;
+    data.iter_mut().for_each(|v| *v = v.scale(scale));
+}
+
+fn transform(data: &mut [Complex], sign: f64) {
+    let n = data.len();
+    assert!(
+        n.is_power_of_two(),
+        "fft length {} is not a power of two",
+        n
+    );
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = Complex::from_phase(sign * 2.0 * PI / len as f64);
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::from(1.0);
+            for k in 0..len / 2 {
+                let a = data[start + k];
+                let b = data[start + k + len / 2] * w;
+                data[start + k] = a + b;
+                data[start + k + len / 2] = a - b;
+                w = w * step;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Spectrum of real `samples`, zero-padded to `n`.
+pub fn real_fft(samples: &[f64], n: usize) -> Vec<Complex> {
+    let mut data: Vec<Complex> = samples.iter().take(n).map(|v| Complex::from(*v)).collect();
+    data.resize(n, Complex::default());
+    fft(&mut data);
+    data
+}
+
+/// 4-term Blackman-Harris window, -92 dB side lobes.
+pub fn blackman_harris(n: usize) -> Vec<f64> {
+    let (a0, a1, a2, a3) = (0.35875, 0.48829, 0.14128, 0.01168);
+    (0..n)
+        .map(|i| {
+            let x = 2.0 * PI * i as f64 / n as f64;
+            a0 - a1 * x.cos() + a2 * (2.0 * x).cos() - a3 * (3.0 * x).cos()
+        })
+        .collect()
+}
+
+/// Periodic Hann window.
+pub fn hann(n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos())
+        .collect()
+}
@@ -0,0 +1,422 @@
+//! Loudness after ITU-R BS.1770-4 and EBU R128 / Tech 3342.
+
+use std::collections::VecDeque;
+
+use super::biquad::Biquad;
+
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+const LRA_RELATIVE_GATE: f64 = -20.0;
+/// Energies are accumulated in 100 ms sub-blocks.
+const SUB_BLOCK_SECONDS: f32 = 0.1;
+const MOMENTARY_BLOCKS: usize = 4;
+const SHORT_TERM_BLOCKS: usize = 30;
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+const TRUE_PEAK_TAPS_PER_PHASE: usize = 12;
+
+/// Pre-filter and RLB high-pass of BS.1770, derived for any sample rate.
+fn k_weighting(sample_rate: f32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+    [shelf, high_pass]
+}
+
+/// BS.1770 channel weight, assuming the L R C LFE Ls Rs order for 6 channels.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (6, 4) | (6, 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn to_lufs(power: f64) -> f64 {
+    if power <= 0.0 {
+        f64::NEG_INFINITY
+    } else {
+        -0.691 + 10.0 * power.log10()
+    }
+}
+
+/// Polyphase windowed-sinc interpolator used for the true-peak estimate.
+#[derive(Clone)]
+struct Oversampler {
+    phases: Vec<Vec<f32>>,
+    history: VecDeque<f32>,
+}
+
+impl Oversampler {
+    fn new() -> Self {
+        let taps = TRUE_PEAK_TAPS_PER_PHASE * TRUE_PEAK_OVERSAMPLING;
+        let center = (taps - 1) as f32 / 2.0;
+        let phases = (0..TRUE_PEAK_OVERSAMPLING)
+            .map(|phase| {
+                (0..TRUE_PEAK_TAPS_PER_PHASE)
+                    .map(|tap| {
+                        let n = (tap * TRUE_PEAK_OVERSAMPLING + phase) as f32;
+                        let x = (n - center) / TRUE_PEAK_OVERSAMPLING as f32;
+                        let sinc = if x.abs() < 1e-6 {
+                            1.0
+                        } else {
+                            (std::f32::consts::PI * x).sin() / (std::f32::consts::PI * x)
+                        };
+                        let window =
+                            0.5 - 0.5 * (2.0 * std::f32::consts::PI * n / (taps - 1) as f32).cos();
+                        sinc * window
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            phases,
+            history: VecDeque::from(vec![0.0; TRUE_PEAK_TAPS_PER_PHASE]),
+        }
+    }
+
+    /// Largest absolute value among the interpolated samples after `sample`.
+    fn push(&mut self, sample: f32) -> f32 {
+        self.history.pop_front();
+        self.history.push_back(sample);
+        self.phases
+            .iter()
+            .map(|phase| {
+                phase
+                    .iter()
+                    .zip(self.history.iter().rev())
+                    .map(|(c, x)| c * x)
+                    .sum::<f32>()
+                    .abs()
+            })
+            .fold(sample.abs(), f32::max)
+    }
+}
+
+/// One point of the loudness history, every 100 ms.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug)]
+pub struct LoudnessPoint {
+    pub time: f32,
+    pub momentary: f32,
+    pub short_term: f32,
+}
+
+/// Summary of a measurement, serialized as the JSON report.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct LoudnessReport {
+    pub duration: f32,
+    pub integrated: Option<f32>,
+    pub loudness_range: Option<f32>,
+    pub max_momentary: Option<f32>,
+    pub max_short_term: Option<f32>,
+    pub true_peak: Option<f32>,
+    pub history: Vec<LoudnessPoint>,
+}
+
+/// Streaming meter; feed it planar frames with `process` in any block size.
+#[derive(Clone)]
+pub struct LoudnessMeter {
+    sample_rate: f32,
+    filters: Vec<[Biquad; 2]>,
+    oversamplers: Vec<Oversampler>,
+    sub_block_len: usize,
+    sub_block_pos: usize,
+    sub_block_energy: Vec<f64>,
+    /// Weighted channel power of the latest sub-blocks.
+    recent: VecDeque<f64>,
+    /// Powers of all 400 ms gating blocks, for the integrated loudness.
+    gating_blocks: Vec<f64>,
+    /// Short-term loudness values, for the loudness range.
+    short_term_values: Vec<f64>,
+    frames: usize,
+    true_peak: f32,
+    history: Vec<LoudnessPoint>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            sample_rate,
+            filters: (0..channels).map(|_| k_weighting(sample_rate)).collect(),
+            oversamplers: (0..channels).map(|_| Oversampler::new()).collect(),
+            sub_block_len: ((sample_rate * SUB_BLOCK_SECONDS) as usize).max(1),
+            sub_block_pos: 0,
+            sub_block_energy: vec![0.0; channels],
+            recent: VecDeque::with_capacity(SHORT_TERM_BLOCKS),
+            gating_blocks: vec![],
+            short_term_values: vec![],
+            frames: 0,
+            true_peak: 0.0,
+            history: vec![],
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate, self.channels());
+    }
+
+    /// Processes one block; `channels[c]` holds the samples of channel `c`.
+    pub fn process(&mut self, channels: &[Vec<f32>]) {
+        let frames = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+        let n = self.channels();
+        for i in 0..frames {
+            for c in 0..n {
+                let x = channels.get(c).map_or(0.0, |samples| samples[i]);
+                self.true_peak = self.true_peak.max(self.oversamplers[c].push(x));
+                let [shelf, high_pass] = &mut self.filters[c];
+                let y = high_pass.process(shelf.process(x as f64));
+                self.sub_block_energy[c] += y * y;
+            }
+            self.sub_block_pos += 1;
+            self.frames += 1;
+            if self.sub_block_pos == self.sub_block_len {
+                self.finish_sub_block();
+            }
+        }
+    }
+
+    fn finish_sub_block(&mut self) {
+        let n = self.channels();
+        let power = self
+            .sub_block_energy
+            .iter()
+            .enumerate()
+            .map(|(c, e)| channel_weight(c, n) * e / self.sub_block_len as f64)
+            .sum::<f64>();
+        self.sub_block_energy.iter_mut().for_each(|e| *e = 0.0);
+        self.sub_block_pos = 0;
+
+        if self.recent.len() == SHORT_TERM_BLOCKS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(power);
+
+        if self.recent.len() >= MOMENTARY_BLOCKS {
+            self.gating_blocks.push(self.window_power(MOMENTARY_BLOCKS));
+        }
+        if self.recent.len() == SHORT_TERM_BLOCKS {
+            self.short_term_values
+                .push(to_lufs(self.window_power(SHORT_TERM_BLOCKS)));
+        }
+        self.history.push(LoudnessPoint {
+            time: self.frames as f32 / self.sample_rate,
+            momentary: self.momentary().unwrap_or(f32::NEG_INFINITY),
+            short_term: self.short_term().unwrap_or(f32::NEG_INFINITY),
+        });
+    }
+
+    fn window_power(&self, blocks: usize) -> f64 {
+        self.recent.iter().rev().take(blocks).sum::<f64>() / blocks as f64
+    }
+
+    /// Loudness of the last 400 ms, in LUFS.
+    pub fn momentary(&self) -> Option<f32> {
+        (self.recent.len() >= MOMENTARY_BLOCKS)
+            .then(|| to_lufs(self.window_power(MOMENTARY_BLOCKS)) as f32)
+    }
+
+    /// Loudness of the last 3 s, in LUFS.
+    pub fn short_term(&self) -> Option<f32> {
+        (self.recent.len() >= SHORT_TERM_BLOCKS)
+            .then(|| to_lufs(self.window_power(SHORT_TERM_BLOCKS)) as f32)
+    }
+
+    /// Gated loudness of everything processed so far, in LUFS.
+    pub fn integrated(&self) -> Option<f32> {
+        let above_absolute: Vec<f64> = self
+            .gating_blocks
+            .iter()
+            .copied()
+            .filter(|p| to_lufs(*p) > ABSOLUTE_GATE)
+            .collect();
+        if above_absolute.is_empty() {
+            return None;
+        }
+        let mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+        let relative_gate = to_lufs(mean) + RELATIVE_GATE;
+        let gated: Vec<f64> = above_absolute
+            .into_iter()
+            .filter(|p| to_lufs(*p) > relative_gate)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+        Some(to_lufs(gated.iter().sum::<f64>() / gated.len() as f64) as f32)
+    }
+
+    /// Spread between the 10th and 95th percentile of gated short-term loudness, in LU.
+    pub fn loudness_range(&self) -> Option<f32> {
+        let above_absolute: Vec<f64> = self
+            .short_term_values
+            .iter()
+            .copied()
+            .filter(|l| *l > ABSOLUTE_GATE)
+            .collect();
+        if above_absolute.is_empty() {
+            return None;
+        }
+        let mean_power = above_absolute
+            .iter()
+            .map(|l| 10f64.powf((l + 0.691) / 10.0))
+            .sum::<f64>()
+            / above_absolute.len() as f64;
+        let relative_gate = to_lufs(mean_power) + LRA_RELATIVE_GATE;
+        let mut gated: Vec<f64> = above_absolute
+            .into_iter()
+            .filter(|l| *l > relative_gate)
+            .collect();
+        if gated.len() < 2 {
+            return None;
+        }
+        gated.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| gated[((gated.len() - 1) as f64 * p).round() as usize];
+        Some((percentile(0.95) - percentile(0.10)) as f32)
+    }
+
+    /// Oversampled peak of everything processed so far, in dBTP.
+    pub fn true_peak(&self) -> Option<f32> {
+        (self.frames > 0).then(|| 20.0 * self.true_peak.max(1e-10).log10())
+    }
+
+    pub fn report(&self) -> LoudnessReport {
+        let max = |values: &mut dyn Iterator<Item = f32>| {
+            values
+                .filter(|v| v.is_finite())
+                .fold(None, |acc: Option<f32>, v| {
+                    Some(acc.map_or(v, |a| a.max(v)))
+                })
+        };
+        LoudnessReport {
+            duration: self.frames as f32 / self.sample_rate,
+            integrated: self.integrated(),
+            loudness_range: self.loudness_range(),
+            max_momentary: max(&mut self.history.iter().map(|p| p.momentary)),
+            max_short_term: max(&mut self.history.iter().map(|p| p.short_term)),
+            true_peak: self.true_peak(),
+            history: self.history.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// `seconds` of a sine at `dbfs` peak on `channels` identical channels.
+    fn sine(frequency: f32, dbfs: f32, seconds: f32, channels: usize) -> Vec<Vec<f32>> {
+        let amplitude = 10f32.powf(dbfs / 20.0);
+        let samples: Vec<f32> = (0..(seconds * SAMPLE_RATE) as usize)
+            .map(|n| {
+                let t = n as f32 / SAMPLE_RATE;
+                amplitude * (std::f32::consts::TAU * frequency * t).sin()
+            })
+            .collect();
+        vec![samples; channels]
+    }
+
+    fn measure(parts: &[Vec<Vec<f32>>]) -> LoudnessMeter {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, parts[0].len());
+        for part in parts {
+            meter.process(part);
+        }
+        meter
+    }
+
+    #[test]
+    fn stereo_sine_at_minus_23_dbfs_reads_minus_23_lufs() {
+        // EBU Tech 3341, test 1
+        let meter = measure(&[sine(997.0, -23.0, 5.0, 2)]);
+        let integrated = meter.integrated().unwrap();
+        assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+        let momentary = meter.momentary().unwrap();
+        assert!((momentary + 23.0).abs() < 0.1, "{}", momentary);
+        let short_term = meter.short_term().unwrap();
+        assert!((short_term + 23.0).abs() < 0.1, "{}", short_term);
+    }
+
+    #[test]
+    fn relative_gate_ignores_quiet_passages() {
+        // EBU Tech 3341, test 3 at half the length: the -36 dBFS parts fall
+        // below the -10 LU gate
+        let meter = measure(&[
+            sine(997.0, -36.0, 5.0, 2),
+            sine(997.0, -23.0, 30.0, 2),
+            sine(997.0, -36.0, 5.0, 2),
+        ]);
+        let integrated = meter.integrated().unwrap();
+        assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+    }
+
+    #[test]
+    fn relative_gate_keeps_passages_within_10_lu() {
+        // EBU Tech 3341, test 5 at a fifth of the length: the -26 dBFS parts
+        // count towards the result
+        let meter = measure(&[
+            sine(997.0, -26.0, 4.0, 2),
+            sine(997.0, -20.0, 4.02, 2),
+            sine(997.0, -26.0, 4.0, 2),
+        ]);
+        let integrated = meter.integrated().unwrap();
+        assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+    }
+
+    #[test]
+    fn absolute_gate_ignores_silence_and_very_quiet_signals() {
+        // long enough for the blocks across the edge not to matter
+        let silence = vec![vec![0.0; 5 * SAMPLE_RATE as usize]; 2];
+        let meter = measure(&[sine(997.0, -23.0, 20.0, 2), silence]);
+        let integrated = meter.integrated().unwrap();
+        assert!((integrated + 23.0).abs() < 0.1, "{}", integrated);
+
+        let quiet = measure(&[sine(997.0, -80.0, 5.0, 2)]);
+        assert_eq!(quiet.integrated(), None);
+    }
+
+    #[test]
+    fn true_peak_catches_inter_sample_peaks() {
+        // a quarter of the sample rate, sampled 45° off its peaks: every
+        // sample sits at -3 dB of the true peak
+        let samples: Vec<f32> = (0..SAMPLE_RATE as usize)
+            .map(|n| {
+                let phase = std::f32::consts::FRAC_PI_2 * (n % 4) as f32;
+                (phase + std::f32::consts::FRAC_PI_4).sin()
+            })
+            .collect();
+        let sample_peak = samples.iter().fold(0.0f32, |a, b| a.max(b.abs()));
+        assert!((20.0 * sample_peak.log10() + 3.01).abs() < 0.01);
+
+        let meter = measure(&[vec![samples]]);
+        let true_peak = meter.true_peak().unwrap();
+        assert!(true_peak.abs() < 0.5, "{}", true_peak);
+    }
+}
@@ -0,0 +1,158 @@
+//! Level meter ballistics. All levels are reported in dBFS.
+
+/// Floor reported for silence.
+pub const MIN_DB: f32 = -120.0;
+/// Samples at or above this magnitude count as clipped; the byte samples of
+/// the analyser top out at 127/128.
+pub const CLIP_LEVEL: f32 = 0.99;
+
+pub fn to_db(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        MIN_DB
+    } else {
+        (20.0 * amplitude.log10()).max(MIN_DB)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MeterSettings {
+    /// Time constant of the RMS average.
+    pub rms_ms: f32,
+    /// VU rise time to 99%.
+    pub vu_ms: f32,
+    /// PPM integration time (attack to -1 dB of a steady tone).
+    pub ppm_attack_ms: f32,
+    /// PPM fall back rate.
+    pub ppm_decay_db_per_s: f32,
+    pub peak_hold_s: f32,
+    pub peak_decay_db_per_s: f32,
+}
+
+impl Default for MeterSettings {
+    fn default() -> Self {
+        Self {
+            rms_ms: 300.0,
+            vu_ms: 300.0,
+            ppm_attack_ms: 10.0,
+            ppm_decay_db_per_s: 20.0 / 1.7,
+            peak_hold_s: 2.0,
+            peak_decay_db_per_s: 20.0,
+        }
+    }
+}
+
+/// Readings of one channel.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Levels {
+    pub peak: f32,
+    pub peak_hold: f32,
+    pub rms: f32,
+    pub vu: f32,
+    pub ppm: f32,
+    pub clipped: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelMeter {
+    peak_db: f32,
+    hold_db: f32,
+    hold_age: f32,
+    mean_square: f32,
+    vu_stage: [f32; 2],
+    ppm: f32,
+    ppm_db: f32,
+    clipped: bool,
+}
+
+impl Default for ChannelMeter {
+    fn default() -> Self {
+        Self {
+            peak_db: MIN_DB,
+            hold_db: MIN_DB,
+            hold_age: 0.0,
+            mean_square: 0.0,
+            vu_stage: [0.0; 2],
+            ppm: 0.0,
+            ppm_db: MIN_DB,
+            clipped: false,
+        }
+    }
+}
+
+/// One-pole smoothing coefficient for time constant `tau` seconds.
+fn coefficient(tau: f32, sample_rate: f32) -> f32 {
+    if tau <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (tau * sample_rate)).exp()
+    }
+}
+
+impl ChannelMeter {
+    /// Feeds `samples` (new audio only) covering `samples.len() / sample_rate` seconds.
+    pub fn process(&mut self, samples: &[f32], sample_rate: f32, settings: &MeterSettings) {
+        if samples.is_empty() {
+            return;
+        }
+        let seconds = samples.len() as f32 / sample_rate;
+        let rms_coef = coefficient(settings.rms_ms / 1000.0, sample_rate);
+        // two critically damped poles reach 99% after about 6.64 time constants
+        let vu_coef = coefficient(settings.vu_ms / 1000.0 / 6.64, sample_rate);
+        // a one-pole integrator reaches -1 dB after ln(1 / (1 - 10^(-1/20))) time constants
+        let ppm_coef = coefficient(settings.ppm_attack_ms / 1000.0 / 2.216, sample_rate);
+
+        let mut block_peak = 0.0f32;
+        let mut ppm_peak = 0.0f32;
+        for x in samples {
+            let magnitude = x.abs();
+            block_peak = block_peak.max(magnitude);
+            if magnitude >= CLIP_LEVEL {
+                self.clipped = true;
+            }
+            self.mean_square = rms_coef * self.mean_square + (1.0 - rms_coef) * x * x;
+            self.vu_stage[0] = vu_coef * self.vu_stage[0] + (1.0 - vu_coef) * magnitude;
+            self.vu_stage[1] = vu_coef * self.vu_stage[1] + (1.0 - vu_coef) * self.vu_stage[0];
+            // attack only; the fall back is linear in dB below
+            if magnitude > self.ppm {
+                self.ppm = ppm_coef * self.ppm + (1.0 - ppm_coef) * magnitude;
+            }
+            ppm_peak = ppm_peak.max(self.ppm);
+        }
+
+        let fallen = self.ppm_db - settings.ppm_decay_db_per_s * seconds;
+        self.ppm_db = fallen.max(to_db(ppm_peak));
+        self.ppm = 10f32.powf(self.ppm_db / 20.0);
+
+        let block_db = to_db(block_peak);
+        self.peak_db = (self.peak_db - settings.peak_decay_db_per_s * seconds).max(block_db);
+        self.hold_age += seconds;
+        if block_db >= self.hold_db {
+            self.hold_db = block_db;
+            self.hold_age = 0.0;
+        } else if self.hold_age > settings.peak_hold_s {
+            self.hold_db = self.peak_db;
+        }
+    }
+
+    pub fn levels(&self) -> Levels {
+        Levels {
+            peak: self.peak_db,
+            peak_hold: self.hold_db,
+            rms: to_db(self.mean_square.sqrt()),
+            // a sine's rectified average is 2/pi of its peak; VU reads sine RMS
+            vu: to_db(self.vu_stage[1] * std::f32::consts::PI / 2.0 / std::f32::consts::SQRT_2),
+            ppm: self.ppm_db,
+            clipped: self.clipped,
+        }
+    }
+
+    pub fn reset_clip(&mut self) {
+        self.clipped = false;
+    }
+
+    pub fn reset_hold(&mut self) {
+        self.hold_db = self.peak_db;
+        self.hold_age = 0.0;
+    }
+}
@@ -0,0 +1,122 @@
+//! Signal processing shared by the GUI and offline analysis. Everything in
+//! here works on plain `f32` samples in -1..1 and knows nothing about egui.
+
+pub mod acoustics;
+pub mod biquad;
+pub mod complex;
+pub mod distortion;
+pub mod fft;
+pub mod loudness;
+pub mod meter;
+pub mod octave;
+pub mod onset;
+pub mod pitch;
+pub mod spectrum;
+pub mod stats;
+pub mod sweep;
+pub mod vad;
+pub mod wav;
+
+use crate::chunk::{to_amplitude, Chunk};
+
+/// Samples of `channels`, one vector per channel, starting at frame `from`.
+pub fn planar(chunk: &Chunk, channels: &[usize], from: usize) -> Vec<Vec<f32>> {
+    channels
+        .iter()
+        .map(|channel| {
+            chunk
+                .channel(*channel)
+                .skip(from)
+                .map(to_amplitude)
+                .collect()
+        })
+        .collect()
+}
+
+/// Consecutive analyser snapshots overlap, so only the tail of a chunk is new
+/// audio. Returns how many frames at the end of `chunk` weren't in the chunk
+/// captured at `previous_time` (ms), judging by the capture clock.
+pub fn fresh_frames(chunk: &Chunk, previous_time: Option<f32>, sample_rate: f32) -> usize {
+    let frames = chunk.frames();
+    match previous_time {
+        Some(previous) if chunk.time > previous => {
+            let elapsed = (chunk.time - previous) / 1000.0 * sample_rate;
+            (elapsed.round() as usize).min(frames)
+        }
+        Some(_) => 0,
+        None => frames,
+    }
+}
+
+/// Averages `channels` of `chunk` into one mono signal.
+pub fn mixdown(chunk: &Chunk, channels: &[usize]) -> Vec<f32> {
+    let mut mono = vec![0.0; chunk.frames()];
+    if channels.is_empty() {
+        return mono;
+    }
+    for channel in channels {
+        for (acc, v) in mono.iter_mut().zip(chunk.channel(*channel)) {
+            *acc += to_amplitude(v);
+        }
+    }
+    let scale = 1.0 / channels.len() as f32;
+    mono.iter_mut().for_each(|v| *v *= scale);
+    mono
+}
+
+/// Fresh audio of consecutive chunks joined into one mono signal, remembering
+/// where each chunk's part starts so signal positions map back to chunks.
+#[derive(Clone, Debug, Default)]
+pub struct Stream {
+    pub samples: Vec<f32>,
+    /// (chunk id, offset in `samples`, first fresh frame, frames, time in ms)
+    spans: Vec<(usize, usize, usize, usize, f32)>,
+}
+
+impl Stream {
+    /// Mixes `active(chunk.channels)` of every chunk down to mono.
+    pub fn from_chunks(
+        chunks: &[Chunk],
+        sample_rate: f32,
+        active: impl Fn(usize) -> Vec<usize>,
+    ) -> Self {
+        let mut stream = Self::default();
+        let mut last_time = None;
+        for chunk in chunks {
+            let fresh = fresh_frames(chunk, last_time, sample_rate);
+            last_time = Some(chunk.time);
+            let mono = mixdown(chunk, &active(chunk.channels));
+            let skip = mono.len() - fresh;
+            stream
+                .spans
+                .push((chunk.id, stream.samples.len(), skip, mono.len(), chunk.time));
+            stream.samples.extend_from_slice(&mono[skip..]);
+        }
+        stream
+    }
+
+    fn span(&self, sample: usize) -> Option<&(usize, usize, usize, usize, f32)> {
+        let index = self
+            .spans
+            .partition_point(|span| span.1 <= sample)
+            .checked_sub(1)?;
+        self.spans.get(index)
+    }
+
+    /// Timeline position of `sample`: chunk id plus the fraction into it.
+    pub fn position(&self, sample: usize) -> f32 {
+        self.span(sample)
+            .map_or(0.0, |(id, offset, skip, frames, _)| {
+                *id as f32 + (skip + sample - offset) as f32 / (*frames).max(1) as f32
+            })
+    }
+
+    /// Capture time of `sample` in seconds, from the chunk timestamps.
+    pub fn time(&self, sample: usize, sample_rate: f32) -> f32 {
+        self.span(sample)
+            .map_or(0.0, |(_, offset, skip, frames, time)| {
+                let behind = frames - (skip + sample - offset);
+                time / 1000.0 - behind as f32 / sample_rate
+            })
+    }
+}
@@ -0,0 +1,388 @@
+//! Fractional-octave band levels after IEC 61260-1, with A/C/Z weighting
+//! from IEC 61672-1.
+
+use std::f64::consts::PI;
+
+use super::{biquad::Biquad, complex::Complex};
+
+/// Octave frequency ratio of the base-10 system.
+const G: f64 = 1.9952623149688795; // 10^(3/10)
+const BUTTERWORTH_ORDER: usize = 3;
+const MIN_FREQUENCY: f64 = 20.0;
+const MAX_FREQUENCY: f64 = 20000.0;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Weighting {
+    A,
+    C,
+    Z,
+}
+
+impl Weighting {
+    pub const ALL: [Weighting; 3] = [Self::A, Self::C, Self::Z];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::C => "C",
+            Self::Z => "Z",
+        }
+    }
+
+    /// Weighting gain at `frequency`, in dB.
+    pub fn gain_db(&self, frequency: f64) -> f64 {
+        let f2 = frequency * frequency;
+        let (p1, p2, p3, p4) = (
+            20.6f64.powi(2),
+            107.7f64.powi(2),
+            737.9f64.powi(2),
+            12194f64.powi(2),
+        );
+        match self {
+            Self::A => {
+                let r = p4 * f2 * f2 / ((f2 + p1) * ((f2 + p2) * (f2 + p3)).sqrt() * (f2 + p4));
+                20.0 * r.log10() + 2.0
+            }
+            Self::C => {
+                let r = p4 * f2 / ((f2 + p1) * (f2 + p4));
+                20.0 * r.log10() + 0.062
+            }
+            Self::Z => 0.0,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Averaging {
+    /// Exponential, 125 ms time constant.
+    Fast,
+    /// Exponential, 1 s time constant.
+    Slow,
+    /// Linear energy average since the last reset.
+    Leq,
+}
+
+impl Averaging {
+    pub const ALL: [Averaging; 3] = [Self::Fast, Self::Slow, Self::Leq];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Fast => "Fast",
+            Self::Slow => "Slow",
+            Self::Leq => "Leq",
+        }
+    }
+
+    fn time_constant(&self) -> Option<f64> {
+        match self {
+            Self::Fast => Some(0.125),
+            Self::Slow => Some(1.0),
+            Self::Leq => None,
+        }
+    }
+}
+
+/// Exact mid-band frequencies of 1/`fraction` octave bands between 20 Hz and
+/// 20 kHz (or 0.45 fs, whichever is lower).
+pub fn band_centers(fraction: usize, sample_rate: f64) -> Vec<f64> {
+    let b = fraction as f64;
+    let max = MAX_FREQUENCY.min(0.45 * sample_rate);
+    let center = |x: i32| {
+        if fraction % 2 == 1 {
+            1000.0 * G.powf(x as f64 / b)
+        } else {
+            1000.0 * G.powf((2 * x + 1) as f64 / (2.0 * b))
+        }
+    };
+    (-60..60)
+        .map(center)
+        .filter(|f| *f >= MIN_FREQUENCY * G.powf(-0.5 / b) && *f <= max)
+        .collect()
+}
+
+/// Nominal label for a band center, e.g. "31.5" or "1k".
+pub fn band_label(center: f64) -> String {
+    if center >= 1000.0 {
+        let khz = format!("{:.2}", center / 1000.0);
+        format!("{}k", khz.trim_end_matches('0').trim_end_matches('.'))
+    } else if center >= 100.0 {
+        format!("{:.0}", center)
+    } else {
+        format!("{:.1}", center)
+    }
+}
+
+/// Butterworth band-pass between `low` and `high` Hz as cascaded biquads,
+/// normalized to unity gain at the geometric center.
+fn butterworth_band_pass(low: f64, high: f64, sample_rate: f64) -> Vec<Biquad> {
+    let fs2 = 2.0 * sample_rate;
+    let warp = |f: f64| fs2 * (PI * f / sample_rate).tan();
+    let (w1, w2) = (warp(low), warp(high));
+    let w0_sqr = Complex::from(w1 * w2);
+    let bandwidth = w2 - w1;
+
+    let mut poles = vec![];
+    for k in 0..BUTTERWORTH_ORDER {
+        let theta = PI * (2 * k + 1 + BUTTERWORTH_ORDER) as f64 / (2 * BUTTERWORTH_ORDER) as f64;
+        let p = Complex::from_phase(theta).scale(bandwidth / 2.0);
+        let d = (p * p - w0_sqr).sqrt();
+        for s in [p + d, p - d] {
+            // bilinear transform
+            let z = (Complex::from(fs2) + s) / (Complex::from(fs2) - s);
+            if z.im > 0.0 {
+                poles.push(z);
+            }
+        }
+    }
+
+    let center = 2.0 * PI * (low * high).sqrt() / sample_rate;
+    let z = Complex::from_phase(center);
+    let z2 = z * z;
+    let mut sections: Vec<([f64; 3], [f64; 2])> = poles
+        .iter()
+        .map(|p| ([1.0, 0.0, -1.0], [-2.0 * p.re, p.norm_sqr()]))
+        .collect();
+    let gain: f64 = sections
+        .iter()
+        .map(|(b, a)| {
+            let numerator = z2.scale(b[0]) + z.scale(b[1]) + Complex::from(b[2]);
+            let denominator = z2 + z.scale(a[0]) + Complex::from(a[1]);
+            (numerator / denominator).abs()
+        })
+        .product();
+    let correction = gain.powf(-1.0 / sections.len().max(1) as f64);
+    sections
+        .iter_mut()
+        .for_each(|(b, _)| b.iter_mut().for_each(|v| *v *= correction));
+    sections
+        .into_iter()
+        .map(|(b, a)| Biquad::new(b, a))
+        .collect()
+}
+
+/// Band-pass filter for the 1/`fraction` octave band around `center`.
+pub fn band_filter(center: f64, fraction: usize, sample_rate: f64) -> Vec<Biquad> {
+    let half = G.powf(0.5 / fraction as f64);
+    butterworth_band_pass(center / half, center * half, sample_rate)
+}
+
+struct Band {
+    center: f64,
+    filters: Vec<Biquad>,
+    /// Mean square of the filtered signal under the chosen averaging.
+    mean_square: f64,
+    energy: f64,
+    peak_db: f32,
+}
+
+/// Real-time analyzer over a mono signal.
+pub struct OctaveAnalyzer {
+    pub fraction: usize,
+    pub sample_rate: f32,
+    pub weighting: Weighting,
+    pub averaging: Averaging,
+    bands: Vec<Band>,
+    frames: usize,
+}
+
+/// One row of the band table.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct BandLevel {
+    pub center: f64,
+    pub level: f32,
+    pub peak: f32,
+}
+
+impl OctaveAnalyzer {
+    pub fn new(
+        fraction: usize,
+        sample_rate: f32,
+        weighting: Weighting,
+        averaging: Averaging,
+    ) -> Self {
+        let bands = band_centers(fraction, sample_rate as f64)
+            .into_iter()
+            .map(|center| Band {
+                center,
+                filters: band_filter(center, fraction, sample_rate as f64),
+                mean_square: 0.0,
+                energy: 0.0,
+                peak_db: f32::NEG_INFINITY,
+            })
+            .collect();
+        Self {
+            fraction,
+            sample_rate,
+            weighting,
+            averaging,
+            bands,
+            frames: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(
+            self.fraction,
+            self.sample_rate,
+            self.weighting,
+            self.averaging,
+        );
+    }
+
+    pub fn reset_peaks(&mut self) {
+        self.bands
+            .iter_mut()
+            .for_each(|band| band.peak_db = f32::NEG_INFINITY);
+    }
+
+    pub fn process(&mut self, samples: &[f32]) {
+        let coefficient = self
+            .averaging
+            .time_constant()
+            .map(|tau| (-1.0 / (tau * self.sample_rate as f64)).exp());
+        for band in self.bands.iter_mut() {
+            for x in samples {
+                let y = band
+                    .filters
+                    .iter_mut()
+                    .fold(*x as f64, |acc, filter| filter.process(acc));
+                let square = y * y;
+                band.energy += square;
+                if let Some(c) = coefficient {
+                    band.mean_square = c * band.mean_square + (1.0 - c) * square;
+                }
+            }
+        }
+        self.frames += samples.len();
+        let levels = self.levels();
+        for (band, level) in self.bands.iter_mut().zip(levels) {
+            band.peak_db = band.peak_db.max(level.level);
+        }
+    }
+
+    /// Weighted band levels in dBFS (a full-scale sine reads -3 dB).
+    pub fn levels(&self) -> Vec<BandLevel> {
+        self.bands
+            .iter()
+            .map(|band| {
+                let mean_square = match self.averaging {
+                    Averaging::Leq => band.energy / self.frames.max(1) as f64,
+                    _ => band.mean_square,
+                };
+                let level = if mean_square > 0.0 {
+                    10.0 * mean_square.log10() + self.weighting.gain_db(band.center)
+                } else {
+                    f64::NEG_INFINITY
+                };
+                BandLevel {
+                    center: band.center,
+                    level: level as f32,
+                    peak: band.peak_db,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Band table as CSV.
+pub fn to_csv(levels: &[BandLevel], weighting: Weighting) -> String {
+    let mut csv = format!(
+        "center_hz,level_db{},peak_db{}\n",
+        weighting.label(),
+        weighting.label()
+    );
+    for band in levels {
+        csv.push_str(&format!(
+            "{:.2},{:.2},{:.2}\n",
+            band.center, band.level, band.peak
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Level of every third-octave band for a full-scale sine at `frequency`.
+    fn third_octave_levels(frequency: f64) -> Vec<BandLevel> {
+        let mut analyzer = OctaveAnalyzer::new(3, SAMPLE_RATE, Weighting::Z, Averaging::Leq);
+        let samples: Vec<f32> = (0..SAMPLE_RATE as usize)
+            .map(|n| (2.0 * PI * frequency * n as f64 / SAMPLE_RATE as f64).sin() as f32)
+            .collect();
+        analyzer.process(&samples);
+        analyzer.levels()
+    }
+
+    fn level_at(levels: &[BandLevel], center: f64) -> f32 {
+        levels
+            .iter()
+            .find(|band| (band.center / center - 1.0).abs() < 1e-6)
+            .map(|band| band.level)
+            .unwrap()
+    }
+
+    #[test]
+    fn band_centers_follow_the_base_10_series() {
+        let octaves = band_centers(1, SAMPLE_RATE as f64);
+        assert_eq!(octaves.len(), 11);
+        assert!((octaves[0] - 15.849).abs() < 1e-3);
+        assert!((octaves[10] - 15848.9).abs() < 0.1);
+        assert!(octaves.iter().any(|f| (f - 1000.0).abs() < 1e-9));
+
+        let thirds = band_centers(3, SAMPLE_RATE as f64);
+        assert_eq!(thirds.len(), 31);
+        assert!((thirds[0] - 19.953).abs() < 1e-3);
+        assert!((thirds[30] - 19952.6).abs() < 0.1);
+        for pair in thirds.windows(2) {
+            assert!((pair[1] / pair[0] - G.powf(1.0 / 3.0)).abs() < 1e-9);
+        }
+
+        // even fractions put 1 kHz on a band edge
+        let sixths = band_centers(6, SAMPLE_RATE as f64);
+        assert!(sixths.iter().all(|f| (f - 1000.0).abs() > 1.0));
+        assert!(sixths
+            .iter()
+            .any(|f| (f - 1000.0 * G.powf(1.0 / 12.0)).abs() < 1e-9));
+
+        // bands stop below 0.45 fs
+        let low_rate = band_centers(3, 16000.0);
+        assert!(*low_rate.last().unwrap() <= 7200.0);
+        assert!((low_rate.last().unwrap() - 6309.6).abs() < 0.1);
+    }
+
+    #[test]
+    fn weightings_match_the_iec_61672_tables() {
+        for (weighting, at_1k, at_100, at_10k) in [
+            (Weighting::A, 0.0, -19.1, -2.5),
+            (Weighting::C, 0.0, -0.3, -4.4),
+            (Weighting::Z, 0.0, 0.0, 0.0),
+        ] {
+            assert!((weighting.gain_db(1000.0) - at_1k).abs() < 0.01);
+            assert!((weighting.gain_db(100.0) - at_100).abs() < 0.1);
+            assert!((weighting.gain_db(10000.0) - at_10k).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn bands_pass_their_center_and_attenuate_their_neighbours() {
+        // a full-scale sine reads -3 dB in its own band
+        let levels = third_octave_levels(1000.0);
+        let own = level_at(&levels, 1000.0);
+        assert!((own + 3.01).abs() < 0.1, "{}", own);
+
+        let step = G.powf(1.0 / 3.0);
+        let adjacent = level_at(&levels, 1000.0 * step).max(level_at(&levels, 1000.0 / step));
+        assert!(adjacent < own - 15.0, "{}", adjacent);
+        let next =
+            level_at(&levels, 1000.0 * step * step).max(level_at(&levels, 1000.0 / step / step));
+        assert!(next < own - 35.0, "{}", next);
+
+        // Butterworth bands are 3 dB down at their edges
+        let edge = 1000.0 * G.powf(1.0 / 6.0);
+        let at_edge = level_at(&third_octave_levels(edge), 1000.0);
+        assert!((at_edge - own + 3.0).abs() < 0.5, "{}", at_edge);
+    }
+}
@@ -0,0 +1,311 @@
+//! Onset detection (spectral flux or complex domain), tempo estimation by
+//! autocorrelation of the detection function and dynamic-programming beat
+//! tracking (Ellis 2007).
+
+use super::{
+    complex::Complex,
+    fft::{hann, real_fft},
+};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum OnsetMethod {
+    SpectralFlux,
+    ComplexDomain,
+}
+
+impl OnsetMethod {
+    pub const ALL: [OnsetMethod; 2] = [Self::SpectralFlux, Self::ComplexDomain];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::SpectralFlux => "Spectral flux",
+            Self::ComplexDomain => "Complex domain",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct OnsetSettings {
+    pub method: OnsetMethod,
+    pub frame_size: usize,
+    pub hop_size: usize,
+    /// 0..1; higher finds more, weaker onsets.
+    pub sensitivity: f32,
+    /// Onsets closer than this are merged.
+    pub min_gap_ms: f32,
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+}
+
+impl Default for OnsetSettings {
+    fn default() -> Self {
+        Self {
+            method: OnsetMethod::SpectralFlux,
+            frame_size: 1024,
+            hop_size: 512,
+            sensitivity: 0.5,
+            min_gap_ms: 50.0,
+            min_bpm: 60.0,
+            max_bpm: 200.0,
+        }
+    }
+}
+
+/// Detection function, onsets and beats of one signal. Positions are in
+/// samples from the start of the analyzed signal.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct OnsetAnalysis {
+    /// Sample position of each detection function value.
+    pub positions: Vec<usize>,
+    /// Normalized to a maximum of 1.
+    pub detection: Vec<f32>,
+    pub onsets: Vec<usize>,
+    pub tempo: Option<f32>,
+    pub beats: Vec<usize>,
+}
+
+/// One value per hop, normalized to a maximum of 1.
+pub fn detection_function(samples: &[f32], settings: &OnsetSettings) -> Vec<f32> {
+    let n = settings.frame_size.next_power_of_two();
+    let hop = settings.hop_size.max(1);
+    let window = hann(n);
+    let bins = n / 2 + 1;
+    let mut previous: Vec<Complex> = vec![Complex::default(); bins];
+    let mut before: Vec<Complex> = vec![Complex::default(); bins];
+    let mut detection = vec![];
+    let mut start = 0;
+    while start + n <= samples.len() {
+        let frame: Vec<f64> = samples[start..start + n]
+            .iter()
+            .zip(&window)
+            .map(|(x, w)| *x as f64 * w)
+            .collect();
+        let spectrum = real_fft(&frame, n);
+        let value: f64 = match settings.method {
+            OnsetMethod::SpectralFlux => spectrum[..bins]
+                .iter()
+                .zip(&previous)
+                .map(|(x, p)| {
+                    // log compression keeps quiet partials from being drowned out
+                    let rise = (1.0 + 100.0 * x.abs()).ln() - (1.0 + 100.0 * p.abs()).ln();
+                    rise.max(0.0)
+                })
+                .sum(),
+            OnsetMethod::ComplexDomain => spectrum[..bins]
+                .iter()
+                .zip(previous.iter().zip(&before))
+                .map(|(x, (p, b))| {
+                    // rectified: only rising magnitudes count
+                    if x.abs() < p.abs() {
+                        return 0.0;
+                    }
+                    let phase = 2.0 * p.arg() - b.arg();
+                    (*x - Complex::from_phase(phase).scale(p.abs())).abs()
+                })
+                .sum(),
+        };
+        detection.push(value);
+        before = std::mem::replace(&mut previous, spectrum[..bins].to_vec());
+        start += hop;
+    }
+    // the first frame has nothing to compare against
+    if let Some(first) = detection.first_mut() {
+        *first = 0.0;
+    }
+    let max = detection.iter().copied().fold(0.0, f64::max);
+    detection
+        .iter()
+        .map(|v| if max > 0.0 { (v / max) as f32 } else { 0.0 })
+        .collect()
+}
+
+/// Local maxima above a moving median plus an offset set by `sensitivity`.
+pub fn pick_peaks(detection: &[f32], hop_seconds: f32, settings: &OnsetSettings) -> Vec<usize> {
+    let delta = 0.3 * (1.0 - settings.sensitivity.clamp(0.0, 1.0)) + 0.01;
+    let radius = ((0.1 / hop_seconds) as usize).max(1);
+    let min_gap = ((settings.min_gap_ms / 1000.0 / hop_seconds) as usize).max(1);
+    let mut onsets: Vec<usize> = vec![];
+    for i in 1..detection.len().saturating_sub(1) {
+        let value = detection[i];
+        if value < detection[i - 1] || value < detection[i + 1] {
+            continue;
+        }
+        let mut window: Vec<f32> =
+            detection[i.saturating_sub(radius)..(i + radius + 1).min(detection.len())].to_vec();
+        window.sort_by(f32::total_cmp);
+        let median = window[window.len() / 2];
+        if value <= median + delta {
+            continue;
+        }
+        match onsets.last() {
+            Some(last) if i - last < min_gap => {
+                if detection[*last] < value {
+                    *onsets.last_mut().unwrap() = i;
+                }
+            }
+            _ => onsets.push(i),
+        }
+    }
+    onsets
+}
+
+/// Tempo in BPM from the autocorrelation of `detection`, weighted towards
+/// 120 BPM to settle octave ambiguity.
+pub fn estimate_tempo(
+    detection: &[f32],
+    hop_seconds: f32,
+    settings: &OnsetSettings,
+) -> Option<f32> {
+    let lag = |bpm: f32| 60.0 / bpm / hop_seconds;
+    let (min_lag, max_lag) = (
+        lag(settings.max_bpm).floor().max(1.0) as usize,
+        lag(settings.min_bpm).ceil() as usize,
+    );
+    if detection.len() < max_lag * 2 {
+        return None;
+    }
+    let mean = detection.iter().sum::<f32>() / detection.len() as f32;
+    let centered: Vec<f32> = detection.iter().map(|v| v - mean).collect();
+    let preferred = lag(120.0);
+    let scores: Vec<f32> = (min_lag..=max_lag + 1)
+        .map(|lag| {
+            let ac: f32 = centered
+                .iter()
+                .zip(&centered[lag..])
+                .map(|(a, b)| a * b)
+                .sum();
+            let octaves = (lag as f32 / preferred).log2();
+            ac * (-0.5 * octaves * octaves).exp()
+        })
+        .collect();
+    let best = (1..scores.len() - 1).max_by(|a, b| scores[*a].total_cmp(&scores[*b]))?;
+    if scores[best] <= 0.0 {
+        return None;
+    }
+    // parabolic interpolation between lags
+    let (l, c, r) = (scores[best - 1], scores[best], scores[best + 1]);
+    let denominator = l - 2.0 * c + r;
+    let offset = if denominator.abs() > f32::EPSILON {
+        (0.5 * (l - r) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some(60.0 / ((min_lag + best) as f32 + offset) / hop_seconds)
+}
+
+/// Beats as detection function indices, spaced close to `bpm`.
+pub fn track_beats(detection: &[f32], hop_seconds: f32, bpm: f32) -> Vec<usize> {
+    const TIGHTNESS: f32 = 100.0;
+    let period = 60.0 / bpm / hop_seconds;
+    if detection.is_empty() || period < 1.0 {
+        return vec![];
+    }
+    let mut score = vec![0.0f32; detection.len()];
+    let mut from: Vec<Option<usize>> = vec![None; detection.len()];
+    for t in 0..detection.len() {
+        let earliest = (t as f32 - 2.0 * period).max(0.0) as usize;
+        let latest = (t as f32 - period / 2.0).floor();
+        let mut best: Option<(usize, f32)> = None;
+        if latest >= 0.0 {
+            for (previous, previous_score) in score
+                .iter()
+                .enumerate()
+                .take(latest as usize + 1)
+                .skip(earliest)
+            {
+                let interval = (t - previous) as f32 / period;
+                let value = previous_score - TIGHTNESS * interval.ln().powi(2);
+                if best.map_or(true, |(_, b)| value > b) {
+                    best = Some((previous, value));
+                }
+            }
+        }
+        score[t] = detection[t] + best.map_or(0.0, |(_, v)| v.max(0.0));
+        from[t] = best.filter(|(_, v)| *v > 0.0).map(|(p, _)| p);
+    }
+    // end on the best score within the last period
+    let tail = (detection.len() as f32 - period).max(0.0) as usize;
+    let mut beat = (tail..detection.len()).max_by(|a, b| score[*a].total_cmp(&score[*b]));
+    let mut beats = vec![];
+    while let Some(t) = beat {
+        beats.push(t);
+        beat = from[t];
+    }
+    beats.reverse();
+    beats
+}
+
+pub fn analyze(samples: &[f32], sample_rate: f32, settings: &OnsetSettings) -> OnsetAnalysis {
+    let detection = detection_function(samples, settings);
+    let hop_seconds = settings.hop_size.max(1) as f32 / sample_rate;
+    let onsets = pick_peaks(&detection, hop_seconds, settings);
+    let tempo = estimate_tempo(&detection, hop_seconds, settings);
+    let beats = tempo.map_or(vec![], |bpm| track_beats(&detection, hop_seconds, bpm));
+    let position = |i: usize| i * settings.hop_size + settings.frame_size / 2;
+    OnsetAnalysis {
+        positions: (0..detection.len()).map(position).collect(),
+        onsets: onsets.into_iter().map(position).collect(),
+        beats: beats.into_iter().map(position).collect(),
+        detection,
+        tempo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Clicks every `60 / bpm` seconds from 0.25 s on: 5 ms bursts of a
+    /// decaying 2 kHz tone. Returns the samples and the click positions.
+    fn click_train(bpm: f32, seconds: f32) -> (Vec<f32>, Vec<usize>) {
+        let mut samples = vec![0.0; (seconds * SAMPLE_RATE) as usize];
+        let period = (60.0 / bpm * SAMPLE_RATE) as usize;
+        let clicks: Vec<usize> = (SAMPLE_RATE as usize / 4..samples.len() - period / 2)
+            .step_by(period)
+            .collect();
+        let burst = (0.005 * SAMPLE_RATE) as usize;
+        for click in &clicks {
+            for i in 0..burst {
+                let t = i as f32 / SAMPLE_RATE;
+                samples[click + i] =
+                    (2.0 * std::f32::consts::PI * 2000.0 * t).sin() * (-t / 0.001).exp();
+            }
+        }
+        (samples, clicks)
+    }
+
+    #[test]
+    fn finds_every_click_and_the_tempo() {
+        for method in OnsetMethod::ALL {
+            let settings = OnsetSettings {
+                method,
+                ..Default::default()
+            };
+            let (samples, clicks) = click_train(100.0, 12.0);
+            let analysis = analyze(&samples, SAMPLE_RATE, &settings);
+            assert_eq!(analysis.onsets.len(), clicks.len(), "{:?}", method);
+            for (onset, click) in analysis.onsets.iter().zip(&clicks) {
+                // within one hop of the click
+                let error = *onset as i64 - *click as i64;
+                assert!(error.unsigned_abs() <= 512, "{:?}: {}", method, error);
+            }
+            let tempo = analysis.tempo.unwrap();
+            assert!((tempo - 100.0).abs() < 1.0, "{:?}: {}", method, tempo);
+            for pair in analysis.beats.windows(2) {
+                let interval = (pair[1] - pair[0]) as f32 / SAMPLE_RATE;
+                assert!((interval - 0.6).abs() < 0.02, "{:?}: {}", method, interval);
+            }
+        }
+    }
+
+    #[test]
+    fn silence_has_no_onsets_or_tempo() {
+        let analysis = analyze(&[0.0; 96000], SAMPLE_RATE, &OnsetSettings::default());
+        assert!(analysis.onsets.is_empty());
+        assert_eq!(analysis.tempo, None);
+        assert!(analysis.beats.is_empty());
+    }
+}
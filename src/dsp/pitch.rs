@@ -0,0 +1,232 @@
+/// Fundamental frequency of one analysis frame.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct PitchEstimate {
+    pub frequency: f32,
+    /// 0..1, one minus the YIN aperiodicity at the chosen lag.
+    pub confidence: f32,
+}
+
+/// Monophonic pitch estimator after de Cheveigné & Kawahara, "YIN, a
+/// fundamental frequency estimator for speech and music" (2002).
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct Yin {
+    /// Aperiodicity below which the first dip is accepted.
+    pub threshold: f32,
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+    /// Frames quieter than this RMS are reported as unvoiced.
+    pub silence_rms: f32,
+}
+
+impl Default for Yin {
+    fn default() -> Self {
+        Self {
+            threshold: 0.15,
+            min_frequency: 60.0,
+            max_frequency: 2000.0,
+            silence_rms: 0.005,
+        }
+    }
+}
+
+impl Yin {
+    pub fn estimate(&self, samples: &[f32], sample_rate: f32) -> Option<PitchEstimate> {
+        let n = samples.len();
+        let rms = (samples.iter().map(|v| v * v).sum::<f32>() / n.max(1) as f32).sqrt();
+        if rms < self.silence_rms {
+            return None;
+        }
+        let max_lag = ((sample_rate / self.min_frequency) as usize).min(n / 2);
+        let min_lag = ((sample_rate / self.max_frequency) as usize).max(2);
+        if max_lag <= min_lag + 1 {
+            return None;
+        }
+        let window = n - max_lag;
+
+        // difference function d(tau), then its cumulative mean normalized form d'(tau)
+        let mut cmnd = vec![1.0f32; max_lag + 1];
+        let mut running_sum = 0.0;
+        for tau in 1..=max_lag {
+            let d: f32 = (0..window)
+                .map(|i| {
+                    let delta = samples[i] - samples[i + tau];
+                    delta * delta
+                })
+                .sum();
+            running_sum += d;
+            cmnd[tau] = if running_sum > 0.0 {
+                d * tau as f32 / running_sum
+            } else {
+                1.0
+            };
+        }
+
+        let mut best = None;
+        let mut tau = min_lag;
+        while tau < max_lag {
+            if cmnd[tau] < self.threshold {
+                while tau + 1 < max_lag && cmnd[tau + 1] < cmnd[tau] {
+                    tau += 1;
+                }
+                best = Some(tau);
+                break;
+            }
+            tau += 1;
+        }
+        // no dip under the threshold: fall back to the global minimum, with low confidence
+        let tau = best.unwrap_or_else(|| {
+            (min_lag..max_lag)
+                .min_by(|a, b| cmnd[*a].total_cmp(&cmnd[*b]))
+                .unwrap_or(min_lag)
+        });
+
+        let refined = parabolic_peak(&cmnd, tau);
+        let confidence = (1.0 - cmnd[tau]).clamp(0.0, 1.0);
+        Some(PitchEstimate {
+            frequency: sample_rate / refined,
+            confidence,
+        })
+    }
+}
+
+/// Sub-sample position of the extremum at `i` from its two neighbours.
+fn parabolic_peak(values: &[f32], i: usize) -> f32 {
+    if i == 0 || i + 1 >= values.len() {
+        return i as f32;
+    }
+    let (a, b, c) = (values[i - 1], values[i], values[i + 1]);
+    let denominator = a - 2.0 * b + c;
+    if denominator.abs() < f32::EPSILON {
+        return i as f32;
+    }
+    i as f32 + 0.5 * (a - c) / denominator
+}
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Nearest equal-tempered note (A4 = 440 Hz) and the deviation from it in cents.
+pub fn note(frequency: f32) -> (String, f32) {
+    let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+    let nearest = midi.round();
+    let cents = (midi - nearest) * 100.0;
+    let nearest = nearest as i32;
+    let name = NOTE_NAMES[nearest.rem_euclid(12) as usize];
+    let octave = nearest.div_euclid(12) - 1;
+    (format!("{}{}", name, octave), cents)
+}
+
+/// Frequency of MIDI note `midi`.
+pub fn midi_frequency(midi: i32) -> f32 {
+    440.0 * 2f32.powf((midi - 69) as f32 / 12.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const FRAME: usize = 4096;
+
+    /// Sum of sines at multiples of `frequency` with the given amplitudes.
+    fn tone(frequency: f32, harmonics: &[f32]) -> Vec<f32> {
+        (0..FRAME)
+            .map(|n| {
+                let t = n as f32 / SAMPLE_RATE;
+                harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| {
+                        a * (std::f32::consts::TAU * frequency * (k + 1) as f32 * t).sin()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn cents(estimate: f32, expected: f32) -> f32 {
+        1200.0 * (estimate / expected).log2()
+    }
+
+    #[test]
+    fn estimates_sines_within_a_few_cents() {
+        let yin = Yin::default();
+        for frequency in [82.41, 220.0, 440.0, 1000.0, 1760.0] {
+            let estimate = yin.estimate(&tone(frequency, &[0.5]), SAMPLE_RATE).unwrap();
+            assert!(
+                cents(estimate.frequency, frequency).abs() < 5.0,
+                "{} Hz read as {} Hz",
+                frequency,
+                estimate.frequency
+            );
+            assert!(estimate.confidence > 0.9, "{:?}", estimate);
+        }
+    }
+
+    #[test]
+    fn finds_the_fundamental_of_harmonic_tones() {
+        let yin = Yin::default();
+        // the second harmonic is louder than the fundamental
+        for frequency in [110.0, 196.0, 330.0] {
+            let samples = tone(frequency, &[0.3, 0.5, 0.2, 0.1]);
+            let estimate = yin.estimate(&samples, SAMPLE_RATE).unwrap();
+            assert!(
+                cents(estimate.frequency, frequency).abs() < 5.0,
+                "{} Hz read as {} Hz",
+                frequency,
+                estimate.frequency
+            );
+        }
+    }
+
+    #[test]
+    fn silence_and_noise_have_no_confident_pitch() {
+        let yin = Yin::default();
+        assert_eq!(yin.estimate(&vec![0.0; FRAME], SAMPLE_RATE), None);
+        assert_eq!(yin.estimate(&tone(440.0, &[0.001]), SAMPLE_RATE), None);
+
+        // xorshift noise, so the test doesn't depend on a random seed
+        let mut state = 0x2545_f491u32;
+        let noise: Vec<f32> = (0..FRAME)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect();
+        let estimate = yin.estimate(&noise, SAMPLE_RATE);
+        assert!(
+            estimate.map_or(true, |e| e.confidence < 1.0 - yin.threshold),
+            "{:?}",
+            estimate
+        );
+    }
+
+    #[test]
+    fn names_notes_around_a4() {
+        assert_eq!(midi_frequency(69), 440.0);
+        assert!((midi_frequency(57) - 220.0).abs() < 1e-3);
+        assert!((midi_frequency(81) - 880.0).abs() < 1e-3);
+        assert!((midi_frequency(60) - 261.626).abs() < 1e-2);
+
+        let (name, cents) = note(440.0);
+        assert_eq!(name, "A4");
+        assert!(cents.abs() < 1e-3);
+        let (name, cents) = note(midi_frequency(70));
+        assert_eq!(name, "A#4");
+        assert!(cents.abs() < 1e-2);
+        let (name, cents) = note(midi_frequency(68));
+        assert_eq!(name, "G#4");
+        assert!(cents.abs() < 1e-2);
+        let (name, cents) = note(440.0 * 2f32.powf(30.0 / 1200.0));
+        assert_eq!(name, "A4");
+        assert!((cents - 30.0).abs() < 0.1);
+        let (name, cents) = note(440.0 * 2f32.powf(60.0 / 1200.0));
+        assert_eq!(name, "A#4");
+        assert!((cents + 40.0).abs() < 0.1);
+        assert_eq!(note(midi_frequency(72)).0, "C5");
+    }
+}
@@ -0,0 +1,67 @@
+//! Averaged power spectrum (Welch) and its strongest peaks.
+
+use super::fft::{blackman_harris, real_fft};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug)]
+pub struct SpectrumPeak {
+    pub frequency: f32,
+    /// Level of a sine with this peak, in dBFS.
+    pub level_db: f32,
+}
+
+/// Power spectrum in dBFS averaged over half-overlapping frames of `n`
+/// samples, `n / 2 + 1` bins of `sample_rate / n` Hz. `n` is rounded up to
+/// a power of two of at least 2.
+pub fn average_spectrum(samples: &[f32], n: usize) -> Vec<f32> {
+    let n = n.max(2).next_power_of_two();
+    let window = blackman_harris(n);
+    let gain: f64 = window.iter().sum::<f64>() / 2.0;
+    let mut power = vec![0.0f64; n / 2 + 1];
+    // shorter signals get one zero-padded frame
+    let starts: Vec<usize> = if samples.len() <= n {
+        vec![0]
+    } else {
+        (0..=samples.len() - n).step_by(n / 2).collect()
+    };
+    for start in &starts {
+        let frame: Vec<f64> = (0..n)
+            .map(|i| samples.get(start + i).map_or(0.0, |x| *x as f64) * window[i])
+            .collect();
+        let spectrum = real_fft(&frame, n);
+        for (p, v) in power.iter_mut().zip(&spectrum) {
+            *p += v.norm_sqr() / (gain * gain);
+        }
+    }
+    let frames = starts.len() as f64;
+    power
+        .iter()
+        .map(|p| (10.0 * (p / frames).max(1e-20).log10()) as f32)
+        .collect()
+}
+
+/// Up to `count` local maxima of `spectrum` above `floor_db`, strongest
+/// first, with parabolic interpolation between bins.
+pub fn peaks(spectrum: &[f32], bin_hz: f32, count: usize, floor_db: f32) -> Vec<SpectrumPeak> {
+    let mut peaks: Vec<SpectrumPeak> = (1..spectrum.len().saturating_sub(1))
+        .filter(|i| {
+            let v = spectrum[*i];
+            v > floor_db && v > spectrum[i - 1] && v >= spectrum[i + 1]
+        })
+        .map(|i| {
+            let (l, c, r) = (spectrum[i - 1], spectrum[i], spectrum[i + 1]);
+            let denominator = l - 2.0 * c + r;
+            let offset = if denominator.abs() > f32::EPSILON {
+                (0.5 * (l - r) / denominator).clamp(-0.5, 0.5)
+            } else {
+                0.0
+            };
+            SpectrumPeak {
+                frequency: (i as f32 + offset) * bin_hz,
+                level_db: c - 0.25 * (l - r) * offset,
+            }
+        })
+        .collect();
+    peaks.sort_by(|a, b| b.level_db.total_cmp(&a.level_db));
+    peaks.truncate(count);
+    peaks
+}
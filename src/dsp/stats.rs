@@ -0,0 +1,45 @@
+//! Whole-signal statistics: levels, DC offset and clipping.
+
+use super::meter::{to_db, CLIP_LEVEL};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct SignalStats {
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+    pub crest_factor_db: f32,
+    pub dc_offset: f32,
+    /// Samples at or above `CLIP_LEVEL`.
+    pub clipped_samples: usize,
+    /// Runs of consecutive clipped samples; each one is an audible clip.
+    pub clipped_runs: usize,
+}
+
+pub fn stats(samples: &[f32]) -> SignalStats {
+    let n = samples.len().max(1) as f64;
+    let mut peak = 0.0f32;
+    let (mut sum, mut square) = (0.0f64, 0.0f64);
+    let (mut clipped_samples, mut clipped_runs) = (0, 0);
+    let mut in_run = false;
+    for x in samples {
+        peak = peak.max(x.abs());
+        sum += *x as f64;
+        square += (*x as f64).powi(2);
+        let clipped = x.abs() >= CLIP_LEVEL;
+        if clipped {
+            clipped_samples += 1;
+            if !in_run {
+                clipped_runs += 1;
+            }
+        }
+        in_run = clipped;
+    }
+    let rms = (square / n).sqrt() as f32;
+    SignalStats {
+        peak_dbfs: to_db(peak),
+        rms_dbfs: to_db(rms),
+        crest_factor_db: to_db(peak) - to_db(rms),
+        dc_offset: (sum / n) as f32,
+        clipped_samples,
+        clipped_runs,
+    }
+}
@@ -0,0 +1,400 @@
+//! Exponential sine sweep measurement (Farina): sweep generation,
+//! deconvolution of the recorded response into linear and harmonic impulse
+//! responses, and smoothed frequency response.
+
+use std::f64::consts::PI;
+
+use super::{
+    complex::Complex,
+    fft::{fft, ifft},
+};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SweepSettings {
+    pub start_hz: f64,
+    pub end_hz: f64,
+    pub duration_s: f64,
+    pub level_db: f64,
+    /// Silence played after the sweep so the tail gets recorded.
+    pub tail_s: f64,
+    pub fade_ms: f64,
+    /// Longest linear impulse response kept.
+    pub ir_length_s: f64,
+    pub max_harmonic: usize,
+}
+
+impl Default for SweepSettings {
+    fn default() -> Self {
+        Self {
+            start_hz: 20.0,
+            end_hz: 20000.0,
+            duration_s: 5.0,
+            level_db: -6.0,
+            tail_s: 1.0,
+            fade_ms: 20.0,
+            ir_length_s: 0.5,
+            max_harmonic: 5,
+        }
+    }
+}
+
+impl SweepSettings {
+    pub fn validate(&self, sample_rate: f32) -> Result<(), String> {
+        if self.start_hz <= 0.0 || self.start_hz >= self.end_hz {
+            return Err("sweep start must be above 0 Hz and below the end".to_owned());
+        }
+        if self.end_hz > sample_rate as f64 / 2.0 {
+            return Err(format!(
+                "sweep end is above Nyquist ({} Hz)",
+                sample_rate / 2.0
+            ));
+        }
+        if !(0.1..=60.0).contains(&self.duration_s) {
+            return Err("sweep duration must be between 0.1 and 60 s".to_owned());
+        }
+        if self.level_db > 0.0 {
+            return Err("sweep level must not exceed 0 dBFS".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Sweep rate constant `L = T / ln(f2 / f1)`, in seconds.
+    fn rate(&self) -> f64 {
+        self.duration_s / (self.end_hz / self.start_hz).ln()
+    }
+
+    /// How far before the linear response harmonic `order` lands, in seconds.
+    pub fn harmonic_offset(&self, order: usize) -> f64 {
+        self.rate() * (order as f64).ln()
+    }
+}
+
+/// The excitation signal, without the trailing silence.
+pub fn exponential_sweep(settings: &SweepSettings, sample_rate: f32) -> Vec<f32> {
+    let fs = sample_rate as f64;
+    let n = (settings.duration_s * fs) as usize;
+    let rate = settings.rate();
+    let amplitude = 10f64.powf(settings.level_db / 20.0);
+    let fade = ((settings.fade_ms / 1000.0 * fs) as usize).clamp(1, n / 2);
+    (0..n)
+        .map(|i| {
+            let t = i as f64 / fs;
+            let phase = 2.0 * PI * settings.start_hz * rate * ((t / rate).exp() - 1.0);
+            let edge = i.min(n - 1 - i);
+            let gain = if edge < fade {
+                0.5 - 0.5 * (PI * edge as f64 / fade as f64).cos()
+            } else {
+                1.0
+            };
+            (amplitude * gain * phase.sin()) as f32
+        })
+        .collect()
+}
+
+/// Impulse responses recovered from a sweep recording.
+#[derive(Clone, Debug)]
+pub struct Deconvolution {
+    pub sample_rate: f32,
+    /// Samples between the start of the recording and the direct sound.
+    pub latency: usize,
+    /// Samples kept ahead of the peak in every response.
+    pub pre_roll: usize,
+    pub impulse: Vec<f32>,
+    /// Harmonic impulse responses, starting with the 2nd harmonic.
+    pub harmonics: Vec<Vec<f32>>,
+}
+
+fn next_power_of_two(n: usize) -> usize {
+    n.max(2).next_power_of_two()
+}
+
+/// Cuts `length` samples starting at `start` out of the circular `signal`,
+/// with a half-Hann fade over the last quarter.
+fn cut(signal: &[f64], start: usize, length: usize) -> Vec<f32> {
+    let n = signal.len();
+    let fade = (length / 4).max(1);
+    (0..length)
+        .map(|i| {
+            let remaining = length - i;
+            let gain = if remaining < fade {
+                0.5 - 0.5 * (PI * remaining as f64 / fade as f64).cos()
+            } else {
+                1.0
+            };
+            (signal[(start + i) % n] * gain) as f32
+        })
+        .collect()
+}
+
+/// Deconvolves `recorded` with the sweep described by `settings` by
+/// regularized spectral division.
+pub fn deconvolve(
+    recorded: &[f32],
+    settings: &SweepSettings,
+    sample_rate: f32,
+) -> Result<Deconvolution, String> {
+    settings.validate(sample_rate)?;
+    let sweep = exponential_sweep(settings, sample_rate);
+    if recorded.len() < sweep.len() {
+        return Err(format!(
+            "recording is shorter than the sweep ({} < {} samples)",
+            recorded.len(),
+            sweep.len()
+        ));
+    }
+    let fs = sample_rate as f64;
+    let n = next_power_of_two(recorded.len() + sweep.len());
+    let spectrum = |samples: &[f32]| {
+        let mut data: Vec<Complex> = samples.iter().map(|v| Complex::from(*v as f64)).collect();
+        data.resize(n, Complex::default());
+        fft(&mut data);
+        data
+    };
+    let x = spectrum(&sweep);
+    let y = spectrum(recorded);
+    let max_power = x.iter().map(|v| v.norm_sqr()).fold(0.0, f64::max);
+    let mut h: Vec<Complex> = x
+        .iter()
+        .zip(&y)
+        .enumerate()
+        .map(|(bin, (x, y))| {
+            let frequency = bin.min(n - bin) as f64 * fs / n as f64;
+            let in_band = frequency >= settings.start_hz && frequency <= settings.end_hz;
+            let epsilon = max_power * if in_band { 1e-6 } else { 1.0 };
+            (*y * x.conj()).scale(1.0 / (x.norm_sqr() + epsilon))
+        })
+        .collect();
+    ifft(&mut h);
+    let h: Vec<f64> = h.iter().map(|v| v.re).collect();
+
+    let latency = (0..n / 2)
+        .max_by(|a, b| h[*a].abs().total_cmp(&h[*b].abs()))
+        .unwrap();
+    let pre_roll = (0.001 * fs) as usize;
+    let offset = |order: usize| (settings.harmonic_offset(order) * fs) as usize;
+    let limit = (settings.ir_length_s * fs) as usize;
+    let impulse_length = limit.min(offset(2).saturating_sub(pre_roll)).max(1);
+    let impulse = cut(&h, latency + n - pre_roll, impulse_length);
+    let harmonics = (2..=settings.max_harmonic)
+        .map(|order| {
+            let length = (offset(order) - offset(order - 1)).min(limit).max(1);
+            let start = (latency + n - offset(order) + n - pre_roll) % n;
+            cut(&h, start, length)
+        })
+        .collect();
+    Ok(Deconvolution {
+        sample_rate,
+        latency,
+        pre_roll,
+        impulse,
+        harmonics,
+    })
+}
+
+/// Magnitude and phase on a log-spaced frequency grid.
+#[derive(Clone, Debug, Default)]
+pub struct FrequencyResponse {
+    pub frequency: Vec<f64>,
+    pub magnitude_db: Vec<f32>,
+    pub phase_deg: Vec<f32>,
+}
+
+/// `count` log-spaced frequencies from `low` to `high`.
+pub fn log_grid(low: f64, high: f64, count: usize) -> Vec<f64> {
+    let ratio = (high / low).ln() / (count.max(2) - 1) as f64;
+    (0..count).map(|i| low * (ratio * i as f64).exp()).collect()
+}
+
+/// Complex response of `impulse` at `frequencies`, averaged over
+/// 1/`smoothing` octave around each one (0 for none). `delay` samples are
+/// compensated in the phase.
+pub fn response_at(
+    impulse: &[f32],
+    sample_rate: f32,
+    frequencies: &[f64],
+    smoothing: usize,
+    delay: usize,
+) -> Vec<Complex> {
+    let fs = sample_rate as f64;
+    let n = next_power_of_two(impulse.len() * 2).max(4096);
+    let mut spectrum: Vec<Complex> = impulse.iter().map(|v| Complex::from(*v as f64)).collect();
+    spectrum.resize(n, Complex::default());
+    fft(&mut spectrum);
+    let bin_hz = fs / n as f64;
+    let at = |bin: usize| {
+        let w = 2.0 * PI * bin as f64 / n as f64;
+        spectrum[bin] * Complex::from_phase(w * delay as f64)
+    };
+    frequencies
+        .iter()
+        .map(|f| {
+            let center = ((f / bin_hz).round() as usize).min(n / 2);
+            if smoothing == 0 {
+                return at(center);
+            }
+            let half = 2f64.powf(0.5 / smoothing as f64);
+            let low = ((f / half / bin_hz).floor() as usize).min(center);
+            let high = ((f * half / bin_hz).ceil() as usize).clamp(center, n / 2);
+            let bins = (low..=high).map(at);
+            let count = (high - low + 1) as f64;
+            // power average for the magnitude, complex average for the phase
+            let (sum, power) = bins.fold((Complex::default(), 0.0), |(sum, power), v| {
+                (sum + v, power + v.norm_sqr())
+            });
+            let magnitude = (power / count).sqrt();
+            Complex::from_phase(sum.arg()).scale(magnitude)
+        })
+        .collect()
+}
+
+pub fn frequency_response(
+    impulse: &[f32],
+    sample_rate: f32,
+    frequencies: &[f64],
+    smoothing: usize,
+    delay: usize,
+) -> FrequencyResponse {
+    let response = response_at(impulse, sample_rate, frequencies, smoothing, delay);
+    FrequencyResponse {
+        frequency: frequencies.to_vec(),
+        magnitude_db: response
+            .iter()
+            .map(|v| (20.0 * v.abs().max(1e-10).log10()) as f32)
+            .collect(),
+        phase_deg: response
+            .iter()
+            .map(|v| v.arg().to_degrees() as f32)
+            .collect(),
+    }
+}
+
+/// Level of each harmonic relative to the fundamental, in dB, against the
+/// excitation frequency. Frequencies whose harmonic is above Nyquist are NaN.
+pub fn harmonic_distortion(
+    deconvolution: &Deconvolution,
+    frequencies: &[f64],
+    smoothing: usize,
+) -> Vec<Vec<f32>> {
+    let fs = deconvolution.sample_rate;
+    let delay = deconvolution.pre_roll;
+    let fundamental = response_at(&deconvolution.impulse, fs, frequencies, smoothing, delay);
+    deconvolution
+        .harmonics
+        .iter()
+        .enumerate()
+        .map(|(i, impulse)| {
+            let order = (i + 2) as f64;
+            let shifted: Vec<f64> = frequencies.iter().map(|f| f * order).collect();
+            let response = response_at(impulse, fs, &shifted, smoothing, delay);
+            response
+                .iter()
+                .zip(&fundamental)
+                .zip(&shifted)
+                .map(|((h, f), frequency)| {
+                    if *frequency >= fs as f64 / 2.0 {
+                        f32::NAN
+                    } else {
+                        (20.0 * (h.abs() / f.abs().max(1e-10)).max(1e-10).log10()) as f32
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn settings() -> SweepSettings {
+        SweepSettings {
+            duration_s: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// `signal` delayed by `delay` samples, with the sweep's tail of silence.
+    fn record(
+        signal: impl Iterator<Item = f32>,
+        delay: usize,
+        settings: &SweepSettings,
+    ) -> Vec<f32> {
+        let tail = (settings.tail_s * SAMPLE_RATE as f64) as usize;
+        let mut recorded = vec![0.0; delay];
+        recorded.extend(signal);
+        recorded.resize(recorded.len() + tail, 0.0);
+        recorded
+    }
+
+    #[test]
+    fn deconvolve_recovers_delay_and_gain() {
+        let settings = settings();
+        let sweep = exponential_sweep(&settings, SAMPLE_RATE);
+        assert_eq!(sweep.len(), SAMPLE_RATE as usize);
+        let (delay, gain) = (480, 0.5);
+        let recorded = record(sweep.iter().map(|v| v * gain), delay, &settings);
+
+        let result = deconvolve(&recorded, &settings, SAMPLE_RATE).unwrap();
+        assert_eq!(result.latency, delay);
+        let peak = (0..result.impulse.len())
+            .max_by(|a, b| {
+                result.impulse[*a]
+                    .abs()
+                    .total_cmp(&result.impulse[*b].abs())
+            })
+            .unwrap();
+        assert_eq!(peak, result.pre_roll);
+
+        let frequencies = [250.0, 1000.0, 4000.0];
+        let response = frequency_response(
+            &result.impulse,
+            SAMPLE_RATE,
+            &frequencies,
+            3,
+            result.pre_roll,
+        );
+        let expected = 20.0 * gain.log10();
+        for (frequency, level) in frequencies.iter().zip(&response.magnitude_db) {
+            assert!(
+                (level - expected).abs() < 0.5,
+                "{} dB at {} Hz, expected {} dB",
+                level,
+                frequency,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn harmonic_distortion_reports_second_harmonic() {
+        let settings = settings();
+        let sweep = exponential_sweep(&settings, SAMPLE_RATE);
+        // x + a x² turns a sine of amplitude A into a 2nd harmonic of a A / 2
+        let a = 0.1;
+        let amplitude = 10f32.powf(settings.level_db as f32 / 20.0);
+        let expected = 20.0 * (a * amplitude / 2.0).log10();
+        let recorded = record(sweep.iter().map(|x| x + a * x * x), 100, &settings);
+
+        let result = deconvolve(&recorded, &settings, SAMPLE_RATE).unwrap();
+        assert_eq!(result.latency, 100);
+        let frequencies = [500.0, 1000.0, 2000.0];
+        let distortion = harmonic_distortion(&result, &frequencies, 3);
+        assert_eq!(distortion.len(), settings.max_harmonic - 1);
+        for (frequency, level) in frequencies.iter().zip(&distortion[0]) {
+            assert!(
+                (level - expected).abs() < 1.0,
+                "2nd harmonic at {} dB for {} Hz, expected {} dB",
+                level,
+                frequency,
+                expected
+            );
+        }
+        // no odd harmonics from a square term
+        for level in &distortion[1] {
+            assert!(*level < expected - 20.0, "3rd harmonic at {} dB", level);
+        }
+    }
+}
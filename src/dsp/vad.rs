@@ -0,0 +1,332 @@
+//! Silence detection: a level gate and a spectral voice activity detector
+//! that compares speech-band energy against an adaptive noise floor. Frame
+//! decisions are smoothed into activity regions.
+
+use super::fft::{hann, real_fft};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum VadMethod {
+    Level,
+    Spectral,
+}
+
+impl VadMethod {
+    pub const ALL: [VadMethod; 2] = [Self::Level, Self::Spectral];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Level => "Level gate",
+            Self::Spectral => "Spectral VAD",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VadSettings {
+    pub method: VadMethod,
+    /// Level gate: frames louder than this (dBFS RMS) are active.
+    pub threshold_db: f32,
+    /// Spectral: speech-band energy needed above the noise floor, in dB.
+    pub margin_db: f32,
+    pub frame_ms: f32,
+    /// Activity is held this long after the last active frame.
+    pub hangover_ms: f32,
+    /// Shorter gaps between active regions are bridged.
+    pub min_silence_ms: f32,
+    /// Shorter active regions are dropped.
+    pub min_activity_ms: f32,
+}
+
+impl Default for VadSettings {
+    fn default() -> Self {
+        Self {
+            method: VadMethod::Level,
+            threshold_db: -45.0,
+            margin_db: 9.0,
+            frame_ms: 20.0,
+            hangover_ms: 200.0,
+            min_silence_ms: 300.0,
+            min_activity_ms: 100.0,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct VadAnalysis {
+    pub frame_size: usize,
+    /// Per-frame activity score, 0..1, for display.
+    pub scores: Vec<f32>,
+    /// Active sample ranges, end exclusive.
+    pub regions: Vec<(usize, usize)>,
+}
+
+const SPEECH_BAND: (f64, f64) = (300.0, 3400.0);
+/// Span of audio the noise floor is estimated over.
+const NOISE_WINDOW_MS: f32 = 10_000.0;
+
+fn to_db(power: f64) -> f32 {
+    (10.0 * power.max(1e-12).log10()) as f32
+}
+
+/// RMS level of every frame in dBFS.
+fn frame_levels(samples: &[f32], frame: usize) -> Vec<f32> {
+    samples
+        .chunks(frame)
+        .map(|f| to_db(f.iter().map(|v| (*v as f64).powi(2)).sum::<f64>() / f.len() as f64))
+        .collect()
+}
+
+/// Speech-band energy (dB) and its spectral flatness (0 tonal .. 1 noise) per frame.
+fn speech_band(samples: &[f32], frame: usize, sample_rate: f32) -> Vec<(f32, f32)> {
+    let n = frame.next_power_of_two();
+    let window = hann(frame);
+    let bin_hz = sample_rate as f64 / n as f64;
+    let low = ((SPEECH_BAND.0 / bin_hz).ceil() as usize).max(1);
+    let high = ((SPEECH_BAND.1 / bin_hz).floor() as usize).clamp(low, n / 2);
+    samples
+        .chunks(frame)
+        .map(|f| {
+            let windowed: Vec<f64> = f.iter().zip(&window).map(|(x, w)| *x as f64 * w).collect();
+            let spectrum = real_fft(&windowed, n);
+            let power: Vec<f64> = spectrum[low..=high]
+                .iter()
+                .map(|v| v.norm_sqr().max(1e-20))
+                .collect();
+            let mean = power.iter().sum::<f64>() / power.len() as f64;
+            let log_mean = power.iter().map(|p| p.ln()).sum::<f64>() / power.len() as f64;
+            (to_db(mean), (log_mean.exp() / mean) as f32)
+        })
+        .collect()
+}
+
+/// Value below which `fraction` of `values` lie.
+fn percentile(values: &[f32], fraction: f32) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted
+        .get(((sorted.len() as f32 - 1.0) * fraction) as usize)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Noise floor at every frame: the quietest tenth of the frames within half
+/// a noise window, re-estimated every twentieth of a window.
+fn noise_floor(energies: &[f32], frame_ms: f32) -> Vec<f32> {
+    let radius = ((NOISE_WINDOW_MS / 2.0 / frame_ms) as usize).max(1);
+    let step = (radius / 10).max(1);
+    let mut floor = Vec::with_capacity(energies.len());
+    for start in (0..energies.len()).step_by(step) {
+        let end = (start + step).min(energies.len());
+        let around = &energies[start.saturating_sub(radius)..(end + radius).min(energies.len())];
+        floor.resize(end, percentile(around, 0.1));
+    }
+    floor
+}
+
+/// Runs of `true` as frame ranges, end exclusive.
+fn runs(active: &[bool]) -> Vec<(usize, usize)> {
+    let mut runs = vec![];
+    let mut start = None;
+    for (i, a) in active.iter().chain([&false]).enumerate() {
+        match (start, a) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                runs.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
+}
+
+/// Applies hangover, bridges short gaps and drops short bursts.
+fn smooth(active: &[bool], frame_ms: f32, settings: &VadSettings) -> Vec<(usize, usize)> {
+    let frames = |ms: f32| (ms / frame_ms).round() as usize;
+    let hangover = frames(settings.hangover_ms);
+    let mut held = active.to_vec();
+    for (start, end) in runs(active) {
+        let until = (end + hangover).min(held.len());
+        held[end..until].iter_mut().for_each(|a| *a = true);
+        // a little ahead too, so onsets aren't clipped
+        held[start.saturating_sub(1)..start]
+            .iter_mut()
+            .for_each(|a| *a = true);
+    }
+    let mut regions: Vec<(usize, usize)> = vec![];
+    for (start, end) in runs(&held) {
+        match regions.last_mut() {
+            Some(last) if start - last.1 < frames(settings.min_silence_ms) => last.1 = end,
+            _ => regions.push((start, end)),
+        }
+    }
+    regions.retain(|(start, end)| end - start >= frames(settings.min_activity_ms).max(1));
+    regions
+}
+
+pub fn analyze(samples: &[f32], sample_rate: f32, settings: &VadSettings) -> VadAnalysis {
+    let frame = ((settings.frame_ms / 1000.0 * sample_rate) as usize).max(16);
+    let frame_ms = frame as f32 / sample_rate * 1000.0;
+    let (active, scores): (Vec<bool>, Vec<f32>) = match settings.method {
+        VadMethod::Level => frame_levels(samples, frame)
+            .into_iter()
+            .map(|db| {
+                (
+                    db > settings.threshold_db,
+                    ((db + 90.0) / 90.0).clamp(0.0, 1.0),
+                )
+            })
+            .unzip(),
+        VadMethod::Spectral => {
+            let bands = speech_band(samples, frame, sample_rate);
+            let energies: Vec<f32> = bands.iter().map(|(db, _)| *db).collect();
+            let floor = noise_floor(&energies, frame_ms);
+            let margin = settings.margin_db.max(0.1);
+            bands
+                .into_iter()
+                .zip(floor)
+                .map(|((db, flatness), floor)| {
+                    let snr = db - floor;
+                    // noise has a flat spectrum, voiced sound doesn't
+                    let active = snr > 2.0 * margin || (snr > margin && flatness < 0.5);
+                    (active, (snr / (3.0 * margin)).clamp(0.0, 1.0))
+                })
+                .unzip()
+        }
+    };
+    let regions = smooth(&active, frame_ms, settings)
+        .into_iter()
+        .map(|(start, end)| (start * frame, (end * frame).min(samples.len())))
+        .collect();
+    VadAnalysis {
+        frame_size: frame,
+        scores,
+        regions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Consecutive (seconds, tone dBFS) parts of a 440 Hz tone over white
+    /// noise at `noise_db`; `None` is noise alone.
+    fn signal(parts: &[(f32, Option<f32>)], noise_db: f32) -> Vec<f32> {
+        let noise = 10f32.powf(noise_db / 20.0) * 3f32.sqrt();
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut samples = vec![];
+        for (seconds, tone_db) in parts {
+            let amplitude = tone_db.map_or(0.0, |db| 10f32.powf(db / 20.0) * 2f32.sqrt());
+            for _ in 0..(seconds * SAMPLE_RATE) as usize {
+                let n = samples.len() as f32;
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let uniform = state as f32 / u64::MAX as f32 * 2.0 - 1.0;
+                let phase = 2.0 * std::f32::consts::PI * 440.0 * n / SAMPLE_RATE;
+                samples.push(amplitude * phase.sin() + noise * uniform);
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn tone_silence_tone_gives_two_regions() {
+        let samples = signal(
+            &[(1.0, Some(-20.0)), (1.0, None), (1.0, Some(-20.0))],
+            -80.0,
+        );
+        for method in VadMethod::ALL {
+            let settings = VadSettings {
+                method,
+                ..Default::default()
+            };
+            let analysis = analyze(&samples, SAMPLE_RATE, &settings);
+            // 20 ms frames; activity is held 10 frames and starts one early
+            assert_eq!(analysis.frame_size, 960);
+            assert_eq!(
+                analysis.regions,
+                vec![(0, 60 * 960), (99 * 960, 150 * 960)],
+                "{:?}",
+                method
+            );
+            assert_eq!(analysis.scores.len(), 150);
+        }
+    }
+
+    #[test]
+    fn smoothing_bridges_short_gaps_and_drops_short_bursts() {
+        let samples = signal(
+            &[
+                (1.0, Some(-20.0)),
+                (0.3, None),
+                (1.0, Some(-20.0)),
+                (1.0, None),
+                (0.04, Some(-20.0)),
+                (1.0, None),
+            ],
+            -80.0,
+        );
+        // without hangover the 300 ms gap is bridged and the 40 ms burst,
+        // 60 ms with its lead-in frame, is too short
+        let settings = VadSettings {
+            hangover_ms: 0.0,
+            ..Default::default()
+        };
+        let analysis = analyze(&samples, SAMPLE_RATE, &settings);
+        assert_eq!(analysis.regions, vec![(0, 115 * 960)]);
+        let settings = VadSettings {
+            min_activity_ms: 0.0,
+            ..settings
+        };
+        let analysis = analyze(&samples, SAMPLE_RATE, &settings);
+        assert_eq!(
+            analysis.regions,
+            vec![(0, 115 * 960), (164 * 960, 167 * 960)]
+        );
+    }
+
+    #[test]
+    fn noise_floor_follows_a_change_in_background_noise() {
+        // the background rises by 30 dB halfway; the tones stand 30 dB
+        // above whichever background they're in
+        let quiet = [(8.0, None), (1.0, Some(-50.0)), (8.0, None)];
+        let loud = [(8.0, None), (1.0, Some(-20.0)), (8.0, None)];
+        let mut samples = signal(&quiet, -80.0);
+        samples.extend(signal(&loud, -50.0));
+        let settings = VadSettings {
+            method: VadMethod::Spectral,
+            ..Default::default()
+        };
+        let regions = analyze(&samples, SAMPLE_RATE, &settings).regions;
+        let frame = 960;
+        assert_eq!(regions.first(), Some(&(399 * frame, 460 * frame)));
+        assert_eq!(regions.last(), Some(&(1249 * frame, 1310 * frame)));
+        // the step itself reads as activity until the floor catches up,
+        // within half a noise window
+        let step = 850 * frame;
+        let lag = (NOISE_WINDOW_MS / 2000.0 * SAMPLE_RATE) as usize;
+        for (start, end) in &regions[1..regions.len() - 1] {
+            assert!(
+                *start >= step - frame && *end <= step + lag,
+                "{:?}",
+                regions
+            );
+        }
+    }
+
+    #[test]
+    fn silence_has_no_regions() {
+        let samples = signal(&[(2.0, None)], -80.0);
+        for method in VadMethod::ALL {
+            let settings = VadSettings {
+                method,
+                ..Default::default()
+            };
+            assert!(analyze(&samples, SAMPLE_RATE, &settings).regions.is_empty());
+        }
+    }
+}
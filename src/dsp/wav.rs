@@ -0,0 +1,379 @@
+//! Minimal RIFF/WAVE reader for PCM (8/16/24/32-bit) and 32/64-bit float,
+//! and a 16-bit PCM writer.
+
+/// Decoded audio, one vector per channel.
+#[derive(Clone, Debug)]
+pub struct Wav {
+    pub sample_rate: f32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl Wav {
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, |c| c.len())
+    }
+
+    /// Average of all channels.
+    pub fn mono(&self) -> Vec<f32> {
+        let scale = 1.0 / self.channels.len().max(1) as f32;
+        (0..self.frames())
+            .map(|i| self.channels.iter().map(|c| c[i]).sum::<f32>() * scale)
+            .collect()
+    }
+
+    /// 16-bit PCM WAV file contents.
+    pub fn encode(&self) -> Vec<u8> {
+        let channels = self.channels.len().max(1);
+        let data_size = (self.frames() * channels * 2) as u32;
+        let sample_rate = self.sample_rate as u32;
+        let mut bytes = Vec::with_capacity(44 + data_size as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&PCM.to_le_bytes());
+        bytes.extend_from_slice(&(channels as u16).to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+        bytes.extend_from_slice(&(channels as u16 * 2).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        for i in 0..self.frames() {
+            for channel in &self.channels {
+                let v = (channel[i].clamp(-1.0, 1.0) * 32767.0).round() as i16;
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        bytes
+    }
+}
+
+/// The `N` bytes at `at`, or an error when `bytes` ends before them.
+fn field<const N: usize>(bytes: &[u8], at: usize) -> Result<[u8; N], String> {
+    at.checked_add(N)
+        .and_then(|end| bytes.get(at..end))
+        .and_then(|field| field.try_into().ok())
+        .ok_or_else(|| "truncated chunk".to_owned())
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Result<u16, String> {
+    field(bytes, at).map(u16::from_le_bytes)
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Result<u32, String> {
+    field(bytes, at).map(u32::from_le_bytes)
+}
+
+const PCM: u16 = 1;
+const FLOAT: u16 = 3;
+const EXTENSIBLE: u16 = 0xfffe;
+
+fn decoder(tag: u16, bits: u16) -> Result<fn(&[u8]) -> f32, String> {
+    Ok(match (tag, bits) {
+        (PCM, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (PCM, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (PCM, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+        (PCM, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+        (FLOAT, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        (FLOAT, 64) => {
+            |b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+        }
+        _ => return Err(format!("unsupported format {} with {} bits", tag, bits)),
+    })
+}
+
+fn deinterleave(
+    data: &[u8],
+    decode: fn(&[u8]) -> f32,
+    bits: u16,
+    channels: usize,
+) -> Vec<Vec<f32>> {
+    let width = bits as usize / 8;
+    let mut planar = vec![vec![]; channels];
+    for (i, sample) in data.chunks_exact(width).enumerate() {
+        planar[i % channels].push(decode(sample));
+    }
+    // drop a trailing partial frame
+    let frames = planar.iter().map(|c| c.len()).min().unwrap_or(0);
+    planar.iter_mut().for_each(|c| c.truncate(frames));
+    planar
+}
+
+/// Sample encodings accepted for headerless PCM, all little-endian.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RawFormat {
+    U8,
+    S16,
+    S24,
+    S32,
+    F32,
+    F64,
+}
+
+impl RawFormat {
+    pub const ALL: [RawFormat; 6] = [
+        Self::U8,
+        Self::S16,
+        Self::S24,
+        Self::S32,
+        Self::F32,
+        Self::F64,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::S16 => "s16le",
+            Self::S24 => "s24le",
+            Self::S32 => "s32le",
+            Self::F32 => "f32le",
+            Self::F64 => "f64le",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    fn tag_and_bits(&self) -> (u16, u16) {
+        match self {
+            Self::U8 => (PCM, 8),
+            Self::S16 => (PCM, 16),
+            Self::S24 => (PCM, 24),
+            Self::S32 => (PCM, 32),
+            Self::F32 => (FLOAT, 32),
+            Self::F64 => (FLOAT, 64),
+        }
+    }
+}
+
+/// Decodes headerless interleaved PCM.
+pub fn parse_raw(
+    bytes: &[u8],
+    format: RawFormat,
+    channels: usize,
+    sample_rate: f32,
+) -> Result<Wav, String> {
+    if channels == 0 {
+        return Err("no channels".to_owned());
+    }
+    let (tag, bits) = format.tag_and_bits();
+    Ok(Wav {
+        sample_rate,
+        channels: deinterleave(bytes, decoder(tag, bits)?, bits, channels),
+    })
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Wav, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_owned());
+    }
+    let mut format = None;
+    let mut at = 12;
+    while bytes.len().saturating_sub(at) >= 8 {
+        let id = &bytes[at..at + 4];
+        let size = u32_at(bytes, at + 4)? as usize;
+        let body = at + 8;
+        // sizes near `u32::MAX` would overflow a 32-bit `usize`
+        let end = body.saturating_add(size).min(bytes.len());
+        match id {
+            b"fmt " if size >= 16 => {
+                // every field must be inside the chunk, not just declared
+                let fmt = &bytes[body..end];
+                let mut tag = u16_at(fmt, 0)?;
+                if tag == EXTENSIBLE && size >= 26 {
+                    // the sub-format GUID starts with the actual tag
+                    tag = u16_at(fmt, 24)?;
+                }
+                format = Some((
+                    tag,
+                    u16_at(fmt, 2)? as usize,
+                    u32_at(fmt, 4)?,
+                    u16_at(fmt, 14)?,
+                ));
+            }
+            b"data" => {
+                let (tag, channels, sample_rate, bits) =
+                    format.ok_or("data chunk before fmt chunk")?;
+                if channels == 0 {
+                    return Err("no channels".to_owned());
+                }
+                if sample_rate == 0 {
+                    return Err("sample rate 0".to_owned());
+                }
+                // streamed WAVs (e.g. piped from ffmpeg) leave the size unset
+                let end = if size == 0 || size == u32::MAX as usize {
+                    bytes.len()
+                } else {
+                    end
+                };
+                return Ok(Wav {
+                    sample_rate: sample_rate as f32,
+                    channels: deinterleave(&bytes[body..end], decoder(tag, bits)?, bits, channels),
+                });
+            }
+            _ => {}
+        }
+        // chunks are padded to an even size
+        at = body.saturating_add(size).saturating_add(size & 1);
+    }
+    Err("no data chunk".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// A WAV file with a `tag`/`bits` fmt chunk, as WAVE_FORMAT_EXTENSIBLE
+    /// when `extensible`, around already encoded `data`.
+    fn file(tag: u16, bits: u16, channels: u16, extensible: bool, data: &[u8]) -> Vec<u8> {
+        let mut fmt = vec![];
+        fmt.extend_from_slice(&(if extensible { EXTENSIBLE } else { tag }).to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        let align = channels * bits / 8;
+        fmt.extend_from_slice(&(SAMPLE_RATE * align as u32).to_le_bytes());
+        fmt.extend_from_slice(&align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        if extensible {
+            fmt.extend_from_slice(&22u16.to_le_bytes());
+            fmt.extend_from_slice(&bits.to_le_bytes());
+            fmt.extend_from_slice(&0u32.to_le_bytes());
+            // KSDATAFORMAT_SUBTYPE_PCM / _IEEE_FLOAT
+            fmt.extend_from_slice(&tag.to_le_bytes());
+            fmt.extend_from_slice(&[
+                0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+            ]);
+        }
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&fmt);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// Interleaved stereo test signal: a ramp and its negation.
+    fn signal() -> Vec<[f32; 2]> {
+        (0..64)
+            .map(|i| {
+                let v = i as f32 / 32.0 - 1.0;
+                [v, -v * 0.5]
+            })
+            .collect()
+    }
+
+    fn check(wav: &Wav, tolerance: f32) {
+        assert_eq!(wav.sample_rate, SAMPLE_RATE as f32);
+        assert_eq!(wav.channels.len(), 2);
+        assert_eq!(wav.frames(), 64);
+        for (i, [left, right]) in signal().into_iter().enumerate() {
+            assert!((wav.channels[0][i] - left).abs() <= tolerance);
+            assert!((wav.channels[1][i] - right).abs() <= tolerance);
+        }
+    }
+
+    #[test]
+    fn encode_round_trips_through_parse() {
+        let wav = Wav {
+            sample_rate: SAMPLE_RATE as f32,
+            channels: vec![
+                signal().iter().map(|s| s[0]).collect(),
+                signal().iter().map(|s| s[1]).collect(),
+            ],
+        };
+        check(&parse(&wav.encode()).unwrap(), 1.0 / 32767.0);
+    }
+
+    #[test]
+    fn decodes_every_supported_encoding() {
+        type Encode = fn(f32) -> Vec<u8>;
+        let encodings: [(u16, u16, Encode, f32); 6] = [
+            (
+                PCM,
+                8,
+                |v| vec![(v * 128.0 + 128.0).round().min(255.0) as u8],
+                1.0 / 128.0,
+            ),
+            (
+                PCM,
+                16,
+                |v| ((v * 32768.0).round() as i16).to_le_bytes().to_vec(),
+                1.0 / 32768.0,
+            ),
+            (
+                PCM,
+                24,
+                |v| ((v * 8388608.0).round() as i32).to_le_bytes()[..3].to_vec(),
+                1e-6,
+            ),
+            (
+                PCM,
+                32,
+                |v| {
+                    ((v as f64 * 2147483648.0).round() as i32)
+                        .to_le_bytes()
+                        .to_vec()
+                },
+                1e-7,
+            ),
+            (FLOAT, 32, |v| v.to_le_bytes().to_vec(), 0.0),
+            (FLOAT, 64, |v| (v as f64).to_le_bytes().to_vec(), 0.0),
+        ];
+        for (tag, bits, encode, tolerance) in encodings {
+            let data: Vec<u8> = signal().into_iter().flatten().flat_map(encode).collect();
+            for extensible in [false, true] {
+                let bytes = file(tag, bits, 2, extensible, &data);
+                check(&parse(&bytes).unwrap(), tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_errors_not_panics() {
+        let bytes = file(PCM, 24, 2, true, &[0; 6 * 64]);
+        let data_at = bytes.len() - 6 * 64;
+        for len in 0..bytes.len() {
+            let result = parse(&bytes[..len]);
+            if len < data_at - 8 {
+                assert!(result.is_err(), "{} bytes", len);
+            } else if let Ok(wav) = result {
+                // a cut data chunk keeps the whole frames before the cut
+                assert_eq!(wav.frames(), (len - data_at) / 6);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let valid = file(PCM, 16, 2, false, &[0; 16]);
+        let patched = |at: usize, field: &[u8]| {
+            let mut bytes = valid.clone();
+            bytes[at..at + field.len()].copy_from_slice(field);
+            parse(&bytes)
+        };
+        assert!(parse(b"RIFF\0\0\0\0AVI LIST").is_err());
+        // no channels, no sample rate, 12-bit samples
+        assert!(patched(22, &0u16.to_le_bytes()).is_err());
+        assert!(patched(24, &0u32.to_le_bytes()).is_err());
+        assert!(patched(34, &12u16.to_le_bytes()).is_err());
+        // a fmt chunk claiming more bytes than the file has
+        assert!(patched(16, &u32::MAX.to_le_bytes()).is_err());
+        // data before fmt
+        let mut swapped = valid[..12].to_vec();
+        swapped.extend_from_slice(&valid[36..]);
+        swapped.extend_from_slice(&valid[12..36]);
+        assert!(parse(&swapped).is_err());
+        // a fmt chunk too short for its fields
+        let mut short = valid[..16].to_vec();
+        short.extend_from_slice(&16u32.to_le_bytes());
+        short.extend_from_slice(&valid[20..30]);
+        assert!(parse(&short).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Failures of the browser APIs the app depends on, with messages meant for
+//! the user rather than the console.
+
+use std::fmt;
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::DomException;
+
+/// Name and message of a rejected promise or thrown exception.
+fn describe(error: &JsValue) -> (String, String) {
+    match error.dyn_ref::<DomException>() {
+        Some(e) => (e.name(), e.message()),
+        None => (
+            String::new(),
+            error.as_string().unwrap_or_else(|| format!("{:?}", error)),
+        ),
+    }
+}
+
+/// Why capture couldn't start.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaptureError {
+    /// `navigator.mediaDevices` is missing, e.g. on a page not served over HTTPS.
+    Unsupported,
+    /// The user or the browser's settings denied microphone access.
+    PermissionDenied,
+    /// No input matches the device and format settings.
+    NoDevice,
+    /// The input exists but can't be opened, e.g. another app holds it.
+    DeviceBusy(String),
+    /// Building the Web Audio graph failed.
+    Audio(String),
+}
+
+impl CaptureError {
+    pub fn from_js(error: &JsValue) -> Self {
+        let (name, message) = describe(error);
+        match name.as_str() {
+            "NotAllowedError" | "SecurityError" => Self::PermissionDenied,
+            "NotFoundError" | "OverconstrainedError" => Self::NoDevice,
+            "NotReadableError" | "AbortError" => Self::DeviceBusy(message),
+            _ => Self::Audio(message),
+        }
+    }
+
+    /// What the user can do about it.
+    pub fn advice(&self) -> &'static str {
+        match self {
+            Self::Unsupported => {
+                "Open the app over HTTPS (or localhost) in a browser with microphone support."
+            }
+            Self::PermissionDenied => {
+                "Allow microphone access in the browser's site settings, then retry."
+            }
+            Self::NoDevice => "Connect an input or pick another device, rate or channel count.",
+            Self::DeviceBusy(_) => "Close other apps that use the input, then retry.",
+            Self::Audio(_) => "Retry, or reload the page.",
+        }
+    }
+}
+
+impl From<JsValue> for CaptureError {
+    fn from(error: JsValue) -> Self {
+        Self::from_js(&error)
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "This browser doesn't offer audio input here"),
+            Self::PermissionDenied => write!(f, "Microphone access was denied"),
+            Self::NoDevice => write!(f, "No matching audio input found"),
+            Self::DeviceBusy(message) => write!(f, "The audio input can't be opened: {}", message),
+            Self::Audio(message) => write!(f, "Audio setup failed: {}", message),
+        }
+    }
+}
+
+/// Why the session can't be kept in IndexedDB.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StorageError {
+    /// IndexedDB is missing or blocked, e.g. in private browsing.
+    Unavailable(String),
+    /// The origin ran out of storage space.
+    QuotaExceeded,
+    /// A single read or write failed.
+    Failed(String),
+}
+
+impl StorageError {
+    pub fn from_js(error: &JsValue) -> Self {
+        let (name, message) = describe(error);
+        match name.as_str() {
+            "QuotaExceededError" => Self::QuotaExceeded,
+            "InvalidStateError" | "SecurityError" | "UnknownError" => Self::Unavailable(message),
+            _ => Self::Failed(message),
+        }
+    }
+
+    /// What the user can do about it.
+    pub fn advice(&self) -> &'static str {
+        match self {
+            Self::Unavailable(_) => {
+                "Recording continues in memory only and is lost when the page closes. Leave private browsing to keep sessions."
+            }
+            Self::QuotaExceeded => {
+                "Recording continues in memory only. Free up disk space or clear old sessions, then retry."
+            }
+            Self::Failed(_) => "Recording continues in memory only. Retry to go back to IndexedDB.",
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable(message) if message.is_empty() => {
+                write!(f, "Browser storage is unavailable")
+            }
+            Self::Unavailable(message) => write!(f, "Browser storage is unavailable: {}", message),
+            Self::QuotaExceeded => write!(f, "Browser storage is full"),
+            Self::Failed(message) => write!(f, "Storage failed: {}", message),
+        }
+    }
+}
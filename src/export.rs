@@ -0,0 +1,68 @@
+#[cfg(target_arch = "wasm32")]
+use js_sys::{Array, Uint8Array};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(target_arch = "wasm32")]
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+use crate::Log;
+
+pub struct Export {}
+
+impl Log for Export {
+    fn name() -> &'static str {
+        "Export"
+    }
+}
+
+impl Export {
+    /// Offers `contents` to the user as a file download.
+    pub fn download(filename: &str, mime: &str, contents: &str) {
+        Self::bytes(filename, mime, contents.as_bytes());
+    }
+
+    /// Like `download`, for binary `contents`.
+    pub fn bytes(filename: &str, mime: &str, contents: &[u8]) {
+        if let Err(e) = Self::try_download(filename, mime, contents) {
+            Self::warn(&format!("download of {} failed: {:?}", filename, e));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn try_download(filename: &str, mime: &str, contents: &[u8]) -> Result<(), JsValue> {
+        let parts = Array::of1(&Uint8Array::from(contents));
+        let mut options = BlobPropertyBag::new();
+        options.type_(mime);
+        let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .ok_or_else(|| JsValue::from_str("no document"))?;
+        let anchor = document
+            .create_element("a")?
+            .dyn_into::<HtmlAnchorElement>()?;
+        anchor.set_href(&url);
+        anchor.set_download(filename);
+        anchor.click();
+        Url::revoke_object_url(&url)
+    }
+
+    /// Natively the "download" is written to the working directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn try_download(filename: &str, _mime: &str, contents: &[u8]) -> std::io::Result<()> {
+        std::fs::write(filename, contents)?;
+        Self::log(&format!("wrote {}", filename));
+        Ok(())
+    }
+
+    pub fn json<T: serde::Serialize>(filename: &str, value: &T) {
+        match serde_json::to_string_pretty(value) {
+            Ok(contents) => Self::download(filename, "application/json", &contents),
+            Err(e) => Self::log(&format!("cannot serialize {}: {}", filename, e)),
+        }
+    }
+
+    pub fn csv(filename: &str, contents: &str) {
+        Self::download(filename, "text/csv", contents);
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(target_arch = "wasm32")]
+use js_sys::Uint8Array;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_futures::JsFuture;
+#[cfg(target_arch = "wasm32")]
+use web_sys::HtmlInputElement;
+
+use crate::Log;
+
+/// A file the user picked, as name and contents.
+pub type PickedFile = (String, Vec<u8>);
+
+/// File chosen through the browser's picker, filled in once it's read.
+#[derive(Clone, Default)]
+pub struct FileRequest {
+    file: Arc<Mutex<Option<PickedFile>>>,
+}
+
+impl FileRequest {
+    pub fn take(&self) -> Option<PickedFile> {
+        self.file.lock().unwrap().take()
+    }
+}
+
+pub struct Import {}
+
+impl Log for Import {
+    fn name() -> &'static str {
+        "Import"
+    }
+}
+
+impl Import {
+    /// Opens the file picker for `accept` (e.g. ".wav"), repainting `ctx`
+    /// once the file is read.
+    pub fn pick(accept: &str, ctx: &egui::Context) -> FileRequest {
+        let request = FileRequest::default();
+        if let Err(e) = Self::try_pick(accept, request.clone(), ctx.clone()) {
+            Self::warn(&format!("file picker failed: {:?}", e));
+        }
+        request
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn try_pick(accept: &str, request: FileRequest, ctx: egui::Context) -> Result<(), JsValue> {
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .ok_or_else(|| JsValue::from_str("no document"))?;
+        let input = document
+            .create_element("input")?
+            .dyn_into::<HtmlInputElement>()?;
+        input.set_type("file");
+        input.set_accept(accept);
+        let target = input.clone();
+        let on_change = Closure::once(move || {
+            let Some(file) = target.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            wasm_bindgen_futures::spawn_local(async move {
+                match JsFuture::from(file.array_buffer()).await {
+                    Ok(buffer) => {
+                        let bytes = Uint8Array::new(&buffer).to_vec();
+                        Self::log(&format!("read {} ({} bytes)", file.name(), bytes.len()));
+                        *request.file.lock().unwrap() = Some((file.name(), bytes));
+                        ctx.request_repaint();
+                    }
+                    Err(e) => Self::log(&format!("cannot read {}: {:?}", file.name(), e)),
+                }
+            });
+        });
+        input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+        on_change.forget();
+        input.click();
+        Ok(())
+    }
+
+    /// There is no file picker natively; the request never fills.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn try_pick(_accept: &str, _request: FileRequest, _ctx: egui::Context) -> Result<(), String> {
+        Err("file import is only available in the browser".to_owned())
+    }
+}
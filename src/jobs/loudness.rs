@@ -0,0 +1,50 @@
+use super::{Job, JobOutput, JobUpdate};
+use crate::dsp::loudness::{LoudnessMeter, LoudnessReport};
+
+/// Integrated loudness of planar audio, measured a few seconds at a time.
+pub struct LoudnessJob {
+    channels: Vec<Vec<f32>>,
+    meter: LoudnessMeter,
+    slice: usize,
+    next: usize,
+}
+
+impl LoudnessJob {
+    /// Audio measured per step.
+    const SLICE_SECONDS: f32 = 5.0;
+
+    pub fn new(channels: Vec<Vec<f32>>, sample_rate: f32) -> Self {
+        Self {
+            meter: LoudnessMeter::new(sample_rate, channels.len()),
+            slice: (sample_rate * Self::SLICE_SECONDS) as usize,
+            channels,
+            next: 0,
+        }
+    }
+}
+
+impl Job for LoudnessJob {
+    fn step(&mut self) -> JobUpdate {
+        let frames = self.channels.iter().map(|c| c.len()).min().unwrap_or(0);
+        if self.next >= frames {
+            return JobUpdate::Done(Some(JobOutput::Loudness(self.meter.report())));
+        }
+        let end = (self.next + self.slice.max(1)).min(frames);
+        let block: Vec<Vec<f32>> = self
+            .channels
+            .iter()
+            .map(|c| c[self.next..end].to_vec())
+            .collect();
+        self.meter.process(&block);
+        self.next = end;
+        // the history only comes with the final report
+        let partial = LoudnessReport {
+            history: vec![],
+            ..self.meter.report()
+        };
+        JobUpdate::Progress {
+            progress: end as f32 / frames as f32,
+            partial: Some(JobOutput::Loudness(partial)),
+        }
+    }
+}
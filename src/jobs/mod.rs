@@ -0,0 +1,193 @@
+//! Background execution of analysis jobs: a thread per job natively, a Web
+//! Worker (the `worker` binary) per job in the browser. Jobs run in slices,
+//! so they report progress and partial results as they go and can be
+//! cancelled between slices; ingest and rendering never wait for them.
+
+mod loudness;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+mod octave;
+mod onset;
+mod pitch;
+mod spectrogram;
+mod vad;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(not(target_arch = "wasm32"))]
+use native::Runner;
+#[cfg(target_arch = "wasm32")]
+use web::Runner;
+
+pub use loudness::LoudnessJob;
+pub use octave::BandsJob;
+pub use onset::OnsetJob;
+pub use pitch::PitchJob;
+pub use spectrogram::{SpectrogramJob, SpectrogramRequest};
+pub use vad::ActivityJob;
+#[cfg(target_arch = "wasm32")]
+pub use web::run_worker;
+
+use crate::dsp::{
+    loudness::LoudnessReport,
+    octave::{BandLevel, Weighting},
+    onset::{OnsetAnalysis, OnsetSettings},
+    pitch::{PitchEstimate, Yin},
+    vad::{VadAnalysis, VadSettings},
+};
+
+/// Everything a job needs, so it can be sent to a worker.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub enum JobRequest {
+    Spectrogram(SpectrogramRequest),
+    /// Planar fresh audio of the measured channels.
+    Loudness {
+        channels: Vec<Vec<f32>>,
+        sample_rate: f32,
+    },
+    /// Mono blocks keyed by chunk id.
+    Pitch {
+        blocks: Vec<(usize, Vec<f32>)>,
+        yin: Yin,
+        sample_rate: f32,
+    },
+    /// Fresh mono audio, measured as one `Leq` average.
+    Bands {
+        samples: Vec<f32>,
+        fraction: usize,
+        weighting: Weighting,
+        sample_rate: f32,
+    },
+    Onsets {
+        samples: Vec<f32>,
+        sample_rate: f32,
+        settings: OnsetSettings,
+    },
+    Activity {
+        samples: Vec<f32>,
+        sample_rate: f32,
+        settings: VadSettings,
+    },
+}
+
+impl JobRequest {
+    pub fn start(self) -> Box<dyn Job> {
+        match self {
+            Self::Spectrogram(request) => Box::new(SpectrogramJob::new(request)),
+            Self::Loudness {
+                channels,
+                sample_rate,
+            } => Box::new(LoudnessJob::new(channels, sample_rate)),
+            Self::Pitch {
+                blocks,
+                yin,
+                sample_rate,
+            } => Box::new(PitchJob::new(blocks, yin, sample_rate)),
+            Self::Bands {
+                samples,
+                fraction,
+                weighting,
+                sample_rate,
+            } => Box::new(BandsJob::new(samples, fraction, weighting, sample_rate)),
+            Self::Onsets {
+                samples,
+                sample_rate,
+                settings,
+            } => Box::new(OnsetJob::new(samples, sample_rate, settings)),
+            Self::Activity {
+                samples,
+                sample_rate,
+                settings,
+            } => Box::new(ActivityJob::new(samples, sample_rate, settings)),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub enum JobOutput {
+    /// Spectrogram columns from `first` on, each bottom bin first, 0..1.
+    SpectrogramColumns {
+        first: usize,
+        columns: Vec<Vec<f32>>,
+    },
+    /// The measurement up to the current point.
+    Loudness(LoudnessReport),
+    /// Estimates of the blocks of the last slice.
+    Pitch(Vec<(usize, Option<PitchEstimate>)>),
+    /// Band levels up to the current point.
+    Bands(Vec<BandLevel>),
+    Onsets(OnsetAnalysis),
+    Activity(VadAnalysis),
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub enum JobUpdate {
+    /// Fraction done, with the results of the last slice.
+    Progress {
+        progress: f32,
+        partial: Option<JobOutput>,
+    },
+    Done(Option<JobOutput>),
+    Failed(String),
+}
+
+impl JobUpdate {
+    pub fn is_final(&self) -> bool {
+        !matches!(self, Self::Progress { .. })
+    }
+}
+
+/// Analysis split into slices that take a few milliseconds each.
+pub trait Job: Send {
+    /// Runs the next slice.
+    fn step(&mut self) -> JobUpdate;
+}
+
+/// A job running in the background. Dropping the handle cancels it.
+pub struct JobHandle {
+    runner: Runner,
+    progress: f32,
+    finished: bool,
+}
+
+impl JobHandle {
+    pub fn spawn(request: JobRequest) -> Self {
+        Self {
+            runner: Runner::spawn(request),
+            progress: 0.0,
+            finished: false,
+        }
+    }
+
+    /// Updates since the last call.
+    pub fn poll(&mut self) -> Vec<JobUpdate> {
+        let updates = self.runner.take_updates();
+        for update in &updates {
+            match update {
+                JobUpdate::Progress { progress, .. } => self.progress = *progress,
+                _ => {
+                    self.progress = 1.0;
+                    self.finished = true;
+                }
+            }
+        }
+        updates
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// True once the job is done or failed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.runner.cancel();
+        }
+    }
+}
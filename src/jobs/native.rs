@@ -0,0 +1,65 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use super::{JobRequest, JobUpdate};
+
+/// Runs a job on its own thread, checking for cancellation between slices.
+pub struct Runner {
+    cancelled: Arc<AtomicBool>,
+    updates: Receiver<JobUpdate>,
+    finished: bool,
+}
+
+impl Runner {
+    pub fn spawn(request: JobRequest) -> Self {
+        let (sender, updates) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        thread::spawn(move || {
+            let mut job = request.start();
+            while !flag.load(Ordering::Relaxed) {
+                let update = job.step();
+                let finished = update.is_final();
+                if sender.send(update).is_err() || finished {
+                    break;
+                }
+            }
+        });
+        Self {
+            cancelled,
+            updates,
+            finished: false,
+        }
+    }
+
+    pub fn take_updates(&mut self) -> Vec<JobUpdate> {
+        let mut updates = vec![];
+        loop {
+            match self.updates.try_recv() {
+                Ok(update) => {
+                    self.finished |= update.is_final();
+                    updates.push(update);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // the job panicked
+                    if !std::mem::replace(&mut self.finished, true) {
+                        updates.push(JobUpdate::Failed("job thread stopped".to_owned()));
+                    }
+                    break;
+                }
+            }
+        }
+        updates
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,41 @@
+use super::{Job, JobOutput, JobUpdate};
+use crate::dsp::octave::{Averaging, OctaveAnalyzer, Weighting};
+
+/// Fractional-octave band levels of a mono signal as one `Leq` measurement,
+/// a few seconds at a time.
+pub struct BandsJob {
+    samples: Vec<f32>,
+    analyzer: OctaveAnalyzer,
+    slice: usize,
+    next: usize,
+}
+
+impl BandsJob {
+    /// Audio analyzed per step.
+    const SLICE_SECONDS: f32 = 2.0;
+
+    pub fn new(samples: Vec<f32>, fraction: usize, weighting: Weighting, sample_rate: f32) -> Self {
+        Self {
+            samples,
+            analyzer: OctaveAnalyzer::new(fraction, sample_rate, weighting, Averaging::Leq),
+            slice: (sample_rate * Self::SLICE_SECONDS) as usize,
+            next: 0,
+        }
+    }
+}
+
+impl Job for BandsJob {
+    fn step(&mut self) -> JobUpdate {
+        let total = self.samples.len();
+        if self.next >= total {
+            return JobUpdate::Done(Some(JobOutput::Bands(self.analyzer.levels())));
+        }
+        let end = (self.next + self.slice.max(1)).min(total);
+        self.analyzer.process(&self.samples[self.next..end]);
+        self.next = end;
+        JobUpdate::Progress {
+            progress: end as f32 / total as f32,
+            partial: Some(JobOutput::Bands(self.analyzer.levels())),
+        }
+    }
+}
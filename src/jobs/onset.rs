@@ -0,0 +1,26 @@
+use super::{Job, JobOutput, JobUpdate};
+use crate::dsp::onset::{analyze, OnsetSettings};
+
+/// Onsets, tempo and beats of a mono signal, in one step.
+pub struct OnsetJob {
+    samples: Vec<f32>,
+    sample_rate: f32,
+    settings: OnsetSettings,
+}
+
+impl OnsetJob {
+    pub fn new(samples: Vec<f32>, sample_rate: f32, settings: OnsetSettings) -> Self {
+        Self {
+            samples,
+            sample_rate,
+            settings,
+        }
+    }
+}
+
+impl Job for OnsetJob {
+    fn step(&mut self) -> JobUpdate {
+        let analysis = analyze(&self.samples, self.sample_rate, &self.settings);
+        JobUpdate::Done(Some(JobOutput::Onsets(analysis)))
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Job, JobOutput, JobUpdate};
+use crate::dsp::pitch::Yin;
+
+/// Pitch of mono blocks keyed by chunk id, a batch of blocks at a time.
+pub struct PitchJob {
+    blocks: Vec<(usize, Vec<f32>)>,
+    yin: Yin,
+    sample_rate: f32,
+    next: usize,
+}
+
+impl PitchJob {
+    /// Blocks estimated per step.
+    const SLICE: usize = 32;
+
+    pub fn new(blocks: Vec<(usize, Vec<f32>)>, yin: Yin, sample_rate: f32) -> Self {
+        Self {
+            blocks,
+            yin,
+            sample_rate,
+            next: 0,
+        }
+    }
+}
+
+impl Job for PitchJob {
+    fn step(&mut self) -> JobUpdate {
+        let total = self.blocks.len();
+        if self.next >= total {
+            return JobUpdate::Done(None);
+        }
+        let first = self.next;
+        self.next = (first + Self::SLICE).min(total);
+        let estimates = self.blocks[first..self.next]
+            .iter()
+            .map(|(id, block)| (*id, self.yin.estimate(block, self.sample_rate)))
+            .collect();
+        JobUpdate::Progress {
+            progress: self.next as f32 / total as f32,
+            partial: Some(JobOutput::Pitch(estimates)),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use super::{Job, JobOutput, JobUpdate};
+use crate::dsp::fft::{hann, real_fft};
+
+/// Short-time spectrum of a mono signal.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SpectrogramRequest {
+    pub samples: Vec<f32>,
+    pub fft_size: usize,
+    pub hop: usize,
+    /// Levels below this many dBFS map to 0.
+    pub floor_db: f32,
+}
+
+impl SpectrogramRequest {
+    pub fn columns(&self) -> usize {
+        match self.samples.len().checked_sub(self.fft_size) {
+            Some(rest) => rest / self.hop.max(1) + 1,
+            None => 0,
+        }
+    }
+}
+
+pub struct SpectrogramJob {
+    request: SpectrogramRequest,
+    window: Vec<f64>,
+    next: usize,
+}
+
+impl SpectrogramJob {
+    /// Columns computed per step.
+    const SLICE: usize = 32;
+
+    pub fn new(request: SpectrogramRequest) -> Self {
+        Self {
+            window: hann(request.fft_size),
+            request,
+            next: 0,
+        }
+    }
+
+    fn column(&self, index: usize) -> Vec<f32> {
+        let n = self.request.fft_size;
+        let start = index * self.request.hop;
+        let windowed: Vec<f64> = self.request.samples[start..start + n]
+            .iter()
+            .zip(&self.window)
+            .map(|(x, w)| *x as f64 * w)
+            .collect();
+        // a full-scale sine peaks at n/4 with the Hann window
+        let scale = 4.0 / n as f64;
+        let floor = self.request.floor_db;
+        real_fft(&windowed, n)[..n / 2]
+            .iter()
+            .map(|bin| {
+                let db = 20.0 * (bin.norm_sqr().sqrt() * scale).max(1e-12).log10();
+                ((db as f32 - floor) / -floor).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+impl Job for SpectrogramJob {
+    fn step(&mut self) -> JobUpdate {
+        let total = self.request.columns();
+        if self.next >= total {
+            return JobUpdate::Done(None);
+        }
+        let first = self.next;
+        self.next = (first + Self::SLICE).min(total);
+        let columns = (first..self.next).map(|i| self.column(i)).collect();
+        JobUpdate::Progress {
+            progress: self.next as f32 / total as f32,
+            partial: Some(JobOutput::SpectrogramColumns { first, columns }),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use super::{Job, JobOutput, JobUpdate};
+use crate::dsp::vad::{analyze, VadSettings};
+
+/// Activity scores and regions of a mono signal, in one step.
+pub struct ActivityJob {
+    samples: Vec<f32>,
+    sample_rate: f32,
+    settings: VadSettings,
+}
+
+impl ActivityJob {
+    pub fn new(samples: Vec<f32>, sample_rate: f32, settings: VadSettings) -> Self {
+        Self {
+            samples,
+            sample_rate,
+            settings,
+        }
+    }
+}
+
+impl Job for ActivityJob {
+    fn step(&mut self) -> JobUpdate {
+        let analysis = analyze(&self.samples, self.sample_rate, &self.settings);
+        JobUpdate::Done(Some(JobOutput::Activity(analysis)))
+    }
+}
@@ -0,0 +1,188 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use js_sys::Date;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{DedicatedWorkerGlobalScope, ErrorEvent, MessageEvent, Worker};
+
+use super::{Job, JobRequest, JobUpdate};
+use crate::Log;
+
+/// Script Trunk generates for the `worker` binary.
+const WORKER_SCRIPT: &str = "./worker_loader.js";
+
+#[derive(Default)]
+struct Shared {
+    updates: VecDeque<JobUpdate>,
+    /// Set when the worker fails to load or throws.
+    error: Option<String>,
+}
+
+struct Remote {
+    worker: Worker,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(ErrorEvent)>,
+}
+
+/// Runs a job in a Web Worker. When workers are unavailable the job runs on
+/// the main thread instead, a few milliseconds per frame.
+pub struct Runner {
+    remote: Option<Remote>,
+    shared: Rc<RefCell<Shared>>,
+    /// Kept until the worker answers, to fall back on if it can't load.
+    request: Option<JobRequest>,
+    local: Option<Box<dyn Job>>,
+    finished: bool,
+}
+
+impl Log for Runner {
+    fn name() -> &'static str {
+        "Jobs"
+    }
+}
+
+impl Runner {
+    /// Main-thread time spent on a local job per `take_updates`.
+    const LOCAL_BUDGET_MS: f64 = 8.0;
+
+    pub fn spawn(request: JobRequest) -> Self {
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        let mut runner = Self {
+            remote: None,
+            shared: shared.clone(),
+            request: None,
+            local: None,
+            finished: false,
+        };
+        match Self::start_worker(&request, shared) {
+            Ok(remote) => {
+                runner.remote = Some(remote);
+                runner.request = Some(request);
+            }
+            Err(e) => {
+                Self::log(&format!("no worker ({:?}), running on the main thread", e));
+                runner.local = Some(request.start());
+            }
+        }
+        runner
+    }
+
+    fn start_worker(request: &JobRequest, shared: Rc<RefCell<Shared>>) -> Result<Remote, JsValue> {
+        let worker = Worker::new(WORKER_SCRIPT)?;
+        let message = serde_wasm_bindgen::to_value(request)?;
+
+        let queue = shared.clone();
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let mut shared = queue.borrow_mut();
+            match serde_wasm_bindgen::from_value(event.data()) {
+                Ok(update) => shared.updates.push_back(update),
+                Err(e) => shared.error = Some(e.to_string()),
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        let on_error = Closure::wrap(Box::new(move |event: ErrorEvent| {
+            event.prevent_default();
+            shared.borrow_mut().error = Some(event.message());
+        }) as Box<dyn FnMut(ErrorEvent)>);
+
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        worker.post_message(&message)?;
+        Ok(Remote {
+            worker,
+            _on_message: on_message,
+            _on_error: on_error,
+        })
+    }
+
+    pub fn take_updates(&mut self) -> Vec<JobUpdate> {
+        if self.finished {
+            return vec![];
+        }
+        let mut updates: Vec<JobUpdate> = self.shared.borrow_mut().updates.drain(..).collect();
+        if !updates.is_empty() {
+            self.request = None;
+        }
+        let error = self.shared.borrow_mut().error.take();
+        if let Some(error) = error {
+            self.cancel();
+            match self.request.take() {
+                Some(request) => {
+                    Self::log(&format!(
+                        "worker failed ({}), running on the main thread",
+                        error
+                    ));
+                    self.local = Some(request.start());
+                }
+                None => updates.push(JobUpdate::Failed(error)),
+            }
+        }
+        if let Some(job) = self.local.as_mut() {
+            let deadline = Date::now() + Self::LOCAL_BUDGET_MS;
+            loop {
+                let update = job.step();
+                let finished = update.is_final();
+                updates.push(update);
+                if finished || Date::now() > deadline {
+                    break;
+                }
+            }
+        }
+        if updates.iter().any(JobUpdate::is_final) {
+            self.finished = true;
+            self.cancel();
+        }
+        updates
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(remote) = self.remote.take() {
+            remote.worker.set_onmessage(None);
+            remote.worker.set_onerror(None);
+            remote.worker.terminate();
+        }
+        self.local = None;
+    }
+}
+
+/// Entry point of the `worker` binary: runs each job it's sent and posts
+/// every update back.
+pub fn run_worker() {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let post = scope.clone();
+    let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let mut job = match serde_wasm_bindgen::from_value::<JobRequest>(event.data()) {
+            Ok(request) => request.start(),
+            Err(e) => Box::new(Failed(e.to_string())) as Box<dyn Job>,
+        };
+        loop {
+            let update = job.step();
+            let finished = update.is_final();
+            match serde_wasm_bindgen::to_value(&update) {
+                Ok(message) => {
+                    let _ = post.post_message(&message);
+                }
+                Err(e) => {
+                    let failed = JobUpdate::Failed(e.to_string());
+                    if let Ok(message) = serde_wasm_bindgen::to_value(&failed) {
+                        let _ = post.post_message(&message);
+                    }
+                    break;
+                }
+            }
+            if finished {
+                break;
+            }
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+}
+
+/// Reports a request the worker couldn't read.
+struct Failed(String);
+
+impl Job for Failed {
+    fn step(&mut self) -> JobUpdate {
+        JobUpdate::Failed(self.0.clone())
+    }
+}
@@ -1,18 +1,41 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+// Capture runs in the browser only; the rest of the GUI also builds
+// natively, without IndexedDB and with `capture` and `data_source` unused.
+pub mod analyzers;
+mod annotations;
 mod app;
+pub mod bridge;
 mod buffer;
+pub mod capture;
+mod channels;
+pub mod chunk;
+mod clock;
 mod data;
+#[cfg(target_arch = "wasm32")]
 pub mod data_source;
+pub mod dsp;
+pub mod error;
+mod export;
+mod import;
+pub mod jobs;
+pub mod logger;
+pub mod remote;
+pub mod settings;
+mod socket;
+mod summary;
+pub mod timeline;
 mod widgets;
 pub use app::TemplateApp;
-use wasm_bindgen::JsValue;
 
+/// Logs through the `log` crate with `name` as the target, so the console
+/// and the in-app log view can filter by component and level.
 pub trait Log {
     fn log(msg: &str) {
-        if Self::name() != "Data" {
-            web_sys::console::debug_1(&JsValue::from_str(&format!("[{}] {}", Self::name(), msg)));
-        }
+        log::debug!(target: Self::name(), "{}", msg);
+    }
+    fn warn(msg: &str) {
+        log::warn!(target: Self::name(), "{}", msg);
     }
     fn name() -> &'static str;
 }
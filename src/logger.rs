@@ -0,0 +1,110 @@
+//! Keeps the latest `log` records for the in-app log view and passes them
+//! on to the browser console, or to stderr natively.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use log::{Level, LevelFilter, Metadata, Record};
+
+use crate::clock;
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// `clock::now_ms()` when it was logged.
+    pub time: f64,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Older entries are dropped.
+const MAX_ENTRIES: usize = 2000;
+
+static ENTRIES: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+#[cfg(target_arch = "wasm32")]
+type Console = eframe::WebLogger;
+#[cfg(not(target_arch = "wasm32"))]
+type Console = env_logger::Logger;
+
+#[cfg(target_arch = "wasm32")]
+fn console(filter: LevelFilter) -> Console {
+    eframe::WebLogger::new(filter)
+}
+
+/// `RUST_LOG` overrides `filter`.
+#[cfg(not(target_arch = "wasm32"))]
+fn console(filter: LevelFilter) -> Console {
+    env_logger::Builder::new()
+        .filter_level(filter)
+        .parse_default_env()
+        .build()
+}
+
+struct Recorder {
+    console: Console,
+}
+
+impl log::Log for Recorder {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.console.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.console.log(record);
+        // a record logged while the view copies the entries is only printed
+        if let Ok(mut entries) = ENTRIES.try_lock() {
+            if entries.len() == MAX_ENTRIES {
+                entries.pop_front();
+            }
+            entries.push_back(LogEntry {
+                time: clock::now_ms(),
+                level: record.level(),
+                target: record.target().to_owned(),
+                message: record.args().to_string(),
+            });
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger for records up to `filter`. Only the first call has
+/// an effect.
+pub fn init(filter: LevelFilter) {
+    let recorder = Recorder {
+        console: console(filter),
+    };
+    if log::set_boxed_logger(Box::new(recorder)).is_ok() {
+        log::set_max_level(filter);
+    }
+}
+
+/// Recorded entries up to `level` whose target or message contains `text`,
+/// oldest first.
+pub fn entries(level: LevelFilter, text: &str) -> Vec<LogEntry> {
+    let text = text.to_lowercase();
+    ENTRIES
+        .lock()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter(|e| e.level <= level)
+                .filter(|e| {
+                    text.is_empty()
+                        || e.target.to_lowercase().contains(&text)
+                        || e.message.to_lowercase().contains(&text)
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut entries) = ENTRIES.lock() {
+        entries.clear();
+    }
+}
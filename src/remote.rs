@@ -0,0 +1,218 @@
+//! PCM streaming over WebSockets, shared by the web app, the `analyze`
+//! client and the reference server in `examples/pcm_server.rs`.
+//!
+//! Every binary message is one frame: an 8 byte little-endian header
+//! (`u32` sample rate, `u16` channels, `u8` sample format, `u8` reserved)
+//! followed by interleaved samples. Clients answer with `LiveMetrics` as
+//! JSON text messages.
+
+use crate::dsp::{
+    loudness::LoudnessMeter,
+    meter::{ChannelMeter, MeterSettings},
+    pitch::{note, PitchEstimate, Yin},
+    wav::{parse_raw, RawFormat},
+};
+
+const HEADER_LEN: usize = 8;
+
+/// One decoded frame of interleaved -1..1 samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PcmFrame {
+    pub sample_rate: f32,
+    pub channels: usize,
+    pub samples: Vec<f32>,
+}
+
+impl PcmFrame {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN {
+            return Err(format!("frame of {} bytes has no header", bytes.len()));
+        }
+        let sample_rate = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let channels = u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
+        let format = RawFormat::ALL
+            .get(bytes[6] as usize)
+            .copied()
+            .ok_or(format!("unknown sample format {}", bytes[6]))?;
+        if sample_rate == 0 {
+            return Err("sample rate 0".to_owned());
+        }
+        let wav = parse_raw(&bytes[HEADER_LEN..], format, channels, sample_rate as f32)?;
+        let frames = wav.frames();
+        let samples = (0..frames)
+            .flat_map(|frame| wav.channels.iter().map(move |channel| channel[frame]))
+            .collect();
+        Ok(Self {
+            sample_rate: wav.sample_rate,
+            channels,
+            samples,
+        })
+    }
+
+    /// Encodes the frame as `f32le` samples.
+    pub fn encode(&self) -> Vec<u8> {
+        let format = RawFormat::ALL
+            .iter()
+            .position(|f| *f == RawFormat::F32)
+            .unwrap_or_default();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.samples.len() * 4);
+        bytes.extend_from_slice(&(self.sample_rate as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.channels as u16).to_le_bytes());
+        bytes.extend_from_slice(&[format as u8, 0]);
+        for sample in &self.samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Cuts frames of any length into blocks of a fixed number of frames, so
+/// streamed audio lines up with the chunks of the timeline.
+#[derive(Default)]
+pub struct Reblocker {
+    pending: Vec<f32>,
+    format: Option<(f32, usize)>,
+}
+
+impl Reblocker {
+    /// Complete blocks of `frames` frames, interleaved. Audio of an earlier
+    /// format is dropped when the format changes.
+    pub fn push(&mut self, frame: &PcmFrame, frames: usize) -> Vec<Vec<f32>> {
+        let format = (frame.sample_rate, frame.channels);
+        if self.format != Some(format) {
+            self.pending.clear();
+            self.format = Some(format);
+        }
+        self.pending.extend_from_slice(&frame.samples);
+        let block = frames.max(1) * frame.channels.max(1);
+        let complete = self.pending.len() / block * block;
+        let blocks = self.pending[..complete]
+            .chunks_exact(block)
+            .map(<[f32]>::to_vec)
+            .collect();
+        self.pending.drain(..complete);
+        blocks
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct ChannelLevels {
+    pub peak_db: f32,
+    pub rms_db: f32,
+}
+
+/// Levels, loudness and pitch of the stream, published as JSON.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct LiveMetrics {
+    /// Audio received so far.
+    pub time_s: f32,
+    pub levels: Vec<ChannelLevels>,
+    pub momentary_lufs: Option<f32>,
+    pub short_term_lufs: Option<f32>,
+    pub integrated_lufs: Option<f32>,
+    pub pitch: Option<PitchEstimate>,
+    pub note: Option<String>,
+}
+
+/// Runs the live meters over a stream and reports them every `interval_s`.
+pub struct MetricsMeter {
+    sample_rate: f32,
+    channels: usize,
+    meters: Vec<ChannelMeter>,
+    settings: MeterSettings,
+    loudness: LoudnessMeter,
+    yin: Yin,
+    /// Mono audio collected for the next pitch estimate.
+    window: Vec<f32>,
+    pitch: Option<PitchEstimate>,
+    frames: usize,
+    next_report: usize,
+    pub interval_s: f32,
+}
+
+impl MetricsMeter {
+    const PITCH_WINDOW: usize = 2048;
+
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            meters: vec![ChannelMeter::default(); channels],
+            settings: MeterSettings::default(),
+            loudness: LoudnessMeter::new(sample_rate, channels),
+            yin: Yin::default(),
+            window: vec![],
+            pitch: None,
+            frames: 0,
+            next_report: 0,
+            interval_s: 0.1,
+        }
+    }
+
+    /// Whether `frame` can be fed to this meter.
+    pub fn accepts(&self, frame: &PcmFrame) -> bool {
+        frame.sample_rate == self.sample_rate && frame.channels == self.channels
+    }
+
+    /// Feeds interleaved samples; returns the metrics once an interval is complete.
+    pub fn process(&mut self, samples: &[f32]) -> Option<LiveMetrics> {
+        let channels = self.channels.max(1);
+        let planar: Vec<Vec<f32>> = (0..channels)
+            .map(|channel| {
+                samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(channels)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        for (meter, samples) in self.meters.iter_mut().zip(&planar) {
+            meter.process(samples, self.sample_rate, &self.settings);
+        }
+        self.loudness.process(&planar);
+        self.window.extend(
+            samples
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+        while self.window.len() >= Self::PITCH_WINDOW {
+            self.pitch = self
+                .yin
+                .estimate(&self.window[..Self::PITCH_WINDOW], self.sample_rate);
+            self.window.drain(..Self::PITCH_WINDOW);
+        }
+        self.frames += samples.len() / channels;
+        if self.frames < self.next_report {
+            return None;
+        }
+        self.next_report = self.frames + (self.interval_s * self.sample_rate) as usize;
+        Some(self.metrics())
+    }
+
+    pub fn metrics(&self) -> LiveMetrics {
+        LiveMetrics {
+            time_s: self.frames as f32 / self.sample_rate,
+            levels: self
+                .meters
+                .iter()
+                .map(|meter| {
+                    let levels = meter.levels();
+                    ChannelLevels {
+                        peak_db: levels.peak,
+                        rms_db: levels.rms,
+                    }
+                })
+                .collect(),
+            momentary_lufs: self.loudness.momentary(),
+            short_term_lufs: self.loudness.short_term(),
+            integrated_lufs: self.loudness.integrated(),
+            pitch: self.pitch,
+            note: self.pitch.map(|p| note(p.frequency).0),
+        }
+    }
+}
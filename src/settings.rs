@@ -0,0 +1,203 @@
+use crate::Log;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum InputSource {
+    Microphone,
+    /// Looped sine from `data_source::sine_buffer`.
+    TestSine,
+}
+
+impl InputSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Microphone => "Microphone",
+            Self::TestSine => "Test sine",
+        }
+    }
+}
+
+/// Analyzer parameters the user can change at runtime.
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// Samples per channel in one captured chunk.
+    pub block_size: usize,
+    /// How often the capture loop polls the analyser, per second.
+    pub refresh_rate: u32,
+    /// Most frames per second the UI draws while data keeps arriving; when
+    /// nothing changes it doesn't draw at all.
+    pub max_fps: u32,
+    /// Chunks written to IndexedDB as one page.
+    pub batch_size: usize,
+    /// Chunks shown in the detail view.
+    pub view_width: usize,
+    pub source: InputSource,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            block_size: 1024,
+            refresh_rate: 60,
+            max_fps: 30,
+            batch_size: 100,
+            view_width: 10,
+            source: InputSource::Microphone,
+        }
+    }
+}
+
+impl Log for Settings {
+    fn name() -> &'static str {
+        "Settings"
+    }
+}
+
+impl Settings {
+    pub const STORAGE_KEY: &'static str = "analyzer_settings";
+    const BLOCK_SIZES: [usize; 6] = [256, 512, 1024, 2048, 4096, 8192];
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !Self::BLOCK_SIZES.contains(&self.block_size) {
+            return Err(format!(
+                "block size must be one of {:?}, got {}",
+                Self::BLOCK_SIZES,
+                self.block_size
+            ));
+        }
+        if !(1..=120).contains(&self.refresh_rate) {
+            return Err(format!(
+                "refresh rate must be within 1..=120 Hz, got {}",
+                self.refresh_rate
+            ));
+        }
+        if !(1..=120).contains(&self.max_fps) {
+            return Err(format!(
+                "max frame rate must be within 1..=120 fps, got {}",
+                self.max_fps
+            ));
+        }
+        if !(10..=10_000).contains(&self.batch_size) {
+            return Err(format!(
+                "batch size must be within 10..=10000 chunks, got {}",
+                self.batch_size
+            ));
+        }
+        if !(2..=self.batch_size).contains(&self.view_width) {
+            return Err(format!(
+                "view width must be within 2..={} chunks (the batch size), got {}",
+                self.batch_size, self.view_width
+            ));
+        }
+        Ok(())
+    }
+
+    /// Nominal time between two chunks.
+    pub fn chunk_period_ms(&self) -> f32 {
+        1000.0 / self.refresh_rate as f32
+    }
+
+    /// Shortest time between two UI frames.
+    pub fn frame_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(1.0 / self.max_fps as f32)
+    }
+
+    /// Analyser `fftSize` that yields `block_size` time-domain samples per poll.
+    pub fn fft_size(&self) -> u32 {
+        (self.block_size * 2) as u32
+    }
+
+    /// Whether switching from `other` needs the capture graph to be rebuilt.
+    pub fn needs_capture_restart(&self, other: &Settings) -> bool {
+        self.block_size != other.block_size
+            || self.refresh_rate != other.refresh_rate
+            || self.source != other.source
+    }
+}
+
+/// Editable copy of the settings; nothing changes until the draft validates.
+#[derive(Default)]
+pub struct SettingsPanel {
+    draft: Option<Settings>,
+    error: Option<String>,
+}
+
+impl SettingsPanel {
+    /// Returns the new settings when the user applied a valid draft.
+    pub fn ui(&mut self, ui: &mut egui::Ui, current: &Settings) -> Option<Settings> {
+        let draft = self.draft.get_or_insert_with(|| current.clone());
+        egui::Grid::new("settings_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Block size");
+                egui::ComboBox::from_id_source("settings_block_size")
+                    .selected_text(format!("{} samples", draft.block_size))
+                    .show_ui(ui, |ui| {
+                        for size in Settings::BLOCK_SIZES {
+                            ui.selectable_value(&mut draft.block_size, size, format!("{}", size));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Refresh rate");
+                ui.add(egui::DragValue::new(&mut draft.refresh_rate).suffix(" Hz"));
+                ui.end_row();
+
+                ui.label("Max frame rate");
+                ui.add(egui::DragValue::new(&mut draft.max_fps).suffix(" fps"))
+                    .on_hover_text("The UI is only redrawn when something changes");
+                ui.end_row();
+
+                ui.label("Batch size");
+                ui.add(egui::DragValue::new(&mut draft.batch_size).suffix(" chunks"))
+                    .on_hover_text("Takes effect for the next recording");
+                ui.end_row();
+
+                ui.label("View width");
+                ui.add(egui::DragValue::new(&mut draft.view_width).suffix(" chunks"));
+                ui.end_row();
+
+                ui.label("Input source");
+                egui::ComboBox::from_id_source("settings_source")
+                    .selected_text(draft.source.label())
+                    .show_ui(ui, |ui| {
+                        for source in [InputSource::Microphone, InputSource::TestSine] {
+                            ui.selectable_value(&mut draft.source, source, source.label());
+                        }
+                    });
+                ui.end_row();
+            });
+
+        let mut applied = None;
+        ui.horizontal(|ui| {
+            let changed = draft != current;
+            if ui
+                .add_enabled(changed, egui::Button::new("Apply"))
+                .clicked()
+            {
+                match draft.validate() {
+                    Ok(()) => {
+                        Settings::log(&format!("applied {:?}", draft));
+                        applied = Some(draft.clone());
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+            if ui
+                .add_enabled(changed, egui::Button::new("Revert"))
+                .clicked()
+            {
+                *draft = current.clone();
+                self.error = None;
+            }
+            if ui.button("Defaults").clicked() {
+                *draft = Settings::default();
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        applied
+    }
+}
@@ -0,0 +1,22 @@
+//! WebSocket client for streamed audio: the browser's `WebSocket` on the
+//! web, a `tungstenite` connection on its own thread natively. Both queue
+//! binary messages until they're taken and repaint the UI when something
+//! arrives.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::Socket;
+#[cfg(target_arch = "wasm32")]
+pub use web::Socket;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SocketState {
+    Connecting,
+    Open,
+    /// With the reason, if the server or the browser gave one.
+    Closed(String),
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::TcpStream,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tungstenite::{client::IntoClientRequest, Error, Message, WebSocket};
+
+use super::SocketState;
+use crate::Log;
+
+struct Shared {
+    state: SocketState,
+    messages: VecDeque<Vec<u8>>,
+}
+
+/// Plain `ws://` connection served by a thread that queues binary messages
+/// until they're taken and repaints the UI when something arrives.
+pub struct Socket {
+    shared: Arc<Mutex<Shared>>,
+    /// Text to send; dropping it closes the connection.
+    outgoing: Sender<String>,
+}
+
+impl Log for Socket {
+    fn name() -> &'static str {
+        "Socket"
+    }
+}
+
+impl Socket {
+    /// Messages beyond this many are dropped while nobody takes them.
+    const MAX_QUEUED: usize = 1000;
+    /// How long a read waits before the thread checks for text to send.
+    const READ_TIMEOUT: Duration = Duration::from_millis(20);
+
+    pub fn connect(url: &str, repaint: egui::Context) -> Result<Self, String> {
+        let request = url.into_client_request().map_err(|e| e.to_string())?;
+        if request.uri().scheme_str() != Some("ws") {
+            return Err(format!("{}: only ws:// URLs are supported natively", url));
+        }
+        let shared = Arc::new(Mutex::new(Shared {
+            state: SocketState::Connecting,
+            messages: VecDeque::new(),
+        }));
+        let (outgoing, texts) = mpsc::channel();
+        let state = shared.clone();
+        thread::spawn(move || {
+            let reason = match Self::open(request) {
+                Ok(ws) => {
+                    Self::log("connected");
+                    state.lock().unwrap().state = SocketState::Open;
+                    repaint.request_repaint();
+                    Self::serve(ws, &state, &texts, &repaint)
+                }
+                Err(e) => e,
+            };
+            Self::log(&reason);
+            state.lock().unwrap().state = SocketState::Closed(reason);
+            repaint.request_repaint();
+        });
+        Ok(Self { shared, outgoing })
+    }
+
+    fn open(
+        request: tungstenite::handshake::client::Request,
+    ) -> Result<WebSocket<TcpStream>, String> {
+        let uri = request.uri();
+        let host = uri.host().unwrap_or_default().to_owned();
+        let port = uri.port_u16().unwrap_or(80);
+        let stream = TcpStream::connect((host.as_str(), port)).map_err(|e| e.to_string())?;
+        let (ws, _) = tungstenite::client(request, stream).map_err(|e| e.to_string())?;
+        ws.get_ref()
+            .set_read_timeout(Some(Self::READ_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        Ok(ws)
+    }
+
+    /// Moves messages both ways until either side closes; returns the reason.
+    fn serve(
+        mut ws: WebSocket<TcpStream>,
+        shared: &Mutex<Shared>,
+        texts: &Receiver<String>,
+        repaint: &egui::Context,
+    ) -> String {
+        loop {
+            loop {
+                match texts.try_recv() {
+                    Ok(text) => {
+                        if let Err(e) = ws.send(Message::Text(text)) {
+                            Self::warn(&format!("send failed: {}", e));
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        let _ = ws.close(None);
+                        let _ = ws.flush();
+                        return "disconnected".to_owned();
+                    }
+                }
+            }
+            match ws.read() {
+                Ok(Message::Binary(bytes)) => {
+                    let mut shared = shared.lock().unwrap();
+                    if shared.messages.len() >= Self::MAX_QUEUED {
+                        shared.messages.pop_front();
+                    }
+                    shared.messages.push_back(bytes);
+                    repaint.request_repaint();
+                }
+                Ok(Message::Close(frame)) => {
+                    return match frame {
+                        Some(frame) if !frame.reason.is_empty() => frame.reason.into_owned(),
+                        Some(frame) => format!("closed ({})", u16::from(frame.code)),
+                        None => "closed".to_owned(),
+                    };
+                }
+                Ok(_) => {}
+                Err(Error::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(Error::ConnectionClosed | Error::AlreadyClosed) => return "closed".to_owned(),
+                Err(e) => return e.to_string(),
+            }
+        }
+    }
+
+    pub fn state(&self) -> SocketState {
+        self.shared.lock().unwrap().state.clone()
+    }
+
+    pub fn take_messages(&self) -> Vec<Vec<u8>> {
+        self.shared.lock().unwrap().messages.drain(..).collect()
+    }
+
+    pub fn send_text(&self, text: &str) {
+        if self.state() == SocketState::Open {
+            let _ = self.outgoing.send(text.to_owned());
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+use super::SocketState;
+use crate::Log;
+
+struct Shared {
+    state: SocketState,
+    messages: VecDeque<Vec<u8>>,
+}
+
+/// Browser WebSocket that queues binary messages until they're taken and
+/// repaints the UI when something arrives.
+pub struct Socket {
+    ws: WebSocket,
+    shared: Rc<RefCell<Shared>>,
+    _on_open: Closure<dyn FnMut()>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+impl Log for Socket {
+    fn name() -> &'static str {
+        "Socket"
+    }
+}
+
+impl Socket {
+    /// Messages beyond this many are dropped while nobody takes them.
+    const MAX_QUEUED: usize = 1000;
+
+    pub fn connect(url: &str, repaint: egui::Context) -> Result<Self, String> {
+        let ws = WebSocket::new(url).map_err(|e| format!("{:?}", e))?;
+        ws.set_binary_type(BinaryType::Arraybuffer);
+        let shared = Rc::new(RefCell::new(Shared {
+            state: SocketState::Connecting,
+            messages: VecDeque::new(),
+        }));
+
+        let state = shared.clone();
+        let ctx = repaint.clone();
+        let on_open = Closure::wrap(Box::new(move || {
+            Self::log("connected");
+            state.borrow_mut().state = SocketState::Open;
+            ctx.request_repaint();
+        }) as Box<dyn FnMut()>);
+
+        let queue = shared.clone();
+        let ctx = repaint.clone();
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let Ok(buffer) = event.data().dyn_into::<ArrayBuffer>() else {
+                return;
+            };
+            let mut shared = queue.borrow_mut();
+            if shared.messages.len() >= Self::MAX_QUEUED {
+                shared.messages.pop_front();
+            }
+            shared.messages.push_back(Uint8Array::new(&buffer).to_vec());
+            ctx.request_repaint();
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        let state = shared.clone();
+        let on_close = Closure::wrap(Box::new(move |event: CloseEvent| {
+            let reason = match event.reason() {
+                reason if reason.is_empty() => format!("closed ({})", event.code()),
+                reason => reason,
+            };
+            Self::log(&reason);
+            state.borrow_mut().state = SocketState::Closed(reason);
+            repaint.request_repaint();
+        }) as Box<dyn FnMut(CloseEvent)>);
+
+        ws.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        Ok(Self {
+            ws,
+            shared,
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    pub fn state(&self) -> SocketState {
+        self.shared.borrow().state.clone()
+    }
+
+    pub fn take_messages(&self) -> Vec<Vec<u8>> {
+        self.shared.borrow_mut().messages.drain(..).collect()
+    }
+
+    pub fn send_text(&self, text: &str) {
+        if self.state() == SocketState::Open {
+            if let Err(e) = self.ws.send_with_str(text) {
+                Self::warn(&format!("send failed: {:?}", e));
+            }
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.ws.set_onopen(None);
+        self.ws.set_onmessage(None);
+        self.ws.set_onclose(None);
+        let _ = self.ws.close();
+    }
+}
@@ -0,0 +1,179 @@
+use crate::data::{to_amplitude, Chunk};
+
+/// Per-channel min/max envelope of one chunk, kept for the whole session so
+/// overview views never have to touch the raw chunks in IndexedDB.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct SummaryEntry {
+    pub id: usize,
+    pub levels: Vec<(f32, f32)>,
+    /// `Chunk::time`, in ms from the session start.
+    #[serde(default)]
+    pub time: f32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+pub struct Summary {
+    entries: Vec<SummaryEntry>,
+}
+
+impl Summary {
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn push(&mut self, chunk: &Chunk) {
+        let levels = (0..chunk.channels)
+            .map(|channel| {
+                chunk
+                    .channel(channel)
+                    .map(to_amplitude)
+                    .fold((0.0f32, 0.0f32), |(min, max), v| (min.min(v), max.max(v)))
+            })
+            .collect();
+        self.entries.push(SummaryEntry {
+            id: chunk.id,
+            levels,
+            time: chunk.time,
+        });
+    }
+
+    /// Id range covered by the summary, inclusive.
+    pub fn id_range(&self) -> Option<(usize, usize)> {
+        match (self.entries.first(), self.entries.last()) {
+            (Some(first), Some(last)) => Some((first.id, last.id)),
+            _ => None,
+        }
+    }
+
+    /// Converts timeline positions of this session to seconds and back.
+    pub fn timebase(&self, period_ms: f32) -> Timebase<'_> {
+        Timebase {
+            entries: &self.entries,
+            period_ms,
+        }
+    }
+
+    /// Reduces the session to `buckets` min/max pairs over `channels`.
+    pub fn envelope(&self, buckets: usize, channels: &[usize]) -> Vec<(f32, f32)> {
+        if self.entries.is_empty() || buckets == 0 {
+            return vec![];
+        }
+        let n = self.entries.len();
+        (0..buckets)
+            .filter_map(|b| {
+                let start = b * n / buckets;
+                let end = ((b + 1) * n / buckets).max(start + 1).min(n);
+                if start >= n {
+                    return None;
+                }
+                Some(
+                    self.entries[start..end]
+                        .iter()
+                        .flat_map(|e| channels.iter().filter_map(|channel| e.levels.get(*channel)))
+                        .fold((0.0f32, 0.0f32), |(min, max), (lo, hi)| {
+                            (min.min(*lo), max.max(*hi))
+                        }),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Seconds from the session start of timeline positions, from the capture
+/// times of the chunks: chunk `id` spans positions `id..id + 1`, which end at
+/// its `time`. Outside the session a chunk lasts `period_ms`.
+#[derive(Clone, Copy)]
+pub struct Timebase<'a> {
+    entries: &'a [SummaryEntry],
+    period_ms: f32,
+}
+
+impl Timebase<'_> {
+    /// Seconds at the start of the `i`th chunk of the session, or at the end
+    /// of the last one for `i == entries.len()`.
+    fn knot(&self, i: usize) -> f32 {
+        match i.checked_sub(1) {
+            Some(previous) => self.entries[previous].time / 1000.0,
+            None => (self.first() as f32 - 1.0) * self.period_ms / 1000.0,
+        }
+    }
+
+    fn first(&self) -> usize {
+        self.entries.first().map_or(1, |e| e.id)
+    }
+
+    pub fn seconds(&self, position: f32) -> f32 {
+        let chunks = position - self.first() as f32;
+        let n = self.entries.len();
+        let i = chunks.floor();
+        if i < 0.0 || i as usize >= n {
+            let edge = if i < 0.0 { 0 } else { n };
+            return self.knot(edge) + (chunks - edge as f32) * self.period_ms / 1000.0;
+        }
+        let (i, fraction) = (i as usize, chunks - i);
+        self.knot(i) + (self.knot(i + 1) - self.knot(i)) * fraction
+    }
+
+    pub fn position(&self, seconds: f32) -> f32 {
+        let first = self.first() as f32;
+        let n = self.entries.len();
+        // chunks that ended before `seconds`
+        let i = self.entries.partition_point(|e| e.time / 1000.0 < seconds);
+        if seconds < self.knot(0) || i == n {
+            let edge = if i == n { n } else { 0 };
+            return first + edge as f32 + (seconds - self.knot(edge)) * 1000.0 / self.period_ms;
+        }
+        let (start, end) = (self.knot(i), self.knot(i + 1));
+        let fraction = if end > start {
+            (seconds - start) / (end - start)
+        } else {
+            0.0
+        };
+        first + i as f32 + fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD_MS: f32 = 20.0;
+
+    /// A summary of chunks ending at `times` ms.
+    fn summary(times: &[f32]) -> Summary {
+        let mut summary = Summary::default();
+        for (i, time) in times.iter().enumerate() {
+            summary.push(&Chunk::new(i + 1, vec![128; 4], *time));
+        }
+        summary
+    }
+
+    #[test]
+    fn follows_the_capture_times_of_the_chunks() {
+        // chunks 1 and 2 last 20 ms, 3 took 60 ms, 4 overlaps
+        let summary = summary(&[20.0, 40.0, 100.0, 110.0]);
+        let timebase = summary.timebase(PERIOD_MS);
+        for (position, seconds) in [
+            (1.0, 0.0),
+            (2.5, 0.03),
+            (3.0, 0.04),
+            (3.5, 0.07),
+            (4.0, 0.1),
+            (5.0, 0.11),
+            // beyond the session at the nominal period
+            (6.0, 0.13),
+            (0.0, -0.02),
+        ] {
+            assert!((timebase.seconds(position) - seconds).abs() < 1e-6);
+            assert!((timebase.position(seconds) - position).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_period_without_chunks() {
+        let summary = Summary::default();
+        let timebase = summary.timebase(PERIOD_MS);
+        assert_eq!(timebase.seconds(51.0), 1.0);
+        assert_eq!(timebase.position(1.0), 51.0);
+    }
+}
@@ -0,0 +1,264 @@
+use egui::{epaint::PathStroke, pos2, Align2, Color32, FontId, Rect, Sense};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum AmplitudeScale {
+    Linear,
+    Percent,
+    Dbfs,
+}
+
+impl AmplitudeScale {
+    pub const ALL: [AmplitudeScale; 3] = [Self::Linear, Self::Percent, Self::Dbfs];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::Percent => "Percent",
+            Self::Dbfs => "dBFS",
+        }
+    }
+}
+
+/// Vertical axis of the waveform view.
+///
+/// Samples are first mapped to a "display value" in -1..1 depending on the
+/// scale (identity for linear/percent, a signed log mapping for dBFS), then
+/// zoomed and shifted so `offset` sits in the middle of the body.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AmplitudeAxis {
+    pub scale: AmplitudeScale,
+    pub zoom: f32,
+    pub offset: f32,
+    /// Lowest level shown on the dBFS scale at zoom 1.
+    pub db_range: f32,
+    pub auto_scale: bool,
+    pub show_grid: bool,
+}
+
+impl Default for AmplitudeAxis {
+    fn default() -> Self {
+        Self {
+            scale: AmplitudeScale::Linear,
+            zoom: 1.0,
+            offset: 0.0,
+            db_range: 60.0,
+            auto_scale: false,
+            show_grid: true,
+        }
+    }
+}
+
+pub fn to_dbfs(amplitude: f32) -> f32 {
+    20.0 * amplitude.abs().max(1e-10).log10()
+}
+
+impl AmplitudeAxis {
+    pub const WIDTH: f32 = 48.0;
+    const MIN_ZOOM: f32 = 0.5;
+    const MAX_ZOOM: f32 = 1000.0;
+    const MIN_TICK_SPACING: f32 = 24.0;
+
+    pub fn display_value(&self, sample: f32) -> f32 {
+        match self.scale {
+            AmplitudeScale::Linear | AmplitudeScale::Percent => sample,
+            AmplitudeScale::Dbfs => {
+                let level = (1.0 + to_dbfs(sample) / self.db_range).max(0.0);
+                level.copysign(sample)
+            }
+        }
+    }
+
+    /// Maps a sample to the plot's -1..1 y range (positive is up).
+    pub fn to_plot(&self, sample: f32) -> f32 {
+        (self.display_value(sample) - self.offset) * self.zoom
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.offset = 0.0;
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+    }
+
+    /// Fits the view so the largest visible sample fills most of the body.
+    pub fn fit_to_peak(&mut self, peak: f32) {
+        let peak = self.display_value(peak.abs());
+        self.offset = 0.0;
+        if peak > 0.0 {
+            self.zoom = (0.95 / peak).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        } else {
+            self.zoom = 1.0;
+        }
+    }
+
+    fn format_tick(&self, value: f32) -> String {
+        match self.scale {
+            AmplitudeScale::Linear => format!("{:.3}", value),
+            AmplitudeScale::Percent => format!("{:.1}%", value * 100.0),
+            AmplitudeScale::Dbfs => {
+                if value == 0.0 {
+                    "-inf".to_owned()
+                } else {
+                    format!("{:.0}", (value.abs() - 1.0) * self.db_range)
+                }
+            }
+        }
+    }
+
+    /// Display values at which gridlines are drawn for a body of `height` pixels.
+    fn ticks(&self, height: f32) -> Vec<f32> {
+        let half_span = 1.0 / self.zoom;
+        let (low, high) = (self.offset - half_span, self.offset + half_span);
+        let max_ticks = (height / Self::MIN_TICK_SPACING).max(2.0);
+        let mut ticks = vec![];
+        match self.scale {
+            AmplitudeScale::Linear | AmplitudeScale::Percent => {
+                let step = nice_step(2.0 * half_span / max_ticks);
+                let mut value = (low / step).ceil() * step;
+                while value <= high {
+                    ticks.push(value);
+                    value += step;
+                }
+            }
+            AmplitudeScale::Dbfs => {
+                let step_db = nice_step(self.db_range * 2.0 * half_span / max_ticks).max(1.0);
+                let mut db = 0.0;
+                while db > -self.db_range {
+                    let level = 1.0 + db / self.db_range;
+                    for value in [level, -level] {
+                        if value >= low && value <= high {
+                            ticks.push(value);
+                        }
+                    }
+                    db -= step_db;
+                }
+                if low <= 0.0 && high >= 0.0 {
+                    ticks.push(0.0);
+                }
+            }
+        }
+        ticks
+    }
+
+    /// Handles zoom/offset interaction on the axis strip at the left of `body_rect`
+    /// and returns whether the pointer is over the strip.
+    pub fn interact(&mut self, ui: &mut egui::Ui, body_rect: Rect) -> bool {
+        let axis_rect = Rect::from_min_max(
+            body_rect.min,
+            pos2(body_rect.min.x + Self::WIDTH, body_rect.max.y),
+        );
+        let response = ui.interact(
+            axis_rect,
+            ui.id().with("amplitude_axis"),
+            Sense::click_and_drag(),
+        );
+        if response.double_clicked() {
+            self.reset();
+            self.auto_scale = false;
+        }
+        if response.dragged() {
+            self.auto_scale = false;
+            let delta = response.drag_delta().y / (body_rect.height() * 0.5) / self.zoom;
+            self.offset += delta;
+        }
+        if response.hovered() {
+            let scroll = ui.input(|i| i.raw_scroll_delta.y);
+            if scroll != 0.0 {
+                self.auto_scale = false;
+                self.zoom_by((scroll * 0.005).exp());
+            }
+        }
+        response.hovered()
+    }
+
+    pub fn draw(&self, ui: &mut egui::Ui, body_rect: Rect) {
+        let color = if ui.visuals().dark_mode {
+            Color32::from_additive_luminance(96)
+        } else {
+            Color32::from_black_alpha(140)
+        };
+        let grid_color = color.gamma_multiply(0.3);
+        let to_screen = egui::emath::RectTransform::from_to(
+            Rect::from_x_y_ranges(0.0..=1.0, 1.0..=-1.0),
+            body_rect,
+        );
+        let font_id = FontId::new(10.0, egui::FontFamily::Monospace);
+        let painter = ui.painter_at(body_rect);
+        for value in self.ticks(body_rect.height()) {
+            let y = (value - self.offset) * self.zoom;
+            if self.show_grid {
+                painter.line_segment(
+                    [to_screen * pos2(0.0, y), to_screen * pos2(1.0, y)],
+                    PathStroke::new(0.5, grid_color),
+                );
+            }
+            let pos = to_screen * pos2(0.0, y);
+            painter.text(
+                pos2(pos.x + 2.0, pos.y),
+                Align2::LEFT_CENTER,
+                self.format_tick(value),
+                font_id.clone(),
+                color,
+            );
+        }
+        let x = body_rect.min.x + Self::WIDTH;
+        painter.line_segment(
+            [pos2(x, body_rect.min.y), pos2(x, body_rect.max.y)],
+            PathStroke::new(0.5, grid_color),
+        );
+    }
+
+    pub fn controls(&mut self, ui: &mut egui::Ui, visible_peak: f32) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("amplitude_scale")
+                .selected_text(self.scale.label())
+                .show_ui(ui, |ui| {
+                    for scale in AmplitudeScale::ALL {
+                        ui.selectable_value(&mut self.scale, scale, scale.label());
+                    }
+                });
+            if self.scale == AmplitudeScale::Dbfs {
+                ui.add(
+                    egui::DragValue::new(&mut self.db_range)
+                        .range(12.0..=144.0)
+                        .suffix(" dB"),
+                );
+            }
+            ui.checkbox(&mut self.show_grid, "Grid");
+            ui.checkbox(&mut self.auto_scale, "Auto-scale");
+            if ui.button("Fit").clicked() {
+                self.fit_to_peak(visible_peak);
+            }
+            if ui.button("Reset").clicked() {
+                self.reset();
+                self.auto_scale = false;
+            }
+            ui.label(format!("x{:.1}", self.zoom));
+        });
+        if self.auto_scale {
+            self.fit_to_peak(visible_peak);
+        }
+    }
+}
+
+/// Rounds `raw` up to 1, 2 or 5 times a power of ten.
+fn nice_step(raw: f32) -> f32 {
+    if raw <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
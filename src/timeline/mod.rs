@@ -0,0 +1,627 @@
+//! Scope view: a time axis over chunked audio with lanes stacked below it.
+//! It knows nothing about capture or storage; hosts feed it through
+//! `TimelineSource`, add their own `TimelineLane`s and react to the events
+//! in `TimelineResponse`.
+
+pub mod amplitude;
+mod waveform;
+
+use egui::{
+    epaint::PathStroke, pos2, vec2, Align2, Color32, CursorIcon, FontId, Pos2, Rect, Sense,
+};
+
+use crate::Log;
+use amplitude::AmplitudeAxis;
+pub use waveform::WaveformLane;
+
+/// Chunks shown across the timeline, inclusive.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct View {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self { start: 1, end: 10 }
+    }
+}
+
+impl View {
+    /// Number of chunks in the view.
+    pub fn width(&self) -> usize {
+        self.end - self.start + 1
+    }
+
+    /// Where `position` falls across the view, 0 at its start and 1 at its end.
+    pub fn fraction(&self, position: f32) -> f32 {
+        (position - self.start as f32) / self.width() as f32
+    }
+
+    /// Inverse of `fraction`.
+    pub fn position(&self, fraction: f32) -> f32 {
+        self.start as f32 + fraction * self.width() as f32
+    }
+
+    /// `width` chunks centered on `position`, not starting before chunk 1.
+    pub fn centered(position: f32, width: usize) -> Self {
+        let start = (position - width as f32 / 2.0).round().max(1.0) as usize;
+        Self {
+            start,
+            end: start + width.max(1) - 1,
+        }
+    }
+}
+
+/// Where a timeline gets its waveforms from.
+pub trait TimelineSource {
+    /// Duration of one chunk, which scales the time axis.
+    fn chunk_period_ms(&self) -> f32;
+    /// Channels to draw a waveform lane for.
+    fn channels(&self) -> Vec<usize>;
+    /// Samples in -1..1 of `channel`, spread evenly across `view`.
+    fn samples(&self, view: &View, channel: usize) -> Vec<f32>;
+    fn channel_name(&self, channel: usize) -> String {
+        format!("{}", channel + 1)
+    }
+}
+
+/// A horizontal strip below the timeline header. Lanes share the time axis,
+/// cursor and selection; the timeline takes care of their order, height and
+/// collapsing.
+pub trait TimelineLane {
+    /// Stable key the lane's layout is remembered under.
+    fn key(&self) -> String;
+    fn title(&self) -> String;
+    fn default_height(&self) -> f32 {
+        60.0
+    }
+    /// Paints the lane into `rect`, which spans `view`. `cursor` is a
+    /// position like `TimelineMarker::position`.
+    fn paint(&self, ui: &mut egui::Ui, rect: Rect, view: &View, cursor: f32);
+}
+
+/// A vertical line across the timeline body, flagged in the header.
+pub struct TimelineMarker {
+    /// Chunk id plus the fraction of the chunk, e.g. 12.5 is halfway into chunk 12.
+    pub position: f32,
+    pub color: Color32,
+    pub label: Option<String>,
+}
+
+/// A shaded span of the timeline body with a bar in the header.
+pub struct TimelineRegion {
+    pub start: f32,
+    pub end: f32,
+    pub color: Color32,
+    pub label: Option<String>,
+}
+
+/// What the user did to the timeline this frame.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimelineEvent {
+    /// Dragged in the header to this position.
+    CursorMoved(f32),
+    SelectionChanged(Option<(f32, f32)>),
+    /// Zoomed to this many chunks across the view.
+    Zoomed(usize),
+}
+
+pub struct TimelineResponse {
+    pub header_rect: Rect,
+    /// Spans all lanes.
+    pub body_rect: Rect,
+    pub events: Vec<TimelineEvent>,
+    /// Position clicked in the header, if any.
+    pub header_clicked: Option<f32>,
+    /// Whether the pointer is over the amplitude axis of the waveforms.
+    pub axis_hovered: bool,
+}
+
+/// Sizes and colors of the timeline. Colors left at `None` follow the egui visuals.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TimelineStyle {
+    pub header_height: f32,
+    pub title_height: f32,
+    /// Spacing of the header ticks; every fifth is labelled.
+    pub tick_ms: f32,
+    pub waveform_color: Option<Color32>,
+    pub waveform_width: f32,
+    pub cursor_color: Option<Color32>,
+    pub selection_color: Option<Color32>,
+}
+
+impl Default for TimelineStyle {
+    fn default() -> Self {
+        Self {
+            header_height: 30.0,
+            title_height: 14.0,
+            tick_ms: 5.0,
+            waveform_color: None,
+            waveform_width: 2.0,
+            cursor_color: None,
+            selection_color: None,
+        }
+    }
+}
+
+/// The timeline's state: view, cursor and selection, the amplitude axis of
+/// the waveforms and the order, heights and collapsed state of the lanes.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct Timeline {
+    pub style: TimelineStyle,
+    pub amplitude: AmplitudeAxis,
+    lanes: Vec<LaneLayout>,
+    #[serde(skip)]
+    view: View,
+    #[serde(skip)]
+    cursor: f32,
+    #[serde(skip)]
+    selection: Option<(f32, f32)>,
+    #[serde(skip)]
+    anchor: Option<f32>,
+    /// Header and body as drawn last frame.
+    #[serde(skip)]
+    rects: Option<(Rect, Rect)>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+struct LaneLayout {
+    key: String,
+    height: f32,
+    collapsed: bool,
+}
+
+impl Log for Timeline {
+    fn name() -> &'static str {
+        "Timeline"
+    }
+}
+
+impl Timeline {
+    const MIN_LANE_HEIGHT: f32 = 20.0;
+    const MAX_LANE_HEIGHT: f32 = 600.0;
+
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        self.view = view;
+    }
+
+    pub fn cursor(&self) -> f32 {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, position: f32) {
+        self.cursor = position;
+    }
+
+    /// Selected span as timeline positions, if any.
+    pub fn selection(&self) -> Option<(f32, f32)> {
+        self.selection
+    }
+
+    pub fn set_selection(&mut self, selection: Option<(f32, f32)>) {
+        self.selection = selection;
+    }
+
+    /// Draws the header, a waveform lane per channel of `source` and `lanes`,
+    /// in the user's order.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        source: &dyn TimelineSource,
+        lanes: &[&dyn TimelineLane],
+    ) -> TimelineResponse {
+        let view = self.view;
+        let mut events = vec![];
+        let waveforms: Vec<WaveformLane<'_>> = source
+            .channels()
+            .into_iter()
+            .map(|channel| WaveformLane {
+                channel,
+                name: source.channel_name(channel),
+                samples: source.samples(&view, channel),
+                amplitude: &self.amplitude,
+                color: self.style.waveform_color,
+                width: self.style.waveform_width,
+            })
+            .collect();
+        let all: Vec<&dyn TimelineLane> = waveforms
+            .iter()
+            .map(|lane| lane as &dyn TimelineLane)
+            .chain(lanes.iter().copied())
+            .collect();
+        for lane in &all {
+            let key = lane.key();
+            if !self.lanes.iter().any(|l| l.key == key) {
+                self.lanes.push(LaneLayout {
+                    key,
+                    height: lane.default_height(),
+                    collapsed: false,
+                });
+            }
+        }
+        let mut ordered: Vec<(usize, &dyn TimelineLane)> = all
+            .iter()
+            .map(|lane| {
+                let key = lane.key();
+                let index = self.lanes.iter().position(|l| l.key == key).unwrap();
+                (index, *lane)
+            })
+            .collect();
+        ordered.sort_by_key(|(index, _)| *index);
+
+        let style = &self.style;
+        let lanes_height: f32 = ordered
+            .iter()
+            .map(|(index, _)| {
+                let layout = &self.lanes[*index];
+                style.title_height + if layout.collapsed { 0.0 } else { layout.height }
+            })
+            .sum();
+        let width = ui.available_width();
+        let (_, rect) = ui.allocate_space(vec2(
+            width,
+            style.header_height + lanes_height.max(Self::MIN_LANE_HEIGHT),
+        ));
+        let header_rect = Rect::from_min_size(rect.min, vec2(width, style.header_height));
+        let body_rect = Rect::from_min_max(pos2(rect.min.x, header_rect.max.y), rect.max);
+        self.rects = Some((header_rect, body_rect));
+
+        let vis = ui.style().noninteractive();
+        let (color, title_color, text_color) = (vis.weak_bg_fill, vis.bg_fill, vis.text_color());
+        let bg_stroke = egui::Stroke {
+            width: 1.0,
+            ..vis.bg_stroke
+        };
+        ui.painter().rect(header_rect, 0.0, color, bg_stroke);
+        ui.painter().rect(body_rect, 0.0, color, bg_stroke);
+        Self::draw_header(ui, header_rect, &view, source.chunk_period_ms(), style);
+
+        // before the lanes, so their titles and grips get the pointer first
+        let header_clicked = Self::scrub(ui, header_rect, &view, &mut events);
+        if let Some(TimelineEvent::CursorMoved(position)) = events.last() {
+            self.cursor = *position;
+        }
+        let response = ui.interact(
+            body_rect,
+            ui.id().with("timeline_body"),
+            Sense::click_and_drag(),
+        );
+        let position = |pointer: Pos2| view.position((pointer.x - rect.min.x) / rect.width());
+        let selection = self.selection;
+        if response.drag_started() {
+            self.anchor = response.interact_pointer_pos().map(position);
+        }
+        if response.dragged() {
+            if let (Some(anchor), Some(pointer)) = (self.anchor, response.interact_pointer_pos()) {
+                let pointer = position(pointer);
+                self.selection = Some((anchor.min(pointer), anchor.max(pointer)));
+            }
+        }
+        if response.clicked() {
+            self.selection = None;
+        }
+        if self.selection != selection {
+            events.push(TimelineEvent::SelectionChanged(self.selection));
+        }
+
+        let font_id = FontId::new(10.0, egui::FontFamily::Proportional);
+        let mut top = body_rect.min.y;
+        let mut dragged = None;
+        let mut spans = vec![];
+        let mut waveform_rect: Option<Rect> = None;
+        for (index, lane) in &ordered {
+            let layout = &mut self.lanes[*index];
+            let lane_top = top;
+            let title_rect =
+                Rect::from_x_y_ranges(body_rect.x_range(), top..=top + self.style.title_height);
+            top = title_rect.max.y;
+            ui.painter().rect_filled(title_rect, 0.0, title_color);
+            ui.painter().text(
+                title_rect.left_center() + vec2(4.0, 0.0),
+                Align2::LEFT_CENTER,
+                format!(
+                    "{} {}",
+                    if layout.collapsed { "⏵" } else { "⏷" },
+                    lane.title()
+                ),
+                font_id.clone(),
+                text_color,
+            );
+            let response = ui
+                .interact(
+                    title_rect,
+                    ui.id().with(("lane_title", &layout.key)),
+                    Sense::click_and_drag(),
+                )
+                .on_hover_text("Click to collapse, drag to reorder");
+            if response.clicked() {
+                layout.collapsed = !layout.collapsed;
+            }
+            if response.dragged() {
+                ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+                dragged = response.interact_pointer_pos().map(|p| (*index, p.y));
+            }
+            if !layout.collapsed {
+                let lane_rect =
+                    Rect::from_x_y_ranges(body_rect.x_range(), top..=top + layout.height);
+                top = lane_rect.max.y;
+                lane.paint(ui, lane_rect, &view, self.cursor);
+                if layout.key.starts_with(WaveformLane::KEY) {
+                    waveform_rect = Some(waveform_rect.map_or(lane_rect, |r| r.union(lane_rect)));
+                }
+                let grip = Rect::from_x_y_ranges(
+                    body_rect.x_range(),
+                    lane_rect.max.y - 3.0..=lane_rect.max.y + 1.0,
+                );
+                let response = ui.interact(
+                    grip,
+                    ui.id().with(("lane_grip", &layout.key)),
+                    Sense::drag(),
+                );
+                if response.hovered() || response.dragged() {
+                    ui.ctx().set_cursor_icon(CursorIcon::ResizeVertical);
+                }
+                if response.dragged() {
+                    layout.height = (layout.height + response.drag_delta().y)
+                        .clamp(Self::MIN_LANE_HEIGHT, Self::MAX_LANE_HEIGHT);
+                }
+            }
+            ui.painter().line_segment(
+                [pos2(body_rect.min.x, top), pos2(body_rect.max.x, top)],
+                bg_stroke,
+            );
+            spans.push((*index, lane_top..=top));
+        }
+        drop(waveforms);
+        if let Some((from, y)) = dragged {
+            if let Some((to, _)) = spans
+                .iter()
+                .find(|(i, span)| *i != from && span.contains(&y))
+            {
+                let layout = self.lanes.remove(from);
+                self.lanes.insert(*to, layout);
+            }
+        }
+        let axis_hovered = waveform_rect.map_or(false, |rect| self.amplitude.interact(ui, rect));
+
+        if ui.rect_contains_pointer(rect) {
+            let zoom = ui.input(|i| i.zoom_delta());
+            if zoom != 1.0 {
+                let width = ((view.width() as f32 / zoom).round() as usize).max(2);
+                if width != view.width() {
+                    self.view = View::centered(self.cursor, width);
+                    events.push(TimelineEvent::Zoomed(width));
+                }
+            }
+        }
+
+        let painter = ui.painter_at(rect);
+        let x = |position: f32| rect.min.x + view.fraction(position).clamp(0.0, 1.0) * rect.width();
+        if let Some((start, end)) = self.selection {
+            let color = self
+                .style
+                .selection_color
+                .unwrap_or(ui.visuals().selection.bg_fill.gamma_multiply(0.25));
+            painter.rect_filled(
+                Rect::from_x_y_ranges(x(start)..=x(end), rect.y_range()),
+                0.0,
+                color,
+            );
+        }
+        painter.line_segment(
+            [
+                pos2(x(self.cursor), body_rect.min.y),
+                pos2(x(self.cursor), body_rect.max.y),
+            ],
+            PathStroke::new(1.0, self.style.cursor_color.unwrap_or(text_color)),
+        );
+        TimelineResponse {
+            header_rect,
+            body_rect,
+            events,
+            header_clicked,
+            axis_hovered,
+        }
+    }
+
+    /// Dragging in the header moves the cursor; returns where it was clicked.
+    fn scrub(
+        ui: &mut egui::Ui,
+        header_rect: Rect,
+        view: &View,
+        events: &mut Vec<TimelineEvent>,
+    ) -> Option<f32> {
+        let response = ui.interact(
+            header_rect,
+            ui.id().with("timeline_header"),
+            Sense::click_and_drag(),
+        );
+        let position = response
+            .interact_pointer_pos()
+            .map(|pointer| view.position((pointer.x - header_rect.min.x) / header_rect.width()));
+        if response.dragged() {
+            events.extend(position.map(TimelineEvent::CursorMoved));
+        }
+        position.filter(|_| response.clicked())
+    }
+
+    /// Draws the `markers` in view over the timeline drawn by `show`.
+    pub fn draw_markers(&self, ui: &mut egui::Ui, markers: &[TimelineMarker]) {
+        let Some((header_rect, body_rect)) = self.rects else {
+            return;
+        };
+        let view = &self.view;
+        let painter = ui.painter_at(header_rect.union(body_rect));
+        let font_id = FontId::new(9.0, egui::FontFamily::Proportional);
+        for marker in markers {
+            let t = view.fraction(marker.position);
+            if !(0.0..=1.0).contains(&t) {
+                continue;
+            }
+            let x = body_rect.min.x + t * body_rect.width();
+            painter.line_segment(
+                [pos2(x, body_rect.min.y), pos2(x, body_rect.max.y)],
+                PathStroke::new(1.0, marker.color.gamma_multiply(0.6)),
+            );
+            let flag = pos2(x, header_rect.max.y);
+            painter.add(egui::Shape::convex_polygon(
+                vec![flag, flag + vec2(-4.0, -6.0), flag + vec2(4.0, -6.0)],
+                marker.color,
+                egui::Stroke::NONE,
+            ));
+            if let Some(label) = &marker.label {
+                painter.text(
+                    flag + vec2(5.0, -6.0),
+                    Align2::LEFT_BOTTOM,
+                    label,
+                    font_id.clone(),
+                    marker.color,
+                );
+            }
+        }
+    }
+
+    /// Draws the `regions` in view over the timeline drawn by `show`, below any markers.
+    pub fn draw_regions(&self, ui: &mut egui::Ui, regions: &[TimelineRegion]) {
+        let Some((header_rect, body_rect)) = self.rects else {
+            return;
+        };
+        let view = &self.view;
+        let painter = ui.painter_at(header_rect.union(body_rect));
+        let font_id = FontId::new(9.0, egui::FontFamily::Proportional);
+        let x = |position: f32| {
+            body_rect.min.x + view.fraction(position).clamp(0.0, 1.0) * body_rect.width()
+        };
+        for region in regions {
+            let (left, right) = (x(region.start), x(region.end));
+            if view.fraction(region.end) < 0.0 || view.fraction(region.start) > 1.0 {
+                continue;
+            }
+            painter.rect_filled(
+                Rect::from_x_y_ranges(left..=right, body_rect.y_range()),
+                0.0,
+                region.color.gamma_multiply(0.15),
+            );
+            let bar = Rect::from_x_y_ranges(
+                left..=right.max(left + 1.0),
+                header_rect.max.y - 4.0..=header_rect.max.y,
+            );
+            painter.rect_filled(bar, 0.0, region.color);
+            if let Some(label) = &region.label {
+                painter.text(
+                    pos2(left + 2.0, bar.min.y - 1.0),
+                    Align2::LEFT_BOTTOM,
+                    label,
+                    font_id.clone(),
+                    region.color,
+                );
+            }
+        }
+    }
+
+    fn draw_header(
+        ui: &mut egui::Ui,
+        rect: Rect,
+        view: &View,
+        chunk_period_ms: f32,
+        style: &TimelineStyle,
+    ) {
+        let color = if ui.visuals().dark_mode {
+            Color32::from_additive_luminance(96)
+        } else {
+            Color32::from_black_alpha(140)
+        };
+        let time = |position: f32| (position - 1.0) * chunk_period_ms;
+        let step = Step {
+            pixel_width: rect.width(),
+            calibration: style.tick_ms,
+            time_range: (time(view.start as f32), time((view.end + 1) as f32)),
+        };
+        if step.time_range.1 <= step.time_range.0 || step.calibration <= 0.0 {
+            return;
+        }
+        let to_screen =
+            egui::emath::RectTransform::from_to(Rect::from_x_y_ranges(0.0..=1.0, 0.0..=1.0), rect);
+        let font_id = FontId::new(10.0, egui::FontFamily::Monospace);
+        for (i, item) in step.iter().enumerate() {
+            let mut start = pos2(item.pos, 0.8);
+            let end = pos2(item.pos, 1.0);
+            if i % 5 == 0 {
+                start.y = 0.5;
+                let pos = to_screen * pos2(item.pos, 0.05);
+                let text = format!("{}", item.time.floor());
+                ui.painter()
+                    .text(pos, Align2::CENTER_TOP, text, font_id.clone(), color);
+            }
+            ui.painter().line_segment(
+                [to_screen * end, to_screen * start],
+                PathStroke::new(0.5, color),
+            );
+        }
+    }
+}
+
+struct Step {
+    pixel_width: f32,
+    calibration: f32,
+    time_range: (f32, f32),
+}
+
+struct StepIter<'a> {
+    step: &'a Step,
+    cur: f32,
+}
+
+impl Step {
+    fn iter(&self) -> StepIter<'_> {
+        StepIter {
+            step: self,
+            cur: 0.0,
+        }
+    }
+}
+
+struct StepIterItem {
+    pos: f32,
+    time: f32,
+}
+impl StepIter<'_> {
+    fn time(&self) -> f32 {
+        self.step.time_range.0 + self.cur * (self.step.time_range.1 - self.step.time_range.0)
+    }
+}
+impl Log for StepIter<'_> {
+    fn name() -> &'static str {
+        "StepIter"
+    }
+}
+impl Iterator for StepIter<'_> {
+    type Item = StepIterItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time_range = self.step.time_range;
+        let time_span = time_range.1 - time_range.0;
+        // Self::log(&format!("time_range: {}, {}", time_range.0, time_range.1));
+        assert!(time_span > 0.0);
+        let resolution = 1.0 / self.step.pixel_width;
+        let time_resolution = time_span * resolution;
+        while self.cur < 1.0 {
+            let remain = self.time() % self.step.calibration;
+            if remain < time_resolution {
+                let pos = self.cur;
+                let time = self.time();
+                self.cur += resolution;
+                return Some(StepIterItem { pos, time });
+            }
+            self.cur += resolution;
+        }
+        None
+    }
+}
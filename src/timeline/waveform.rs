@@ -0,0 +1,60 @@
+use egui::{epaint::PathStroke, pos2, Color32, Pos2, Rect};
+
+use super::{amplitude::AmplitudeAxis, TimelineLane, View};
+
+/// Samples of one channel across the view.
+pub struct WaveformLane<'a> {
+    pub channel: usize,
+    pub name: String,
+    /// -1..1, spread evenly across the view.
+    pub samples: Vec<f32>,
+    pub amplitude: &'a AmplitudeAxis,
+    /// Follows the egui visuals if `None`.
+    pub color: Option<Color32>,
+    pub width: f32,
+}
+
+impl WaveformLane<'_> {
+    /// Prefix of the keys of all waveform lanes.
+    pub const KEY: &'static str = "waveform";
+}
+
+impl TimelineLane for WaveformLane<'_> {
+    fn key(&self) -> String {
+        format!("{}:{}", Self::KEY, self.channel)
+    }
+
+    fn title(&self) -> String {
+        format!("Waveform {}", self.name)
+    }
+
+    fn default_height(&self) -> f32 {
+        120.0
+    }
+
+    fn paint(&self, ui: &mut egui::Ui, rect: Rect, _view: &View, _cursor: f32) {
+        let color = self.color.unwrap_or(if ui.visuals().dark_mode {
+            Color32::from_additive_luminance(196)
+        } else {
+            Color32::from_black_alpha(240)
+        });
+        let to_screen =
+            egui::emath::RectTransform::from_to(Rect::from_x_y_ranges(0.0..=1.0, 1.0..=-1.0), rect);
+        let n = self.samples.len();
+        let points: Vec<Pos2> = self
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let t = i as f64 / (n as f64);
+                let y = self.amplitude.to_plot(*v);
+                to_screen * pos2(t as f32, y)
+            })
+            .collect();
+        ui.painter_at(rect).add(egui::epaint::Shape::line(
+            points,
+            PathStroke::new(self.width, color),
+        ));
+        self.amplitude.draw(ui, rect);
+    }
+}
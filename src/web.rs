@@ -0,0 +1,679 @@
+//! Browser entry point: the eframe web runner and the Web Audio capture loop.
+
+use eframe_template::{
+    bridge::{Label, RangeQuery},
+    capture::{
+        enumerate_inputs, session_metadata, stop_stream, stream_channel_count, CaptureSettings,
+        InputDevice, SessionMetadata,
+    },
+    data_source::{line_buffer, samples_buffer, sine_buffer, sweep_buffer},
+    dsp::sweep::SweepSettings,
+    error::CaptureError,
+    logger,
+    settings::{InputSource, Settings},
+    TemplateApp,
+};
+use js_sys::{Float32Array, Function, Promise};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+
+use eframe::wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{AnalyserNode, AudioContext, AudioNode, MediaStream};
+
+/// How often events are passed on to JavaScript, per second.
+const EVENT_RATE: i32 = 30;
+const QUERY_POLL_MS: i32 = 20;
+const QUERY_TIMEOUT_MS: i32 = 10_000;
+/// How often failed capture checks for a retry, per second.
+const RETRY_POLL_RATE: i32 = 10;
+
+#[derive(Clone)]
+#[wasm_bindgen]
+pub struct WebHandle {
+    runner: eframe::WebRunner,
+    /// Callbacks registered with `on`, by event name.
+    callbacks: Rc<RefCell<Vec<(String, Function)>>>,
+}
+
+#[wasm_bindgen]
+impl WebHandle {
+    /// Installs a panic hook, then returns.
+    #[allow(clippy::new_without_default)]
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        // Redirect [`log`] message to `console.log` and friends, and the in-app log:
+        logger::init(log::LevelFilter::Debug);
+
+        Self {
+            runner: eframe::WebRunner::new(),
+            callbacks: Default::default(),
+        }
+    }
+
+    /// Call this once from JavaScript to start your app.
+    #[wasm_bindgen]
+    pub async fn start(&self, canvas_id: &str) -> Result<(), wasm_bindgen::JsValue> {
+        self.runner
+            .start(
+                canvas_id,
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(TemplateApp::new(cc)))),
+            )
+            .await
+    }
+
+    // The following are optional:
+
+    /// Shut down eframe and clean up resources.
+    #[wasm_bindgen]
+    pub fn destroy(&self) {
+        self.runner.destroy();
+    }
+
+    #[wasm_bindgen]
+    pub fn update(&mut self, data: &[u8]) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.update(data);
+        }
+    }
+    /// Pushes one block of `channels` interleaved channels.
+    #[wasm_bindgen]
+    pub fn update_interleaved(&mut self, data: &[u8], channels: usize) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.update_interleaved(data, channels);
+        }
+    }
+    /// Shows one block of `channels` interleaved channels on the meters
+    /// without recording it.
+    #[wasm_bindgen]
+    pub fn monitor_interleaved(&mut self, data: &[u8], channels: usize) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.monitor_interleaved(data, channels);
+        }
+    }
+    #[wasm_bindgen]
+    pub fn clear(&mut self) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.clear();
+        }
+    }
+    #[wasm_bindgen]
+    pub fn is_paused(&mut self) -> bool {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.is_paused()
+        } else {
+            true
+        }
+    }
+
+    /// Pushes a block of interleaved -1..1 samples, e.g. from an
+    /// `AudioWorklet`. `timestamp_ms` is the `Date.now()` of its first sample.
+    #[wasm_bindgen]
+    pub fn push_samples(
+        &mut self,
+        samples: &[f32],
+        sample_rate: f32,
+        timestamp_ms: f64,
+        channels: Option<usize>,
+    ) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.push_samples(samples, channels.unwrap_or(1), sample_rate, timestamp_ms);
+        }
+    }
+
+    /// Shows `start_s..end_s` seconds of the session.
+    #[wasm_bindgen]
+    pub fn set_view(&mut self, start_s: f32, end_s: f32) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_view(start_s, end_s);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_cursor(&mut self, time_s: f32) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_cursor(time_s);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_selection(&mut self, start_s: f32, end_s: f32) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_selection(Some((start_s.min(end_s), start_s.max(end_s))));
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_selection(&mut self) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_selection(None);
+        }
+    }
+
+    /// Adds an annotation like the ones in label files:
+    /// `{ start_s, end_s?, text?, color?: "#rrggbb", tags? }`.
+    #[wasm_bindgen]
+    pub fn add_marker(&mut self, label: JsValue) -> Result<(), JsValue> {
+        let label: Label = serde_wasm_bindgen::from_value(label)?;
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.add_label(&label);
+        }
+        Ok(())
+    }
+
+    /// Resolves to a `Float32Array` with the samples of `channel`, or of all
+    /// channels mixed down, in `start_s..end_s`.
+    #[wasm_bindgen]
+    pub fn query_samples(&mut self, start_s: f32, end_s: f32, channel: Option<usize>) -> Promise {
+        let query = self.query(start_s, end_s);
+        future_to_promise(async move {
+            let query = wait_for(query?).await?;
+            Ok(Float32Array::from(&query.samples(channel)[..]).into())
+        })
+    }
+
+    /// Resolves to the levels, loudness, pitch and markers of `start_s..end_s`.
+    #[wasm_bindgen]
+    pub fn query_results(&mut self, start_s: f32, end_s: f32) -> Promise {
+        let query = self.query(start_s, end_s);
+        future_to_promise(async move {
+            let query = wait_for(query?).await?;
+            Ok(serde_wasm_bindgen::to_value(&query.results())?)
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn start_capture(&mut self) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_paused(false);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn stop_capture(&mut self) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_paused(true);
+        }
+    }
+
+    /// Fires `trigger` events when the signal rises through `level`; off if undefined.
+    #[wasm_bindgen]
+    pub fn set_trigger(&mut self, level: Option<f32>) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_trigger(level);
+        }
+    }
+
+    /// Fires `alarm` events when the peak level crosses `threshold_db`; off if undefined.
+    #[wasm_bindgen]
+    pub fn set_alarm(&mut self, threshold_db: Option<f32>) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_alarm(threshold_db);
+        }
+    }
+
+    /// Calls `callback` with every `selection`, `trigger` or `alarm` event.
+    #[wasm_bindgen]
+    pub fn on(&self, event: &str, callback: Function) {
+        self.callbacks
+            .borrow_mut()
+            .push((event.to_owned(), callback));
+    }
+
+    /// Removes all callbacks of `event`.
+    #[wasm_bindgen]
+    pub fn off(&self, event: &str) {
+        self.callbacks
+            .borrow_mut()
+            .retain(|(name, _)| name != event);
+    }
+
+    /// The JavaScript can check whether or not your app has crashed:
+    #[wasm_bindgen]
+    pub fn has_panicked(&self) -> bool {
+        self.runner.has_panicked()
+    }
+
+    #[wasm_bindgen]
+    pub fn panic_message(&self) -> Option<String> {
+        self.runner.panic_summary().map(|s| s.message())
+    }
+
+    #[wasm_bindgen]
+    pub fn panic_callstack(&self) -> Option<String> {
+        self.runner.panic_summary().map(|s| s.callstack())
+    }
+}
+
+// Helpers for the capture loop that aren't exposed to JavaScript.
+impl WebHandle {
+    fn query(&mut self, start_s: f32, end_s: f32) -> Result<RangeQuery, JsValue> {
+        self.runner
+            .app_mut::<TemplateApp>()
+            .map(|app| app.query(start_s.min(end_s), start_s.max(end_s)))
+            .ok_or_else(|| JsValue::from_str("the analyzer isn't running"))
+    }
+
+    /// Passes the app's events on to the registered callbacks.
+    fn dispatch_events(&mut self) {
+        let events = self
+            .runner
+            .app_mut::<TemplateApp>()
+            .map(|mut app| app.take_events())
+            .unwrap_or_default();
+        // a callback may register more callbacks
+        let callbacks = self.callbacks.borrow().clone();
+        for event in events {
+            let Ok(value) = serde_wasm_bindgen::to_value(&event) else {
+                continue;
+            };
+            for (_, callback) in callbacks.iter().filter(|(name, _)| name == event.name()) {
+                if let Err(e) = callback.call1(&JsValue::NULL, &value) {
+                    web_sys::console::error_2(&JsValue::from_str("event callback failed"), &e);
+                }
+            }
+        }
+    }
+
+    fn capture_settings(&mut self) -> CaptureSettings {
+        self.runner
+            .app_mut::<TemplateApp>()
+            .map(|app| app.capture_settings().clone())
+            .unwrap_or_default()
+    }
+
+    fn settings(&mut self) -> Settings {
+        self.runner
+            .app_mut::<TemplateApp>()
+            .map(|app| app.settings().clone())
+            .unwrap_or_default()
+    }
+
+    fn take_capture_request(&mut self) -> Option<CaptureSettings> {
+        self.runner
+            .app_mut::<TemplateApp>()
+            .and_then(|mut app| app.take_capture_request())
+    }
+
+    fn take_sweep_request(&mut self) -> Option<SweepSettings> {
+        self.runner
+            .app_mut::<TemplateApp>()
+            .and_then(|mut app| app.take_sweep_request())
+    }
+
+    fn take_playback_request(&mut self) -> Option<(Vec<f32>, f32)> {
+        self.runner
+            .app_mut::<TemplateApp>()
+            .and_then(|mut app| app.take_playback_request())
+    }
+
+    fn set_input_devices(&mut self, devices: Vec<InputDevice>) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_input_devices(devices);
+        }
+    }
+
+    fn set_session_metadata(&mut self, metadata: SessionMetadata) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_session_metadata(metadata);
+        }
+    }
+
+    fn set_capture_error(&mut self, error: Option<CaptureError>) {
+        if let Some(ref mut app) = self.runner.app_mut::<TemplateApp>() {
+            app.set_capture_error(error);
+        }
+    }
+}
+
+pub fn main() {
+    // Redirect `log` message to `console.log` and friends, and the in-app log:
+    logger::init(log::LevelFilter::Debug);
+
+    let handle = WebHandle::new();
+
+    wasm_bindgen_futures::spawn_local(async move {
+        let start_result = handle.start("the_canvas_id").await;
+
+        let mut handle = handle;
+        handle.clear();
+        // lets the embedding page drive the analyzer as `window.analyzer`
+        let _ = js_sys::Reflect::set(
+            &window(),
+            &JsValue::from_str("analyzer"),
+            &JsValue::from(handle.clone()),
+        );
+        let mut events = handle.clone();
+        run_limited(
+            move || {
+                events.dispatch_events();
+                true
+            },
+            EVENT_RATE,
+        );
+        let settings = handle.capture_settings();
+        setup_audio_device(handle, settings).await;
+
+        // Remove the loading text and spinner:
+        let loading_text = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("loading_text"));
+        if let Some(loading_text) = loading_text {
+            match start_result {
+                Ok(_) => {
+                    loading_text.remove();
+                }
+                Err(e) => {
+                    loading_text.set_inner_html(
+                        "<p> The app has crashed. See the developer console for details. </p>",
+                    );
+                    panic!("Failed to start eframe: {e:?}");
+                }
+            }
+        }
+    });
+}
+
+/// A running capture graph.
+struct Capture {
+    audio_ctx: AudioContext,
+    /// `None` for the test signal, which needs no microphone.
+    media_stream: Option<MediaStream>,
+    analyzers: Vec<AnalyserNode>,
+}
+
+impl Capture {
+    fn stop(&self) {
+        if let Some(media_stream) = &self.media_stream {
+            stop_stream(media_stream);
+        }
+        let _ = self.audio_ctx.close();
+    }
+}
+
+/// Starts capture, or shows why it can't and waits for the user to retry.
+async fn setup_audio_device(mut handle: WebHandle, settings: CaptureSettings) {
+    let analyzer_settings = handle.settings();
+    match open_capture(&analyzer_settings, &settings).await {
+        Ok(capture) => {
+            handle.set_capture_error(None);
+            run_capture(handle, capture, analyzer_settings);
+        }
+        Err(error) => {
+            log::warn!("capture failed: {}", error);
+            handle.set_capture_error(Some(error));
+            run_limited(
+                move || match handle.take_capture_request() {
+                    Some(settings) => {
+                        wasm_bindgen_futures::spawn_local(setup_audio_device(
+                            handle.clone(),
+                            settings,
+                        ));
+                        false
+                    }
+                    None => true,
+                },
+                RETRY_POLL_RATE,
+            );
+        }
+    }
+}
+
+async fn open_capture(
+    analyzer_settings: &Settings,
+    settings: &CaptureSettings,
+) -> Result<Capture, CaptureError> {
+    let media_stream = if analyzer_settings.source == InputSource::TestSine {
+        None
+    } else {
+        let devices = window()
+            .navigator()
+            .media_devices()
+            .map_err(|_| CaptureError::Unsupported)?;
+        // `mediaDevices` is undefined outside secure contexts
+        if devices.is_undefined() {
+            return Err(CaptureError::Unsupported);
+        }
+        let media = devices.get_user_media_with_constraints(&settings.constraints())?;
+        Some(MediaStream::from(JsFuture::from(media).await?))
+    };
+    let audio_ctx = match AudioContext::new_with_context_options(&settings.context_options()) {
+        Ok(audio_ctx) => audio_ctx,
+        Err(e) => {
+            if let Some(media_stream) = &media_stream {
+                stop_stream(media_stream);
+            }
+            return Err(e.into());
+        }
+    };
+    let mut capture = Capture {
+        audio_ctx,
+        media_stream,
+        analyzers: vec![],
+    };
+    match connect_analyzers(&capture, analyzer_settings) {
+        Ok(analyzers) => {
+            capture.analyzers = analyzers;
+            Ok(capture)
+        }
+        Err(e) => {
+            capture.stop();
+            Err(e.into())
+        }
+    }
+}
+
+/// Connects the microphone, or the test signal, to one analyser per channel.
+fn connect_analyzers(
+    capture: &Capture,
+    analyzer_settings: &Settings,
+) -> Result<Vec<AnalyserNode>, JsValue> {
+    let audio_ctx = &capture.audio_ctx;
+    let source: AudioNode = match &capture.media_stream {
+        None => {
+            let source = audio_ctx.create_buffer_source()?;
+            let buffer = sine_buffer(audio_ctx)?;
+            source.set_buffer(Some(&buffer));
+            source.set_loop(true);
+            source.start()?;
+            source.into()
+        }
+        Some(media_stream) => audio_ctx.create_media_stream_source(media_stream)?.into(),
+    };
+    let channels = capture
+        .media_stream
+        .as_ref()
+        .and_then(stream_channel_count)
+        .unwrap_or(source.channel_count());
+    channel_analyzers(
+        audio_ctx,
+        &source,
+        channels.max(1),
+        analyzer_settings.fft_size(),
+    )
+}
+
+/// Polls the analysers until the capture settings change.
+fn run_capture(mut handle: WebHandle, capture: Capture, analyzer_settings: Settings) {
+    let Capture {
+        audio_ctx,
+        media_stream,
+        analyzers,
+    } = &capture;
+    let buffer_size = analyzers[0].frequency_bin_count() as usize;
+    log::debug!(
+        "sample_rate: {}, time: {}, buffer_size: {}, channels: {}",
+        audio_ctx.sample_rate(),
+        audio_ctx.current_time(),
+        buffer_size,
+        analyzers.len()
+    );
+    let metadata = match media_stream {
+        Some(media_stream) => session_metadata(
+            media_stream,
+            audio_ctx.sample_rate(),
+            analyzers.len(),
+            js_sys::Date::now(),
+        ),
+        None => SessionMetadata {
+            device_label: InputSource::TestSine.label().to_owned(),
+            sample_rate: audio_ctx.sample_rate(),
+            channels: analyzers.len(),
+            started_at: js_sys::Date::now(),
+            ..Default::default()
+        },
+    };
+    handle.set_session_metadata(metadata);
+    refresh_input_devices(handle.clone());
+
+    let mut buffers = vec![vec![0; buffer_size]; analyzers.len()];
+    let mut frame = vec![0; buffer_size * analyzers.len()];
+    let mut pause_state = true;
+
+    run_limited(
+        move || {
+            let audio_ctx = &capture.audio_ctx;
+            if let Some(settings) = handle.take_capture_request() {
+                capture.stop();
+                wasm_bindgen_futures::spawn_local(setup_audio_device(handle.clone(), settings));
+                return false;
+            }
+            if let Some(sweep) = handle.take_sweep_request() {
+                if let Err(e) = play_sweep(audio_ctx, &sweep) {
+                    log::warn!("cannot play the sweep: {:?}", e);
+                }
+            }
+            if let Some((samples, sample_rate)) = handle.take_playback_request() {
+                if let Err(e) = play_samples(audio_ctx, &samples, sample_rate) {
+                    log::warn!("cannot play the recording: {:?}", e);
+                }
+            }
+            // the context keeps running while paused so the meters still
+            // move; it may start suspended until the first user gesture
+            let paused = handle.is_paused();
+            if pause_state && !paused {
+                let _ = audio_ctx.resume();
+            }
+            pause_state = paused;
+            for (analyzer, buffer) in capture.analyzers.iter().zip(buffers.iter_mut()) {
+                analyzer.get_byte_time_domain_data(buffer);
+            }
+            let channels = buffers.len();
+            for (channel, buffer) in buffers.iter().enumerate() {
+                for (i, v) in buffer.iter().enumerate() {
+                    frame[i * channels + channel] = *v;
+                }
+            }
+            if paused {
+                handle.monitor_interleaved(&frame, channels);
+            } else {
+                handle.update_interleaved(&frame, channels);
+            }
+            true
+        },
+        analyzer_settings.refresh_rate as i32,
+    );
+}
+
+/// Plays a measurement sweep once through the default output.
+fn play_sweep(audio_ctx: &AudioContext, settings: &SweepSettings) -> Result<(), JsValue> {
+    let source = audio_ctx.create_buffer_source()?;
+    let buffer = sweep_buffer(audio_ctx, settings)?;
+    source.set_buffer(Some(&buffer));
+    source.connect_with_audio_node(&audio_ctx.destination())?;
+    source.start()
+}
+
+/// Plays recorded audio once through the default output. The context may
+/// not have been resumed yet, so it gets resumed for the playback.
+fn play_samples(
+    audio_ctx: &AudioContext,
+    samples: &[f32],
+    sample_rate: f32,
+) -> Result<(), JsValue> {
+    let source = audio_ctx.create_buffer_source()?;
+    let buffer = samples_buffer(audio_ctx, samples, sample_rate)?;
+    source.set_buffer(Some(&buffer));
+    source.connect_with_audio_node(&audio_ctx.destination())?;
+    let _ = audio_ctx.resume();
+    source.start()
+}
+
+/// Polls `query` until its chunks arrived, giving up after `QUERY_TIMEOUT_MS`.
+async fn wait_for(query: RangeQuery) -> Result<RangeQuery, JsValue> {
+    let mut waited = 0;
+    while !query.is_ready() {
+        if waited >= QUERY_TIMEOUT_MS {
+            return Err(JsValue::from_str("timed out loading the range"));
+        }
+        sleep(QUERY_POLL_MS).await;
+        waited += QUERY_POLL_MS;
+    }
+    Ok(query)
+}
+
+async fn sleep(ms: i32) {
+    let promise = Promise::new(&mut |resolve, _| {
+        let _ = window().set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+fn refresh_input_devices(mut handle: WebHandle) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let devices = enumerate_inputs().await;
+        handle.set_input_devices(devices);
+    });
+}
+
+/// Splits `source` into `channels` outputs, each feeding its own analyser.
+fn channel_analyzers(
+    audio_ctx: &AudioContext,
+    source: &AudioNode,
+    channels: u32,
+    fft_size: u32,
+) -> Result<Vec<AnalyserNode>, JsValue> {
+    let splitter = audio_ctx.create_channel_splitter_with_number_of_outputs(channels)?;
+    source.connect_with_audio_node(&splitter)?;
+    (0..channels)
+        .map(|channel| {
+            let analyzer = audio_ctx.create_analyser()?;
+            analyzer.set_fft_size(fft_size);
+            analyzer.set_smoothing_time_constant(0.0);
+            splitter.connect_with_audio_node_and_output(&analyzer, channel)?;
+            Ok(analyzer)
+        })
+        .collect()
+}
+
+/// Calls `tick` about `rate` times a second until it returns false. Runs on
+/// timers rather than animation frames: capture and event polling keep their
+/// own pace, the UI repaints only when the app asks for it.
+fn run_limited(mut tick: impl FnMut() -> bool + 'static, rate: i32) {
+    let timeout_cb = Rc::new(RefCell::new(None));
+    let timeout_cb2 = timeout_cb.clone();
+
+    let w = window();
+    *timeout_cb2.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+        if !tick() {
+            return;
+        }
+        set_timeout(&w, timeout_cb.borrow().as_ref().unwrap(), 1000 / rate);
+    }) as Box<dyn FnMut()>));
+
+    set_timeout(&window(), timeout_cb2.borrow().as_ref().unwrap(), 0);
+}
+
+fn window() -> web_sys::Window {
+    web_sys::window().expect("no global `window` exists")
+}
+
+fn set_timeout(window: &web_sys::Window, f: &Closure<dyn FnMut()>, timeout_ms: i32) -> i32 {
+    window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            f.as_ref().unchecked_ref(),
+            timeout_ms,
+        )
+        .expect("should register `setTimeout` OK")
+}
@@ -0,0 +1,241 @@
+use egui::{epaint::PathStroke, pos2, vec2, Align2, Color32, FontId, Sense, Stroke};
+
+use crate::{
+    buffer::RangeLoad,
+    channels::ChannelSelection,
+    dsp::{
+        acoustics::{analyze, DecayFit, RoomReport},
+        wav, Stream,
+    },
+    export::Export,
+    import::{FileRequest, Import},
+    Log,
+};
+
+/// Room-acoustic parameters of an impulse response taken from the sweep
+/// measurement, the captured view or a WAV file.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct AcousticsPanel {
+    pub channels: ChannelSelection,
+    /// Row of the table whose decay is plotted.
+    pub selected: usize,
+    #[serde(skip)]
+    source: String,
+    #[serde(skip)]
+    pending: Option<RangeLoad>,
+    #[serde(skip)]
+    import: Option<FileRequest>,
+    #[serde(skip)]
+    result: Option<Result<RoomReport, String>>,
+}
+
+impl Log for AcousticsPanel {
+    fn name() -> &'static str {
+        "Acoustics"
+    }
+}
+
+impl AcousticsPanel {
+    const PLOT_HEIGHT: f32 = 160.0;
+    const DECAY_RANGE: f32 = 70.0;
+
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+
+    fn set_impulse(&mut self, source: String, ir: &[f32], sample_rate: f32) {
+        Self::log(&format!("analyzing {} ({} samples)", source, ir.len()));
+        self.source = source;
+        self.result = Some(analyze(ir, sample_rate));
+    }
+
+    /// Picks up loaded ranges and imported files.
+    pub fn poll(&mut self, sample_rate: f32) {
+        if let Some(load) = RangeLoad::take_ready(&mut self.pending) {
+            let stream =
+                Stream::from_chunks(&load.chunks(), sample_rate, |n| self.channels.active(n));
+            let source = format!("chunks {}..={}", load.start, load.end);
+            self.set_impulse(source, &stream.samples, sample_rate);
+        }
+        if let Some((name, bytes)) = self.import.as_ref().and_then(|i| i.take()) {
+            self.import = None;
+            match wav::parse(&bytes) {
+                // the first channel is the response
+                Ok(wav) => self.set_impulse(name, &wav.channels[0], wav.sample_rate),
+                Err(e) => self.result = Some(Err(format!("{}: {}", name, e))),
+            }
+        }
+    }
+
+    /// `sweep_ir` is the latest impulse response of the sweep measurement.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        channels: usize,
+        sweep_ir: Option<(&[f32], f32)>,
+        load: impl Fn() -> RangeLoad,
+    ) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(sweep_ir.is_some(), egui::Button::new("From sweep"))
+                .on_disabled_hover_text("Run a sweep measurement first")
+                .clicked()
+            {
+                let (ir, sample_rate) = sweep_ir.unwrap();
+                self.set_impulse("sweep".to_owned(), ir, sample_rate);
+            }
+            self.channels.picker(ui, "acoustics_channels", channels);
+            if ui.button("From view").clicked() {
+                self.pending = Some(load());
+            }
+            if ui.button("Import WAV").clicked() {
+                self.import = Some(Import::pick(".wav,audio/wav", ui.ctx()));
+            }
+            if let Some(pending) = &self.pending {
+                ui.add(egui::ProgressBar::new(pending.progress()).desired_width(100.0));
+            }
+        });
+        let report = match &self.result {
+            None => {
+                ui.weak("no impulse response");
+                return;
+            }
+            Some(Err(error)) => {
+                ui.colored_label(ui.visuals().warn_fg_color, error);
+                return;
+            }
+            Some(Ok(report)) => report,
+        };
+        ui.weak(format!("source: {}", self.source));
+        let seconds = |fit: &Option<DecayFit>| {
+            fit.map_or("--".to_owned(), |f| format!("{:.2}", f.decay_time))
+        };
+        let correlation = |fit: &Option<DecayFit>| {
+            fit.map_or("--".to_owned(), |f| format!("{:.3}", f.correlation))
+        };
+        egui::Grid::new("acoustics_table")
+            .striped(true)
+            .show(ui, |ui| {
+                for header in [
+                    "band", "EDT s", "T20 s", "r", "T30 s", "r", "ξ ‰", "C50", "C80", "D50 %",
+                    "DR dB",
+                ] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for (i, band) in report.bands.iter().enumerate() {
+                    ui.selectable_value(&mut self.selected, i, &band.band);
+                    ui.label(seconds(&band.edt));
+                    ui.label(seconds(&band.t20));
+                    ui.label(correlation(&band.t20));
+                    ui.label(seconds(&band.t30));
+                    ui.label(correlation(&band.t30));
+                    let curvature = band
+                        .curvature_permille
+                        .map_or("--".to_owned(), |c| format!("{:.0}", c));
+                    // ISO 3382 flags curvature above 100 permille as a non-exponential decay
+                    if band.curvature_permille.map_or(false, |c| c.abs() > 100.0) {
+                        ui.colored_label(ui.visuals().warn_fg_color, curvature);
+                    } else {
+                        ui.label(curvature);
+                    }
+                    ui.label(format!("{:.1}", band.c50));
+                    ui.label(format!("{:.1}", band.c80));
+                    ui.label(format!("{:.0}", band.d50));
+                    // T30 needs 45 dB of range: 35 dB of fit plus 10 dB above the noise
+                    if band.dynamic_range_db < 45.0 {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("{:.0}", band.dynamic_range_db),
+                        );
+                    } else {
+                        ui.label(format!("{:.0}", band.dynamic_range_db));
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(band) = report.bands.get(self.selected) {
+            Self::draw_decay(
+                ui,
+                report.sample_rate,
+                &band.decay,
+                &[band.edt, band.t20, band.t30],
+            );
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Export CSV").clicked() {
+                Export::csv("room_acoustics.csv", &report.to_csv());
+            }
+            if ui.button("Export JSON").clicked() {
+                Export::json("room_acoustics.json", report);
+            }
+        });
+    }
+
+    /// Schroeder curve with the EDT, T20 and T30 regression lines.
+    fn draw_decay(ui: &mut egui::Ui, sample_rate: f32, decay: &[f32], fits: &[Option<DecayFit>]) {
+        let (rect, _) = ui.allocate_exact_size(
+            vec2(ui.available_width(), Self::PLOT_HEIGHT),
+            Sense::hover(),
+        );
+        let vis = ui.style().noninteractive();
+        ui.painter().rect(
+            rect,
+            0.0,
+            vis.weak_bg_fill,
+            Stroke::new(1.0, vis.bg_stroke.color),
+        );
+        let painter = ui.painter_at(rect);
+        let text_color = vis.text_color();
+        let font_id = FontId::new(9.0, egui::FontFamily::Monospace);
+        let duration = (decay.len() as f32 / sample_rate).max(f32::EPSILON);
+        let x = |t: f32| rect.min.x + t / duration * rect.width();
+        let y = |db: f32| rect.min.y - (db / Self::DECAY_RANGE).max(-1.0) * rect.height();
+        for db in (0..=60).step_by(10) {
+            let level = -(db as f32);
+            painter.line_segment(
+                [pos2(rect.min.x, y(level)), pos2(rect.max.x, y(level))],
+                Stroke::new(0.5, text_color.gamma_multiply(0.2)),
+            );
+            painter.text(
+                pos2(rect.min.x + 2.0, y(level)),
+                Align2::LEFT_BOTTOM,
+                format!("{}", level),
+                font_id.clone(),
+                text_color,
+            );
+        }
+        let step = (decay.len() as f32 / rect.width()).max(1.0);
+        let points: Vec<_> = (0..rect.width() as usize)
+            .filter_map(|px| {
+                let i = (px as f32 * step) as usize;
+                decay
+                    .get(i)
+                    .map(|db| pos2(x(i as f32 / sample_rate), y(*db)))
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            PathStroke::new(1.5, ui.visuals().selection.stroke.color),
+        ));
+        let colors = [
+            Color32::from_rgb(230, 190, 40),
+            Color32::from_rgb(220, 90, 50),
+            Color32::from_rgb(60, 180, 75),
+        ];
+        for (fit, color) in fits.iter().zip(colors) {
+            if let Some(fit) = fit {
+                let at = |t: f32| pos2(x(t), y(fit.intercept_db + fit.slope_db_per_s * t));
+                painter.line_segment([at(0.0), at(duration)], Stroke::new(1.0, color));
+            }
+        }
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            format!("decay (dB) over {:.2} s · EDT / T20 / T30 fits", duration),
+            font_id,
+            text_color,
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use egui::{Color32, Key};
+
+use crate::{
+    annotations::{split_tags, Annotation, Label, LabelFormat},
+    export::Export,
+    import::{FileRequest, Import},
+    summary::Timebase,
+    timeline::{TimelineMarker, TimelineRegion},
+    Log,
+};
+
+/// Point markers and regions placed by the user.
+///
+/// `M` drops a marker at the cursor, `R` starts and ends a region, `,` and
+/// `.` jump to the previous and next annotation.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AnnotationPanel {
+    items: Vec<Annotation>,
+    next_id: u64,
+    /// Color and tags given to new annotations.
+    pub color: [u8; 3],
+    pub tags: String,
+    pub format: LabelFormat,
+    #[serde(skip)]
+    selected: Option<u64>,
+    /// Start of the region being placed.
+    #[serde(skip)]
+    region_start: Option<f32>,
+    #[serde(skip)]
+    import: Option<(LabelFormat, FileRequest)>,
+    #[serde(skip)]
+    error: Option<String>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl Default for AnnotationPanel {
+    fn default() -> Self {
+        Self {
+            items: vec![],
+            next_id: 1,
+            color: [240, 200, 60],
+            tags: String::new(),
+            format: LabelFormat::Csv,
+            selected: None,
+            region_start: None,
+            import: None,
+            error: None,
+            changed: false,
+        }
+    }
+}
+
+impl Log for AnnotationPanel {
+    fn name() -> &'static str {
+        "Annotations"
+    }
+}
+
+impl AnnotationPanel {
+    /// Positions closer than this count as the same place when navigating.
+    const EPSILON: f32 = 0.01;
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.selected = None;
+        self.region_start = None;
+        self.changed = true;
+    }
+
+    fn add(&mut self, start: f32, end: Option<f32>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let text = match end {
+            Some(_) => format!("Region {}", id),
+            None => format!("Marker {}", id),
+        };
+        self.items.push(Annotation {
+            id,
+            start,
+            end,
+            text,
+            color: self.color,
+            tags: split_tags(&self.tags, ','),
+        });
+        self.items.sort_by(|a, b| a.start.total_cmp(&b.start));
+        self.selected = Some(id);
+        self.changed = true;
+        id
+    }
+
+    /// Adds one tagged region per segment, e.g. from silence detection.
+    pub fn add_segments(&mut self, segments: &[(f32, f32)]) {
+        for (i, (start, end)) in segments.iter().enumerate() {
+            let id = self.add(*start, Some(*end));
+            if let Some(segment) = self.items.iter_mut().find(|a| a.id == id) {
+                segment.text = format!("Segment {}", i + 1);
+                segment.tags.push("segment".to_owned());
+            }
+        }
+    }
+
+    pub fn mark(&mut self, position: f32) {
+        self.add(position, None);
+    }
+
+    pub fn add_region(&mut self, start: f32, end: f32) {
+        self.add(start, Some(end));
+    }
+
+    /// Starts a region at `position`, or ends the one being placed there.
+    pub fn toggle_region(&mut self, position: f32) {
+        match self.region_start.take() {
+            Some(start) if (start - position).abs() > Self::EPSILON => {
+                self.add(start.min(position), Some(start.max(position)));
+            }
+            Some(_) => {}
+            None => self.region_start = Some(position),
+        }
+    }
+
+    pub fn next(&self, position: f32) -> Option<f32> {
+        self.items
+            .iter()
+            .map(|a| a.start)
+            .find(|start| *start > position + Self::EPSILON)
+    }
+
+    pub fn previous(&self, position: f32) -> Option<f32> {
+        self.items
+            .iter()
+            .rev()
+            .map(|a| a.start)
+            .find(|start| *start < position - Self::EPSILON)
+    }
+
+    /// Keyboard shortcuts; returns the position to move the view to.
+    pub fn handle_keys(&mut self, ui: &egui::Ui, cursor: f32) -> Option<f32> {
+        if ui.ctx().wants_keyboard_input() {
+            return None;
+        }
+        let (mark, region, previous, next) = ui.input(|i| {
+            (
+                i.key_pressed(Key::M),
+                i.key_pressed(Key::R),
+                i.key_pressed(Key::Comma),
+                i.key_pressed(Key::Period),
+            )
+        });
+        if mark {
+            self.mark(cursor);
+        }
+        if region {
+            self.toggle_region(cursor);
+        }
+        if previous {
+            return self.previous(cursor);
+        }
+        if next {
+            return self.next(cursor);
+        }
+        None
+    }
+
+    /// All annotations if they changed since the last call, to be persisted.
+    pub fn take_changed(&mut self) -> Option<&[Annotation]> {
+        std::mem::take(&mut self.changed).then_some(&self.items)
+    }
+
+    /// Adds annotations timed in seconds; `timebase` converts them to chunks.
+    pub fn add_labels(&mut self, labels: &[Label], timebase: &Timebase<'_>) {
+        for label in labels {
+            let annotation = label.to_annotation(self.next_id, timebase, self.color);
+            self.next_id += 1;
+            self.items.push(annotation);
+        }
+        self.items.sort_by(|a, b| a.start.total_cmp(&b.start));
+        self.changed = true;
+    }
+
+    /// Picks up imported label files. `timebase` converts their seconds to chunks.
+    pub fn poll(&mut self, timebase: &Timebase<'_>) {
+        let Some((format, request)) = &self.import else {
+            return;
+        };
+        let Some((name, bytes)) = request.take() else {
+            return;
+        };
+        let format = *format;
+        self.import = None;
+        let labels = String::from_utf8(bytes)
+            .map_err(|e| e.to_string())
+            .and_then(|text| format.read(&text));
+        match labels {
+            Ok(labels) => {
+                Self::log(&format!("imported {} labels from {}", labels.len(), name));
+                self.add_labels(&labels, timebase);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{}: {}", name, e)),
+        }
+    }
+
+    /// Returns the position to move the view to, if one was picked.
+    pub fn ui(&mut self, ui: &mut egui::Ui, cursor: f32, timebase: &Timebase<'_>) -> Option<f32> {
+        let mut jump = None;
+        ui.horizontal(|ui| {
+            if ui.button("Marker").on_hover_text("M").clicked() {
+                self.mark(cursor);
+            }
+            let region = if self.region_start.is_some() {
+                "End region"
+            } else {
+                "Start region"
+            };
+            if ui.button(region).on_hover_text("R").clicked() {
+                self.toggle_region(cursor);
+            }
+            if ui.button("◀").on_hover_text(",").clicked() {
+                jump = self.previous(cursor);
+            }
+            if ui.button("▶").on_hover_text(".").clicked() {
+                jump = self.next(cursor);
+            }
+            ui.color_edit_button_srgb(&mut self.color);
+            ui.label("Tags");
+            ui.add(egui::TextEdit::singleline(&mut self.tags).desired_width(120.0));
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("annotation_format")
+                .selected_text(self.format.label())
+                .show_ui(ui, |ui| {
+                    for format in LabelFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.label());
+                    }
+                });
+            if ui.button("Export").clicked() {
+                let labels: Vec<_> = self.items.iter().map(|a| a.to_label(timebase)).collect();
+                Export::download(
+                    self.format.filename(),
+                    self.format.mime(),
+                    &self.format.write(&labels),
+                );
+            }
+            if ui.button("Import").clicked() {
+                self.import = Some((self.format, Import::pick(self.format.accept(), ui.ctx())));
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, error);
+        }
+        let mut remove = None;
+        egui::Grid::new("annotation_list")
+            .striped(true)
+            .show(ui, |ui| {
+                for annotation in &mut self.items {
+                    let time = annotation.to_label(timebase);
+                    let when = match time.end_s {
+                        Some(end) => format!("{:.2}–{:.2} s", time.start_s, end),
+                        None => format!("{:.2} s", time.start_s),
+                    };
+                    if ui
+                        .selectable_label(self.selected == Some(annotation.id), when)
+                        .clicked()
+                    {
+                        self.selected = Some(annotation.id);
+                        jump = Some(annotation.start);
+                    }
+                    let mut changed = ui.color_edit_button_srgb(&mut annotation.color).changed();
+                    changed |= ui.text_edit_singleline(&mut annotation.text).changed();
+                    let mut tags = annotation.tags.join(", ");
+                    if ui
+                        .add(egui::TextEdit::singleline(&mut tags).hint_text("tags"))
+                        .changed()
+                    {
+                        annotation.tags = split_tags(&tags, ',');
+                        changed = true;
+                    }
+                    if ui.small_button("🗑").clicked() {
+                        remove = Some(annotation.id);
+                    }
+                    self.changed |= changed;
+                    ui.end_row();
+                }
+            });
+        if let Some(id) = remove {
+            self.items.retain(|a| a.id != id);
+            self.changed = true;
+        }
+        jump
+    }
+
+    fn color(annotation: &Annotation) -> Color32 {
+        let [r, g, b] = annotation.color;
+        Color32::from_rgb(r, g, b)
+    }
+
+    fn label(annotation: &Annotation) -> Option<String> {
+        let mut label = annotation.text.clone();
+        for tag in &annotation.tags {
+            label.push_str(&format!(" #{}", tag));
+        }
+        (!label.is_empty()).then_some(label)
+    }
+
+    pub fn markers(&self) -> Vec<TimelineMarker> {
+        let pending = self.region_start.map(|position| TimelineMarker {
+            position,
+            color: Color32::from_rgb(self.color[0], self.color[1], self.color[2]),
+            label: Some("region…".to_owned()),
+        });
+        self.items
+            .iter()
+            .filter(|a| !a.is_region())
+            .map(|a| TimelineMarker {
+                position: a.start,
+                color: Self::color(a),
+                label: Self::label(a),
+            })
+            .chain(pending)
+            .collect()
+    }
+
+    pub fn regions(&self) -> Vec<TimelineRegion> {
+        self.items
+            .iter()
+            .filter_map(|a| {
+                Some(TimelineRegion {
+                    start: a.start,
+                    end: a.end?,
+                    color: Self::color(a),
+                    label: Self::label(a),
+                })
+            })
+            .collect()
+    }
+}
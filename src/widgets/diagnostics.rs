@@ -0,0 +1,247 @@
+use std::{collections::VecDeque, time::Duration};
+
+use log::{Level, LevelFilter};
+
+use crate::{
+    buffer::BufferStats, chunk::Chunk, clock, dsp::fresh_frames, logger, settings::Settings,
+};
+
+/// Capture rate, gaps and overlaps of the ingested chunks.
+#[derive(Default)]
+struct IngestStats {
+    /// Wall clock time and fresh frames of the chunks of the last `WINDOW_MS`.
+    recent: VecDeque<(f64, usize)>,
+    last_time: Option<f32>,
+    gaps: usize,
+    gap_ms: f32,
+    overlaps: usize,
+    overlap_ms: f32,
+}
+
+impl IngestStats {
+    /// Chunks and samples per channel per second over the window.
+    fn rates(&self) -> Option<(f32, f32)> {
+        let (first, last) = (self.recent.front()?, self.recent.back()?);
+        let seconds = ((last.0 - first.0) / 1000.0) as f32;
+        if seconds <= 0.0 {
+            return None;
+        }
+        let frames: usize = self.recent.iter().skip(1).map(|(_, frames)| frames).sum();
+        Some((
+            (self.recent.len() - 1) as f32 / seconds,
+            frames as f32 / seconds,
+        ))
+    }
+}
+
+/// Capture, storage and frame health, and the in-app log.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DiagnosticsPanel {
+    /// Most verbose level shown in the log view.
+    pub level: LevelFilter,
+    /// Shows only entries whose target or message contains this.
+    pub filter: String,
+    #[serde(skip)]
+    ingest: IngestStats,
+    /// Start time and duration in ms of the latest UI frames.
+    #[serde(skip)]
+    frames: VecDeque<(f64, f32)>,
+}
+
+impl Default for DiagnosticsPanel {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            filter: String::new(),
+            ingest: IngestStats::default(),
+            frames: VecDeque::new(),
+        }
+    }
+}
+
+impl DiagnosticsPanel {
+    /// Rates are measured over this much wall clock time.
+    const WINDOW_MS: f64 = 2000.0;
+    /// Timestamp jitter below this isn't a gap or overlap.
+    const TOLERANCE_MS: f32 = 2.0;
+    const LEVELS: [LevelFilter; 5] = [
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+
+    pub fn clear(&mut self) {
+        self.ingest = IngestStats::default();
+    }
+
+    /// Records a chunk. A gap is audio missing between two chunks. Captured
+    /// chunks overlap by design, so for them an overlap is a chunk that
+    /// arrived before the next poll was due, `poll_period_ms` after the last;
+    /// pushed blocks (`None`) overlap when they repeat audio.
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32, poll_period_ms: Option<f32>) {
+        let now = clock::now_ms();
+        let stats = &mut self.ingest;
+        let fresh = fresh_frames(chunk, stats.last_time, sample_rate);
+        stats.recent.push_back((now, fresh));
+        while stats
+            .recent
+            .front()
+            .map_or(false, |(time, _)| now - time > Self::WINDOW_MS)
+        {
+            stats.recent.pop_front();
+        }
+        if let Some(last) = stats.last_time {
+            let interval = chunk.time - last;
+            let duration = chunk.frames() as f32 / sample_rate * 1000.0;
+            if interval > duration + Self::TOLERANCE_MS {
+                stats.gaps += 1;
+                stats.gap_ms += interval - duration;
+            } else {
+                let expected = poll_period_ms.unwrap_or(duration);
+                if interval < expected - Self::TOLERANCE_MS {
+                    stats.overlaps += 1;
+                    stats.overlap_ms += expected - interval;
+                }
+            }
+        }
+        stats.last_time = Some(chunk.time);
+    }
+
+    /// Records a UI frame that started at `start` and ended at `end` (ms).
+    pub fn frame(&mut self, start: f64, end: f64) {
+        self.frames.push_back((start, (end - start) as f32));
+        while self
+            .frames
+            .front()
+            .map_or(false, |(time, _)| end - time > Self::WINDOW_MS)
+        {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: &Settings,
+        sample_rate: f32,
+        buffer: &BufferStats,
+    ) {
+        // rates decay and the log grows without anything else repainting
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+
+        let (chunk_rate, frame_rate) = self.ingest.rates().unwrap_or_default();
+        let stats = &self.ingest;
+        let storage = &buffer.storage;
+        egui::Grid::new("diagnostics_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Chunks/s");
+                ui.monospace(format!(
+                    "{:.1} (expected {})",
+                    chunk_rate, settings.refresh_rate
+                ));
+                ui.end_row();
+
+                ui.label("Samples/s per channel");
+                ui.monospace(format!("{:.0} (expected {:.0})", frame_rate, sample_rate))
+                    .on_hover_text(
+                        "New audio only; fewer than expected when audio between chunks is lost",
+                    );
+                ui.end_row();
+
+                ui.label("Gaps");
+                ui.monospace(format!("{} ({:.0} ms missed)", stats.gaps, stats.gap_ms));
+                ui.end_row();
+
+                ui.label("Overlaps")
+                    .on_hover_text("Chunks that arrived before the next poll was due");
+                ui.monospace(format!(
+                    "{} ({:.0} ms repeated)",
+                    stats.overlaps, stats.overlap_ms
+                ));
+                ui.end_row();
+
+                ui.label("IndexedDB writes");
+                ui.monospace(format!(
+                    "{} pending, {} failed, {} chunks unwritten",
+                    storage.pending_writes, storage.failed_writes, storage.unwritten_chunks
+                ));
+                ui.end_row();
+
+                ui.label("Pages in memory only");
+                ui.monospace(format!("{}", storage.memory_pages));
+                ui.end_row();
+
+                ui.label("Cached pages");
+                ui.monospace(format!(
+                    "{} loaded ({} chunks), {} loading",
+                    buffer.loaded_pages, buffer.cached_chunks, buffer.loading_pages
+                ));
+                ui.end_row();
+
+                ui.label("Frame time");
+                let times = self.frames.iter().map(|(_, ms)| *ms);
+                let average = times.clone().sum::<f32>() / self.frames.len().max(1) as f32;
+                let max = times.fold(0.0, f32::max);
+                let fps = self.frames.len() as f64 * 1000.0 / Self::WINDOW_MS;
+                ui.monospace(format!(
+                    "{:.1} ms avg, {:.1} ms max, {:.0} frames/s",
+                    average, max, fps
+                ));
+                ui.end_row();
+            });
+
+        ui.separator();
+        self.log_ui(ui);
+    }
+
+    fn log_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("log_level")
+                .selected_text(format!("{}", self.level))
+                .show_ui(ui, |ui| {
+                    for level in Self::LEVELS {
+                        ui.selectable_value(&mut self.level, level, format!("{}", level));
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("component or text")
+                    .desired_width(160.0),
+            );
+            if ui.button("Clear").clicked() {
+                logger::clear();
+            }
+        });
+        let entries = logger::entries(self.level, &self.filter);
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, entries.len(), |ui, rows| {
+                for entry in &entries[rows] {
+                    let color = match entry.level {
+                        Level::Error => ui.visuals().error_fg_color,
+                        Level::Warn => ui.visuals().warn_fg_color,
+                        Level::Info => ui.visuals().text_color(),
+                        Level::Debug | Level::Trace => ui.visuals().weak_text_color(),
+                    };
+                    ui.colored_label(
+                        color,
+                        egui::RichText::new(format!(
+                            "{} {:5} [{}] {}",
+                            clock::time_of_day(entry.time),
+                            entry.level,
+                            entry.target,
+                            entry.message
+                        ))
+                        .monospace(),
+                    );
+                }
+            });
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::VecDeque;
+
+use egui::{epaint::PathStroke, pos2, vec2, Align2, Color32, FontId, Rect, Sense, Stroke};
+
+use crate::{
+    buffer::RangeLoad,
+    channels::ChannelSelection,
+    data::Chunk,
+    dsp::{
+        distortion::{analyze, DistortionReport, DistortionSettings, MIN_FRAMES},
+        fresh_frames, mixdown, Stream,
+    },
+    export::Export,
+    Log,
+};
+
+/// THD / THD+N / SNR / SINAD measurement of a selection or the live stream.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct DistortionPanel {
+    pub channels: ChannelSelection,
+    pub settings: DistortionSettings,
+    pub live: bool,
+    pub show_residual: bool,
+    #[serde(skip)]
+    samples: VecDeque<f32>,
+    #[serde(skip)]
+    last_time: Option<f32>,
+    /// Fresh frames since the last live analysis.
+    #[serde(skip)]
+    unanalyzed: usize,
+    #[serde(skip)]
+    pending: Option<RangeLoad>,
+    #[serde(skip)]
+    result: Option<Result<DistortionReport, String>>,
+}
+
+impl Log for DistortionPanel {
+    fn name() -> &'static str {
+        "Distortion"
+    }
+}
+
+impl DistortionPanel {
+    const LIVE_FRAMES: usize = 16384;
+    const SPECTRUM_RANGE: (f32, f32) = (-140.0, 0.0);
+    const PLOT_HEIGHT: f32 = 140.0;
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.last_time = None;
+        self.unanalyzed = 0;
+        self.pending = None;
+    }
+
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        if !self.live {
+            return;
+        }
+        let mono = mixdown(chunk, &self.channels.active(chunk.channels));
+        self.samples.extend(&mono[mono.len() - fresh..]);
+        while self.samples.len() > Self::LIVE_FRAMES {
+            self.samples.pop_front();
+        }
+        self.unanalyzed += fresh;
+    }
+
+    /// Re-analyzes the live block about four times a second and finishes
+    /// pending range measurements.
+    pub fn poll(&mut self, sample_rate: f32) {
+        if self.live
+            && self.samples.len() >= MIN_FRAMES
+            && self.unanalyzed as f32 >= sample_rate / 4.0
+        {
+            self.unanalyzed = 0;
+            let samples: Vec<f32> = self.samples.iter().copied().collect();
+            self.result = Some(analyze(&samples, sample_rate, &self.settings));
+        }
+        if let Some(load) = RangeLoad::take_ready(&mut self.pending) {
+            Self::log(&format!("measuring chunks {}..={}", load.start, load.end));
+            let stream =
+                Stream::from_chunks(&load.chunks(), sample_rate, |n| self.channels.active(n));
+            self.live = false;
+            self.result = Some(analyze(&stream.samples, sample_rate, &self.settings));
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, channels: usize, load: impl Fn(bool) -> RangeLoad) {
+        ui.horizontal(|ui| {
+            self.channels.picker(ui, "distortion_channels", channels);
+            if ui.checkbox(&mut self.live, "Live").changed() {
+                self.samples.clear();
+            }
+            if ui.button("Measure view").clicked() {
+                self.pending = Some(load(false));
+            }
+            if ui.button("Measure session").clicked() {
+                self.pending = Some(load(true));
+            }
+            if let Some(pending) = &self.pending {
+                ui.add(egui::ProgressBar::new(pending.progress()).desired_width(100.0));
+            }
+        });
+        ui.horizontal(|ui| {
+            let settings = &mut self.settings;
+            ui.label("Harmonics up to");
+            ui.add(egui::DragValue::new(&mut settings.max_harmonic).range(2..=50));
+            ui.label("Bandwidth");
+            ui.add(
+                egui::DragValue::new(&mut settings.min_frequency)
+                    .range(0.0..=settings.max_frequency)
+                    .suffix(" Hz"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut settings.max_frequency)
+                    .range(settings.min_frequency..=96000.0)
+                    .suffix(" Hz"),
+            );
+            ui.checkbox(&mut self.show_residual, "Notch residual");
+        });
+        let report = match &self.result {
+            None => {
+                ui.weak("no measurement");
+                return;
+            }
+            Some(Err(error)) => {
+                ui.colored_label(ui.visuals().warn_fg_color, error);
+                return;
+            }
+            Some(Ok(report)) => report,
+        };
+        ui.monospace(format!(
+            "f0 {:.2} Hz  {:.2} dBFS  ({} frames)",
+            report.fundamental, report.fundamental_dbfs, report.frames
+        ));
+        ui.monospace(format!(
+            "THD {:.4} % ({:.1} dB)  THD+N {:.4} % ({:.1} dB)",
+            report.thd_percent, report.thd_db, report.thd_n_percent, report.thd_n_db
+        ));
+        ui.monospace(format!(
+            "SNR {:.1} dB  SINAD {:.1} dB  ENOB {:.2} bit",
+            report.snr_db, report.sinad_db, report.enob
+        ));
+        egui::CollapsingHeader::new("Harmonics").show(ui, |ui| {
+            egui::Grid::new("distortion_harmonics")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("H");
+                    ui.strong("Hz");
+                    ui.strong("dBFS");
+                    ui.strong("dBc");
+                    ui.end_row();
+                    for h in &report.harmonics {
+                        ui.label(format!("{}", h.order));
+                        ui.label(format!("{:.1}", h.frequency));
+                        ui.label(format!("{:.1}", h.level_dbfs));
+                        ui.label(format!("{:.1}", h.level_dbc));
+                        ui.end_row();
+                    }
+                });
+        });
+        Self::draw_spectrum(ui, report);
+        if self.show_residual {
+            Self::draw_residual(ui, report);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Export JSON").clicked() {
+                Export::json("distortion_report.json", report);
+            }
+            if ui.button("Export CSV").clicked() {
+                Export::csv("distortion_harmonics.csv", &report.harmonics_csv());
+            }
+        });
+    }
+
+    fn plot_rect(ui: &mut egui::Ui) -> (Rect, egui::Painter) {
+        let (rect, _) = ui.allocate_exact_size(
+            vec2(ui.available_width(), Self::PLOT_HEIGHT),
+            Sense::hover(),
+        );
+        let vis = ui.style().noninteractive();
+        ui.painter().rect(
+            rect,
+            0.0,
+            vis.weak_bg_fill,
+            Stroke::new(1.0, vis.bg_stroke.color),
+        );
+        (rect, ui.painter_at(rect))
+    }
+
+    /// Log-frequency spectrum with the fundamental and harmonics marked.
+    fn draw_spectrum(ui: &mut egui::Ui, report: &DistortionReport) {
+        let (rect, painter) = Self::plot_rect(ui);
+        let text_color = ui.style().noninteractive().text_color();
+        let font_id = FontId::new(9.0, egui::FontFamily::Monospace);
+        let nyquist = report.sample_rate as f64 / 2.0;
+        let (f_low, f_high) = (10f64.log10(), nyquist.log10());
+        let x = |f: f64| {
+            rect.min.x + ((f.max(10.0).log10() - f_low) / (f_high - f_low)) as f32 * rect.width()
+        };
+        let (low, high) = Self::SPECTRUM_RANGE;
+        let y = |db: f32| rect.max.y - ((db - low) / (high - low)).clamp(0.0, 1.0) * rect.height();
+        for db in (-120..=0).step_by(20) {
+            painter.line_segment(
+                [
+                    pos2(rect.min.x, y(db as f32)),
+                    pos2(rect.max.x, y(db as f32)),
+                ],
+                Stroke::new(0.5, text_color.gamma_multiply(0.2)),
+            );
+            painter.text(
+                pos2(rect.min.x + 2.0, y(db as f32)),
+                Align2::LEFT_BOTTOM,
+                format!("{}", db),
+                font_id.clone(),
+                text_color,
+            );
+        }
+        let points: Vec<_> = report
+            .spectrum
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(bin, db)| pos2(x(bin as f64 * report.bin_hz), y(*db)))
+            .collect();
+        let accent = ui.visuals().selection.stroke.color;
+        painter.add(egui::Shape::line(points, PathStroke::new(1.0, accent)));
+        let marker = |frequency: f64, db: f32, label: String| {
+            let pos = pos2(x(frequency), y(db));
+            painter.circle_stroke(pos, 3.0, Stroke::new(1.0, Color32::from_rgb(230, 190, 40)));
+            painter.text(
+                pos - vec2(0.0, 4.0),
+                Align2::CENTER_BOTTOM,
+                label,
+                font_id.clone(),
+                text_color,
+            );
+        };
+        marker(report.fundamental, report.fundamental_dbfs, "F".to_owned());
+        for h in &report.harmonics {
+            marker(h.frequency, h.level_dbfs, format!("{}", h.order));
+        }
+    }
+
+    fn draw_residual(ui: &mut egui::Ui, report: &DistortionReport) {
+        let (rect, painter) = Self::plot_rect(ui);
+        let text_color = ui.style().noninteractive().text_color();
+        let peak = report
+            .residual
+            .iter()
+            .fold(f32::EPSILON, |acc, v| acc.max(v.abs()));
+        let step = (report.residual.len() as f32 / rect.width()).max(1.0);
+        let points: Vec<_> = (0..rect.width() as usize)
+            .filter_map(|px| {
+                let v = report.residual.get((px as f32 * step) as usize)?;
+                Some(pos2(
+                    rect.min.x + px as f32,
+                    rect.center().y - v / peak * rect.height() / 2.0,
+                ))
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            PathStroke::new(1.0, ui.visuals().selection.stroke.color),
+        ));
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            format!("residual, peak {:.1} dBFS", 20.0 * peak.log10()),
+            FontId::new(10.0, egui::FontFamily::Monospace),
+            text_color,
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+
+use egui::{epaint::PathStroke, pos2, vec2, Align2, FontId, Rect};
+
+use crate::{
+    buffer::RangeLoad,
+    channels::ChannelSelection,
+    data::Chunk,
+    dsp::{
+        fresh_frames,
+        loudness::{LoudnessMeter, LoudnessReport},
+        planar,
+    },
+    export::Export,
+    jobs::{JobHandle, JobOutput, JobRequest, JobUpdate},
+    timeline::TimelineLane,
+    timeline::View,
+    Log,
+};
+
+/// Live and offline loudness measurement with a timeline lane.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct LoudnessPanel {
+    pub channels: ChannelSelection,
+    pub show_lane: bool,
+    #[serde(skip)]
+    live: Option<LoudnessMeter>,
+    #[serde(skip)]
+    last_time: Option<f32>,
+    /// Momentary and short-term loudness after each ingested chunk.
+    #[serde(skip)]
+    lane: BTreeMap<usize, (f32, f32)>,
+    #[serde(skip)]
+    pending: Option<RangeLoad>,
+    #[serde(skip)]
+    job: Option<JobHandle>,
+    #[serde(skip)]
+    offline: Option<LoudnessReport>,
+}
+
+impl Log for LoudnessPanel {
+    fn name() -> &'static str {
+        "Loudness"
+    }
+}
+
+/// The audio of `chunks` in planar form, skipping the overlap between
+/// snapshots.
+fn fresh_planar(chunks: &[Chunk], channels: &ChannelSelection, sample_rate: f32) -> Vec<Vec<f32>> {
+    let count = chunks.iter().map(|c| c.channels).max().unwrap_or(1);
+    let active = channels.active(count);
+    let mut audio = vec![vec![]; active.len()];
+    let mut last_time = None;
+    for chunk in chunks {
+        let fresh = fresh_frames(chunk, last_time, sample_rate);
+        last_time = Some(chunk.time);
+        for (all, new) in audio
+            .iter_mut()
+            .zip(planar(chunk, &active, chunk.frames() - fresh))
+        {
+            all.extend(new);
+        }
+    }
+    audio
+}
+
+/// Runs a fresh meter over `chunks`.
+pub fn measure(chunks: &[Chunk], channels: &ChannelSelection, sample_rate: f32) -> LoudnessReport {
+    let audio = fresh_planar(chunks, channels, sample_rate);
+    let mut meter = LoudnessMeter::new(sample_rate, audio.len());
+    meter.process(&audio);
+    meter.report()
+}
+
+impl LoudnessPanel {
+    const LANE_RANGE: (f32, f32) = (-60.0, 0.0);
+
+    pub fn clear(&mut self) {
+        self.live = None;
+        self.last_time = None;
+        self.lane.clear();
+        self.offline = None;
+        self.pending = None;
+        self.job = None;
+    }
+
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let active = self.channels.active(chunk.channels);
+        let stale = self.live.as_ref().map_or(true, |m| {
+            m.channels() != active.len() || m.sample_rate() != sample_rate
+        });
+        if stale {
+            self.live = Some(LoudnessMeter::new(sample_rate, active.len()));
+            self.last_time = None;
+        }
+        let meter = self.live.as_mut().unwrap();
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        meter.process(&planar(chunk, &active, chunk.frames() - fresh));
+        self.lane.insert(
+            chunk.id,
+            (
+                meter.momentary().unwrap_or(f32::NEG_INFINITY),
+                meter.short_term().unwrap_or(f32::NEG_INFINITY),
+            ),
+        );
+    }
+
+    /// Starts an offline measurement once its pages are loaded and collects
+    /// its results.
+    pub fn poll(&mut self, sample_rate: f32) {
+        if let Some(load) = RangeLoad::take_ready(&mut self.pending) {
+            Self::log(&format!("measuring chunks {}..={}", load.start, load.end));
+            self.job = Some(JobHandle::spawn(JobRequest::Loudness {
+                channels: fresh_planar(&load.chunks(), &self.channels, sample_rate),
+                sample_rate,
+            }));
+        }
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        for update in job.poll() {
+            match update {
+                JobUpdate::Progress {
+                    partial: Some(JobOutput::Loudness(report)),
+                    ..
+                }
+                | JobUpdate::Done(Some(JobOutput::Loudness(report))) => self.offline = Some(report),
+                JobUpdate::Failed(e) => Self::warn(&format!("measurement failed: {}", e)),
+                _ => {}
+            }
+        }
+        if job.is_finished() {
+            self.job = None;
+        }
+    }
+
+    /// Whether a measurement is running in the background.
+    pub fn is_busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, channels: usize, load: impl Fn(bool) -> RangeLoad) {
+        let format = |v: Option<f32>, unit: &str| {
+            v.filter(|v| v.is_finite())
+                .map_or("--".to_owned(), |v| format!("{:.1} {}", v, unit))
+        };
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_lane, "Loudness lane");
+            self.channels.picker(ui, "loudness_channels", channels);
+            if ui.button("Reset").clicked() {
+                if let Some(meter) = self.live.as_mut() {
+                    meter.reset();
+                }
+                self.last_time = None;
+            }
+        });
+        if let Some(meter) = &self.live {
+            ui.monospace(format!(
+                "M {}  S {}  I {}  LRA {}  TP {}",
+                format(meter.momentary(), "LUFS"),
+                format(meter.short_term(), "LUFS"),
+                format(meter.integrated(), "LUFS"),
+                format(meter.loudness_range(), "LU"),
+                format(meter.true_peak(), "dBTP"),
+            ));
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Measure view").clicked() {
+                self.pending = Some(load(false));
+            }
+            if ui.button("Measure session").clicked() {
+                self.pending = Some(load(true));
+            }
+            if let Some(pending) = &self.pending {
+                ui.add(egui::ProgressBar::new(pending.progress()).desired_width(100.0));
+            } else if let Some(job) = &self.job {
+                ui.add(egui::ProgressBar::new(job.progress()).desired_width(100.0));
+                if ui.button("Cancel").clicked() {
+                    self.job = None;
+                }
+            }
+        });
+        if let Some(report) = &self.offline {
+            ui.monospace(format!(
+                "{:.1} s  I {}  LRA {}  max M {}  max S {}  TP {}",
+                report.duration,
+                format(report.integrated, "LUFS"),
+                format(report.loudness_range, "LU"),
+                format(report.max_momentary, "LUFS"),
+                format(report.max_short_term, "LUFS"),
+                format(report.true_peak, "dBTP"),
+            ));
+            if self.job.is_none() && ui.button("Export JSON").clicked() {
+                Export::json("loudness_report.json", report);
+            }
+        }
+    }
+
+    fn loudness_to_y(value: f32) -> f32 {
+        let (low, high) = Self::LANE_RANGE;
+        1.0 - ((value - low) / (high - low)).clamp(0.0, 1.0)
+    }
+}
+
+impl TimelineLane for LoudnessPanel {
+    fn key(&self) -> String {
+        "loudness".to_owned()
+    }
+
+    fn title(&self) -> String {
+        "Loudness".to_owned()
+    }
+
+    fn default_height(&self) -> f32 {
+        80.0
+    }
+
+    /// Momentary (thin) and short-term (thick) loudness aligned to the timeline.
+    fn paint(&self, ui: &mut egui::Ui, rect: Rect, view: &View, _cursor: f32) {
+        let vis = ui.style().noninteractive();
+        let painter = ui.painter_at(rect);
+        let text_color = vis.text_color();
+        let font_id = FontId::new(10.0, egui::FontFamily::Monospace);
+        let to_screen =
+            egui::emath::RectTransform::from_to(Rect::from_x_y_ranges(0.0..=1.0, 0.0..=1.0), rect);
+        for lufs in [-50.0, -40.0, -30.0, -23.0, -14.0] {
+            let y = Self::loudness_to_y(lufs);
+            painter.line_segment(
+                [to_screen * pos2(0.0, y), to_screen * pos2(1.0, y)],
+                PathStroke::new(0.5, text_color.gamma_multiply(0.2)),
+            );
+            painter.text(
+                to_screen * pos2(0.0, y) + vec2(2.0, 0.0),
+                Align2::LEFT_BOTTOM,
+                format!("{}", lufs),
+                font_id.clone(),
+                text_color,
+            );
+        }
+        let width = (view.end - view.start + 1) as f32;
+        let points = |pick: fn(&(f32, f32)) -> f32| {
+            (view.start..=view.end)
+                .filter_map(|id| {
+                    let value = pick(self.lane.get(&id)?);
+                    value.is_finite().then(|| {
+                        let t = (id - view.start) as f32 / width;
+                        to_screen * pos2(t, Self::loudness_to_y(value))
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let accent = ui.visuals().selection.stroke.color;
+        painter.add(egui::Shape::line(
+            points(|v| v.0),
+            PathStroke::new(1.0, accent.gamma_multiply(0.5)),
+        ));
+        painter.add(egui::Shape::line(
+            points(|v| v.1),
+            PathStroke::new(2.0, accent),
+        ));
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            "LUFS (M / S)",
+            font_id,
+            text_color,
+        );
+    }
+}
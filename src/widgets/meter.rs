@@ -0,0 +1,202 @@
+use egui::{pos2, vec2, Align2, Color32, FontId, Rect, Sense, Stroke};
+
+use crate::{
+    channels::channel_name,
+    data::{to_amplitude, Chunk},
+    dsp::{
+        fresh_frames,
+        meter::{ChannelMeter, Levels, MeterSettings},
+    },
+    Log,
+};
+
+/// Per-channel peak/RMS/VU/PPM bars, fed from the ingest path.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct LevelMeters {
+    pub settings: MeterSettings,
+    #[serde(skip)]
+    meters: Vec<ChannelMeter>,
+    #[serde(skip)]
+    last_time: Option<f32>,
+}
+
+impl Log for LevelMeters {
+    fn name() -> &'static str {
+        "LevelMeters"
+    }
+}
+
+impl LevelMeters {
+    const RANGE: (f32, f32) = (-60.0, 0.0);
+    const BAR_WIDTH: f32 = 8.0;
+    const BARS: [&'static str; 4] = ["Pk", "RMS", "VU", "PPM"];
+
+    pub fn clear(&mut self) {
+        self.meters.clear();
+        self.last_time = None;
+    }
+
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        if self.meters.len() < chunk.channels {
+            self.meters
+                .resize_with(chunk.channels, ChannelMeter::default);
+        }
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        let skip = chunk.frames() - fresh;
+        for (channel, meter) in self.meters.iter_mut().enumerate().take(chunk.channels) {
+            let samples: Vec<f32> = chunk
+                .channel(channel)
+                .skip(skip)
+                .map(to_amplitude)
+                .collect();
+            meter.process(&samples, sample_rate, &self.settings);
+        }
+    }
+
+    pub fn reset_clips(&mut self) {
+        self.meters.iter_mut().for_each(|m| {
+            m.reset_clip();
+            m.reset_hold();
+        });
+    }
+
+    fn db_to_y(rect: Rect, db: f32) -> f32 {
+        let (low, high) = Self::RANGE;
+        let t = ((db - low) / (high - low)).clamp(0.0, 1.0);
+        rect.max.y - t * rect.height()
+    }
+
+    fn bar_color(db: f32) -> Color32 {
+        if db > -3.0 {
+            Color32::from_rgb(220, 50, 50)
+        } else if db > -12.0 {
+            Color32::from_rgb(230, 190, 40)
+        } else {
+            Color32::from_rgb(60, 180, 75)
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Levels");
+            if ui
+                .button("Reset")
+                .on_hover_text("Reset clip and peak hold")
+                .clicked()
+            {
+                self.reset_clips();
+            }
+        });
+        if self.meters.is_empty() {
+            ui.weak("no signal");
+        }
+        let text_color = ui.style().noninteractive().text_color();
+        let font_id = FontId::new(9.0, egui::FontFamily::Monospace);
+        let mut reset_clip = None;
+        ui.horizontal_top(|ui| {
+            let height = 220.0;
+            // dBFS scale
+            let (scale_rect, _) = ui.allocate_exact_size(vec2(24.0, height), Sense::hover());
+            let bars_rect = scale_rect.shrink2(vec2(0.0, 14.0));
+            for db in [0, -6, -12, -18, -24, -36, -48, -60] {
+                ui.painter().text(
+                    pos2(scale_rect.max.x, Self::db_to_y(bars_rect, db as f32)),
+                    Align2::RIGHT_CENTER,
+                    format!("{}", db),
+                    font_id.clone(),
+                    text_color,
+                );
+            }
+            for (channel, meter) in self.meters.iter().enumerate() {
+                let levels = meter.levels();
+                let width = Self::BAR_WIDTH * 4.0 + 9.0;
+                let (rect, _) = ui.allocate_exact_size(vec2(width, height), Sense::hover());
+                let (clip_rect, clip_response) = {
+                    let clip_rect = Rect::from_min_size(rect.min, vec2(width, 10.0));
+                    let response =
+                        ui.interact(clip_rect, ui.id().with(("clip", channel)), Sense::click());
+                    (clip_rect, response)
+                };
+                let clip_color = if levels.clipped {
+                    Color32::RED
+                } else {
+                    ui.visuals().faint_bg_color
+                };
+                ui.painter().rect_filled(clip_rect, 2.0, clip_color);
+                if clip_response
+                    .on_hover_text("Clip, click to reset")
+                    .clicked()
+                {
+                    reset_clip = Some(channel);
+                }
+                let bars = Rect::from_x_y_ranges(rect.x_range(), bars_rect.y_range());
+                self.draw_bars(ui, bars, &levels);
+                ui.painter().text(
+                    rect.center_bottom(),
+                    Align2::CENTER_BOTTOM,
+                    channel_name(channel),
+                    font_id.clone(),
+                    text_color,
+                );
+            }
+        });
+        if let Some(channel) = reset_clip {
+            self.meters[channel].reset_clip();
+        }
+        ui.weak(format!("bars: {}", Self::BARS.join(" · ")));
+        if let Some(levels) = self.meters.first().map(|m| m.levels()) {
+            ui.monospace(format!(
+                "Pk {:.1}  RMS {:.1}  VU {:.1}  PPM {:.1}",
+                levels.peak, levels.rms, levels.vu, levels.ppm
+            ));
+        }
+        egui::CollapsingHeader::new("Ballistics").show(ui, |ui| {
+            let settings = &mut self.settings;
+            let drag = |ui: &mut egui::Ui, value: &mut f32, label: &str, suffix: &str| {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    ui.add(
+                        egui::DragValue::new(value)
+                            .range(0.0..=10000.0)
+                            .suffix(suffix),
+                    );
+                });
+            };
+            drag(ui, &mut settings.rms_ms, "RMS window", " ms");
+            drag(ui, &mut settings.vu_ms, "VU rise", " ms");
+            drag(ui, &mut settings.ppm_attack_ms, "PPM attack", " ms");
+            drag(ui, &mut settings.ppm_decay_db_per_s, "PPM decay", " dB/s");
+            drag(ui, &mut settings.peak_hold_s, "Peak hold", " s");
+            drag(ui, &mut settings.peak_decay_db_per_s, "Peak decay", " dB/s");
+            if ui.button("Defaults").clicked() {
+                *settings = MeterSettings::default();
+            }
+        });
+    }
+
+    fn draw_bars(&self, ui: &egui::Ui, rect: Rect, levels: &Levels) {
+        let painter = ui.painter_at(rect.expand(1.0));
+        let values = [levels.peak, levels.rms, levels.vu, levels.ppm];
+        for (i, value) in values.iter().enumerate() {
+            let x = rect.min.x + i as f32 * (Self::BAR_WIDTH + 3.0);
+            let bar = Rect::from_x_y_ranges(x..=x + Self::BAR_WIDTH, rect.y_range());
+            painter.rect_filled(bar, 0.0, ui.visuals().extreme_bg_color);
+            let top = Self::db_to_y(rect, *value);
+            painter.rect_filled(
+                Rect::from_x_y_ranges(bar.x_range(), top..=rect.max.y),
+                0.0,
+                Self::bar_color(*value),
+            );
+            if i == 0 {
+                let y = Self::db_to_y(rect, levels.peak_hold);
+                painter.line_segment(
+                    [pos2(bar.min.x, y), pos2(bar.max.x, y)],
+                    Stroke::new(2.0, Self::bar_color(levels.peak_hold)),
+                );
+            }
+            painter.rect_stroke(bar, 0.0, Stroke::new(0.5, ui.visuals().weak_text_color()));
+        }
+    }
+}
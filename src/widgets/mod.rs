@@ -1 +1,11 @@
-pub mod timeline;
+pub mod acoustics;
+pub mod annotations;
+pub mod diagnostics;
+pub mod distortion;
+pub mod loudness;
+pub mod meter;
+pub mod overview;
+pub mod pitch;
+pub mod remote;
+pub mod rta;
+pub mod sweep;
@@ -0,0 +1,103 @@
+use egui::{epaint::PathStroke, pos2, vec2, Color32, Rect, Sense, Stroke};
+
+/// Strip above the detail view that shows the envelope of the whole session
+/// and a window rectangle for the chunks the `Timeline` currently displays.
+pub struct Overview {}
+
+pub trait OverviewApi {
+    /// Min/max pairs for the whole session, reduced to `buckets` columns.
+    fn envelope(&self, buckets: usize) -> Vec<(f32, f32)>;
+    /// Id range of the whole session, inclusive.
+    fn session_range(&self) -> Option<(usize, usize)>;
+    /// Id range shown by the detail view, inclusive.
+    fn view_range(&self) -> (usize, usize);
+    /// Moves the detail view so it is centered on chunk `id`.
+    fn jump_to(&mut self, id: usize);
+}
+
+impl Overview {
+    const HEIGHT: f32 = 40.0;
+
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, api: &mut dyn OverviewApi) {
+        let desired_size = vec2(ui.available_width(), Self::HEIGHT);
+        let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
+
+        let vis = ui.style().noninteractive();
+        let bg_stroke = Stroke {
+            width: 1.0,
+            ..vis.bg_stroke
+        };
+        ui.painter().rect(rect, 0.0, vis.weak_bg_fill, bg_stroke);
+
+        let Some((first, last)) = api.session_range() else {
+            return;
+        };
+        let span = (last - first + 1) as f32;
+        let id_to_x = |id: f32| rect.min.x + (id - first as f32) / span * rect.width();
+        let x_to_id = |x: f32| {
+            let id = first as f32 + (x - rect.min.x) / rect.width() * span;
+            id.clamp(first as f32, last as f32)
+        };
+
+        let color = if ui.visuals().dark_mode {
+            Color32::from_additive_luminance(160)
+        } else {
+            Color32::from_black_alpha(200)
+        };
+        let to_screen =
+            egui::emath::RectTransform::from_to(Rect::from_x_y_ranges(0.0..=1.0, 1.0..=-1.0), rect);
+        let envelope = api.envelope(rect.width().max(1.0) as usize);
+        let n = envelope.len();
+        let shapes = envelope
+            .iter()
+            .enumerate()
+            .map(|(i, (min, max))| {
+                let t = (i as f32 + 0.5) / n as f32;
+                let (top, bottom) = (to_screen * pos2(t, *max), to_screen * pos2(t, *min));
+                egui::epaint::Shape::line_segment(
+                    [top, pos2(top.x, bottom.y.max(top.y + 1.0))],
+                    PathStroke::new(1.0, color),
+                )
+            })
+            .collect::<Vec<_>>();
+        ui.painter_at(rect).extend(shapes);
+
+        let (view_start, view_end) = api.view_range();
+        let window = Rect::from_x_y_ranges(
+            id_to_x(view_start as f32)
+                ..=id_to_x(view_end as f32 + 1.0).max(id_to_x(view_start as f32) + 2.0),
+            rect.y_range(),
+        );
+        let accent = ui.visuals().selection.bg_fill;
+        ui.painter_at(rect).rect(
+            window,
+            0.0,
+            accent.gamma_multiply(0.25),
+            Stroke::new(1.0, accent),
+        );
+
+        let view_center = (view_start + view_end) as f32 / 2.0;
+        let grab_id = response.id.with("grab_offset");
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let pointer_id = x_to_id(pointer.x);
+            if response.drag_started() {
+                let offset = if window.contains(pointer) {
+                    pointer_id - view_center
+                } else {
+                    0.0
+                };
+                ui.data_mut(|d| d.insert_temp(grab_id, offset));
+            }
+            if response.dragged() {
+                let offset = ui.data(|d| d.get_temp::<f32>(grab_id)).unwrap_or(0.0);
+                api.jump_to((pointer_id - offset).round().max(0.0) as usize);
+            } else if response.clicked() {
+                api.jump_to(pointer_id.round() as usize);
+            }
+        }
+    }
+}
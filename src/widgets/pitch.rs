@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use egui::{epaint::PathStroke, pos2, vec2, Align2, Color32, FontId, Rect};
+
+use crate::{
+    channels::ChannelSelection,
+    data::Chunk,
+    dsp::{
+        mixdown,
+        pitch::{midi_frequency, note, PitchEstimate, Yin},
+    },
+    jobs::{JobHandle, JobOutput, JobRequest, JobUpdate},
+    timeline::TimelineLane,
+    timeline::View,
+    Log,
+};
+
+/// Per-chunk pitch estimates, filled live on ingest and by a background job
+/// for chunks loaded back from the buffer.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct PitchTrack {
+    pub yin: Yin,
+    pub channels: ChannelSelection,
+    pub show_lane: bool,
+    /// Estimates below this confidence are drawn faded and ignored by the tuner.
+    pub min_confidence: f32,
+    #[serde(skip)]
+    track: BTreeMap<usize, Option<PitchEstimate>>,
+    #[serde(skip)]
+    job: Option<JobHandle>,
+}
+
+impl Log for PitchTrack {
+    fn name() -> &'static str {
+        "PitchTrack"
+    }
+}
+
+impl PitchTrack {
+    /// Drops the cached estimates, e.g. after the estimator settings changed.
+    pub fn clear(&mut self) {
+        self.track.clear();
+        self.job = None;
+    }
+
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let channels = self.channels.active(chunk.channels);
+        let estimate = self.yin.estimate(&mixdown(chunk, &channels), sample_rate);
+        self.track.insert(chunk.id, estimate);
+    }
+
+    /// Starts estimating the chunks of the view that weren't seen on ingest,
+    /// unless a job is still busy with earlier ones.
+    pub fn analyze(&mut self, chunks: &[Chunk], sample_rate: f32) {
+        if self.job.is_some() {
+            return;
+        }
+        let blocks: Vec<(usize, Vec<f32>)> = chunks
+            .iter()
+            .filter(|chunk| !self.track.contains_key(&chunk.id))
+            .map(|chunk| {
+                let channels = self.channels.active(chunk.channels);
+                (chunk.id, mixdown(chunk, &channels))
+            })
+            .collect();
+        if blocks.is_empty() {
+            return;
+        }
+        Self::log(&format!("estimating {} chunks", blocks.len()));
+        // unvoiced until the job reports, so a failed job isn't retried
+        self.track.extend(blocks.iter().map(|(id, _)| (*id, None)));
+        self.job = Some(JobHandle::spawn(JobRequest::Pitch {
+            blocks,
+            yin: self.yin.clone(),
+            sample_rate,
+        }));
+    }
+
+    /// Collects the estimates of the running job.
+    pub fn poll(&mut self) {
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        for update in job.poll() {
+            match update {
+                JobUpdate::Progress {
+                    partial: Some(JobOutput::Pitch(estimates)),
+                    ..
+                } => self.track.extend(estimates),
+                JobUpdate::Failed(e) => Self::warn(&format!("estimation failed: {}", e)),
+                _ => {}
+            }
+        }
+        if job.is_finished() {
+            self.job = None;
+        }
+    }
+
+    /// Whether estimates are being computed in the background.
+    pub fn is_busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn get(&self, id: usize) -> Option<PitchEstimate> {
+        self.track
+            .get(&id)
+            .copied()
+            .flatten()
+            .filter(|e| e.confidence >= self.min_confidence)
+    }
+
+    pub fn latest(&self) -> Option<PitchEstimate> {
+        self.track
+            .iter()
+            .next_back()
+            .and_then(|(id, _)| self.get(*id))
+    }
+
+    fn frequency_to_y(&self, frequency: f32) -> f32 {
+        let (low, high) = (self.yin.min_frequency.log2(), self.yin.max_frequency.log2());
+        1.0 - (frequency.log2() - low) / (high - low)
+    }
+
+    pub fn controls(&mut self, ui: &mut egui::Ui, channels: usize) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_lane, "Pitch lane");
+            self.channels.picker(ui, "pitch_channels", channels);
+            let mut changed = false;
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.yin.threshold)
+                        .range(0.01..=0.5)
+                        .speed(0.005)
+                        .prefix("threshold "),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.yin.min_frequency)
+                        .range(20.0..=self.yin.max_frequency - 1.0)
+                        .suffix(" Hz"),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.yin.max_frequency)
+                        .range(self.yin.min_frequency + 1.0..=8000.0)
+                        .suffix(" Hz"),
+                )
+                .changed();
+            ui.add(egui::Slider::new(&mut self.min_confidence, 0.0..=1.0).text("min confidence"));
+            if changed {
+                self.clear();
+            }
+        });
+    }
+
+    /// Live tuner readout for the most recent chunk.
+    pub fn tuner(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let Some(estimate) = self.latest() else {
+                ui.monospace("Tuner: --");
+                return;
+            };
+            let (name, cents) = note(estimate.frequency);
+            ui.monospace(format!("Tuner: {:>4} {:+4.0}c", name, cents));
+            let (rect, _) = ui.allocate_exact_size(vec2(160.0, 14.0), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            let color = ui.style().noninteractive().text_color();
+            painter.line_segment(
+                [rect.center_top(), rect.center_bottom()],
+                PathStroke::new(1.0, color),
+            );
+            let x = rect.center().x + cents / 50.0 * rect.width() * 0.5;
+            let in_tune = cents.abs() < 5.0;
+            let needle = if in_tune {
+                Color32::GREEN
+            } else {
+                ui.visuals().warn_fg_color
+            };
+            painter.line_segment(
+                [pos2(x, rect.min.y), pos2(x, rect.max.y)],
+                PathStroke::new(3.0, needle),
+            );
+            ui.monospace(format!("{:.1} Hz", estimate.frequency));
+        });
+    }
+}
+
+impl TimelineLane for PitchTrack {
+    fn key(&self) -> String {
+        "pitch".to_owned()
+    }
+
+    fn title(&self) -> String {
+        "Pitch".to_owned()
+    }
+
+    fn default_height(&self) -> f32 {
+        80.0
+    }
+
+    /// Pitch lane aligned to the timeline body, with a readout for `cursor`.
+    fn paint(&self, ui: &mut egui::Ui, rect: Rect, view: &View, cursor: f32) {
+        let cursor = cursor as usize;
+        let vis = ui.style().noninteractive();
+        let painter = ui.painter_at(rect);
+        let text_color = vis.text_color();
+        let grid_color = text_color.gamma_multiply(0.2);
+        let font_id = FontId::new(10.0, egui::FontFamily::Monospace);
+        let to_screen =
+            egui::emath::RectTransform::from_to(Rect::from_x_y_ranges(0.0..=1.0, 0.0..=1.0), rect);
+
+        // a gridline per C
+        for octave in 0..10 {
+            let frequency = midi_frequency(12 * (octave + 1));
+            if frequency < self.yin.min_frequency || frequency > self.yin.max_frequency {
+                continue;
+            }
+            let y = self.frequency_to_y(frequency);
+            painter.line_segment(
+                [to_screen * pos2(0.0, y), to_screen * pos2(1.0, y)],
+                PathStroke::new(0.5, grid_color),
+            );
+            painter.text(
+                to_screen * pos2(0.0, y) + vec2(2.0, 0.0),
+                Align2::LEFT_BOTTOM,
+                format!("C{}", octave),
+                font_id.clone(),
+                text_color,
+            );
+        }
+
+        let width = (view.end - view.start + 1) as f32;
+        let accent = ui.visuals().selection.stroke.color;
+        for id in view.start..=view.end {
+            let Some(Some(estimate)) = self.track.get(&id) else {
+                continue;
+            };
+            let t = (id - view.start) as f32 / width;
+            let y = self.frequency_to_y(estimate.frequency);
+            let alpha = if estimate.confidence >= self.min_confidence {
+                1.0
+            } else {
+                0.25
+            };
+            painter.line_segment(
+                [to_screen * pos2(t, y), to_screen * pos2(t + 1.0 / width, y)],
+                PathStroke::new(3.0, accent.gamma_multiply(alpha)),
+            );
+        }
+
+        let readout = match self.get(cursor) {
+            Some(estimate) => {
+                let (name, cents) = note(estimate.frequency);
+                format!(
+                    "{:.1} Hz  {} {:+.0}c  ({:.0}%)",
+                    estimate.frequency,
+                    name,
+                    cents,
+                    estimate.confidence * 100.0
+                )
+            }
+            None => "unvoiced".to_owned(),
+        };
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            readout,
+            font_id,
+            text_color,
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    clock,
+    remote::{LiveMetrics, MetricsMeter, PcmFrame, Reblocker},
+    socket::{Socket, SocketState},
+    Log,
+};
+
+/// A block of streamed audio, ready for `TemplateApp::push_samples`.
+pub struct RemoteBlock {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: f32,
+    pub timestamp_ms: f64,
+}
+
+/// What arrived since the last `RemotePanel::poll`.
+#[derive(Default)]
+pub struct RemoteUpdate {
+    pub blocks: Vec<RemoteBlock>,
+    /// Metrics of the new frames, in the order they were published.
+    pub metrics: Vec<LiveMetrics>,
+}
+
+/// WebSocket client mode: ingests PCM frames from a server and sends the
+/// live metrics back.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RemotePanel {
+    pub url: String,
+    pub publish: bool,
+    #[serde(skip)]
+    socket: Option<Socket>,
+    #[serde(skip)]
+    reblocker: Reblocker,
+    #[serde(skip)]
+    meter: Option<MetricsMeter>,
+    #[serde(skip)]
+    metrics: Option<LiveMetrics>,
+    /// Wall clock time of the first frame and frames received since.
+    #[serde(skip)]
+    clock: Option<(f64, usize)>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl Default for RemotePanel {
+    fn default() -> Self {
+        Self {
+            url: "ws://127.0.0.1:9002".to_owned(),
+            publish: true,
+            socket: None,
+            reblocker: Reblocker::default(),
+            meter: None,
+            metrics: None,
+            clock: None,
+            error: None,
+        }
+    }
+}
+
+impl Log for RemotePanel {
+    fn name() -> &'static str {
+        "Remote"
+    }
+}
+
+impl RemotePanel {
+    pub fn connect(&mut self, ctx: &egui::Context) {
+        self.disconnect();
+        match Socket::connect(&self.url, ctx.clone()) {
+            Ok(socket) => {
+                Self::log(&format!("connecting to {}", self.url));
+                self.socket = Some(socket);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Why the stream ended, once it has.
+    pub fn closed(&self) -> Option<&str> {
+        match &self.socket {
+            Some(_) => None,
+            None => Some(self.error.as_deref().unwrap_or("disconnected")),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.socket = None;
+        self.reblocker = Reblocker::default();
+        self.meter = None;
+        self.metrics = None;
+        self.clock = None;
+    }
+
+    /// Decodes the frames received since the last call into blocks of
+    /// `sample_rate / blocks_per_second` frames, and publishes metrics.
+    /// Frames that don't decode are logged and skipped.
+    pub fn poll(&mut self, blocks_per_second: f32) -> RemoteUpdate {
+        let Some(socket) = &self.socket else {
+            return RemoteUpdate::default();
+        };
+        let mut update = RemoteUpdate::default();
+        for message in socket.take_messages() {
+            let frame = match PcmFrame::decode(&message) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    Self::warn(&format!("skipping frame: {}", e));
+                    self.error = Some(e);
+                    continue;
+                }
+            };
+            if !self.meter.as_ref().map_or(false, |m| m.accepts(&frame)) {
+                Self::log(&format!(
+                    "stream format {} Hz, {} channels",
+                    frame.sample_rate, frame.channels
+                ));
+                self.meter = Some(MetricsMeter::new(frame.sample_rate, frame.channels));
+                self.clock = None;
+            }
+            if let Some(metrics) = self.meter.as_mut().and_then(|m| m.process(&frame.samples)) {
+                if self.publish {
+                    if let Ok(json) = serde_json::to_string(&metrics) {
+                        socket.send_text(&json);
+                    }
+                }
+                update.metrics.push(metrics.clone());
+                self.metrics = Some(metrics);
+            }
+            let size = (frame.sample_rate / blocks_per_second).round() as usize;
+            for samples in self.reblocker.push(&frame, size) {
+                let (start, frames) = *self.clock.get_or_insert((clock::now_ms(), 0));
+                update.blocks.push(RemoteBlock {
+                    timestamp_ms: start + frames as f64 / frame.sample_rate as f64 * 1000.0,
+                    samples,
+                    channels: frame.channels,
+                    sample_rate: frame.sample_rate,
+                });
+                self.clock = Some((start, frames + size));
+            }
+        }
+        // after taking what arrived before the close
+        if let SocketState::Closed(reason) = socket.state() {
+            self.error = Some(reason);
+            self.disconnect();
+        }
+        update
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Server");
+            ui.add_enabled(
+                self.socket.is_none(),
+                egui::TextEdit::singleline(&mut self.url).desired_width(200.0),
+            );
+            if self.socket.is_some() {
+                if ui.button("Disconnect").clicked() {
+                    self.disconnect();
+                }
+            } else if ui.button("Connect").clicked() {
+                self.connect(ui.ctx());
+            }
+            ui.checkbox(&mut self.publish, "Publish metrics");
+        });
+        match self.socket.as_ref().map(|s| s.state()) {
+            Some(SocketState::Connecting) => {
+                ui.label("connecting…");
+            }
+            Some(SocketState::Open) => match &self.metrics {
+                Some(metrics) => {
+                    let peak = metrics
+                        .levels
+                        .iter()
+                        .map(|l| l.peak_db)
+                        .fold(f32::NEG_INFINITY, f32::max);
+                    let lufs = metrics
+                        .momentary_lufs
+                        .map_or("–".to_owned(), |v| format!("{:.1}", v));
+                    ui.monospace(format!(
+                        "{:.1} s received, peak {:.1} dBFS, {} LUFS M{}",
+                        metrics.time_s,
+                        peak,
+                        lufs,
+                        metrics
+                            .note
+                            .as_ref()
+                            .map_or(String::new(), |note| format!(", {}", note)),
+                    ));
+                }
+                None => {
+                    ui.label("waiting for audio…");
+                }
+            },
+            _ => {}
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, error);
+        }
+    }
+}
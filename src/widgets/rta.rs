@@ -0,0 +1,306 @@
+use egui::{pos2, vec2, Align2, Color32, FontId, Rect, Sense, Stroke};
+
+use crate::{
+    buffer::RangeLoad,
+    channels::ChannelSelection,
+    data::Chunk,
+    dsp::{
+        fresh_frames, mixdown,
+        octave::{band_label, to_csv, Averaging, BandLevel, OctaveAnalyzer, Weighting},
+        Stream,
+    },
+    export::Export,
+    jobs::{JobHandle, JobOutput, JobRequest, JobUpdate},
+    Log,
+};
+
+/// Where the displayed band levels come from.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum RtaSource {
+    Live,
+    Range,
+}
+
+/// Fractional-octave real-time analyzer, fed live or from a buffered range.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RtaPanel {
+    pub channels: ChannelSelection,
+    /// Bands per octave: 1, 3 or 6.
+    pub fraction: usize,
+    pub weighting: Weighting,
+    pub averaging: Averaging,
+    pub source: RtaSource,
+    #[serde(skip)]
+    live: Option<OctaveAnalyzer>,
+    #[serde(skip)]
+    last_time: Option<f32>,
+    #[serde(skip)]
+    pending: Option<RangeLoad>,
+    #[serde(skip)]
+    job: Option<JobHandle>,
+    #[serde(skip)]
+    offline: Option<Vec<BandLevel>>,
+}
+
+impl Default for RtaPanel {
+    fn default() -> Self {
+        Self {
+            channels: ChannelSelection::default(),
+            fraction: 3,
+            weighting: Weighting::Z,
+            averaging: Averaging::Fast,
+            source: RtaSource::Live,
+            live: None,
+            last_time: None,
+            pending: None,
+            job: None,
+            offline: None,
+        }
+    }
+}
+
+impl Log for RtaPanel {
+    fn name() -> &'static str {
+        "Rta"
+    }
+}
+
+impl RtaPanel {
+    const FRACTIONS: [usize; 3] = [1, 3, 6];
+    const RANGE: (f32, f32) = (-90.0, 0.0);
+    const HEIGHT: f32 = 180.0;
+
+    pub fn clear(&mut self) {
+        self.live = None;
+        self.last_time = None;
+        self.pending = None;
+        self.job = None;
+        self.offline = None;
+    }
+
+    fn analyzer(&self, sample_rate: f32) -> OctaveAnalyzer {
+        OctaveAnalyzer::new(self.fraction, sample_rate, self.weighting, self.averaging)
+    }
+
+    pub fn ingest(&mut self, chunk: &Chunk, sample_rate: f32) {
+        let stale = self.live.as_ref().map_or(true, |a| {
+            a.sample_rate != sample_rate
+                || a.fraction != self.fraction
+                || a.averaging != self.averaging
+        });
+        if stale {
+            self.live = Some(self.analyzer(sample_rate));
+            self.last_time = None;
+        }
+        let analyzer = self.live.as_mut().unwrap();
+        analyzer.weighting = self.weighting;
+        let fresh = fresh_frames(chunk, self.last_time, sample_rate);
+        self.last_time = Some(chunk.time);
+        let mono = mixdown(chunk, &self.channels.active(chunk.channels));
+        analyzer.process(&mono[mono.len() - fresh..]);
+    }
+
+    /// Starts analyzing a loaded range once its pages arrive and collects
+    /// the band levels.
+    pub fn poll(&mut self, sample_rate: f32) {
+        if let Some(load) = RangeLoad::take_ready(&mut self.pending) {
+            Self::log(&format!("analyzing chunks {}..={}", load.start, load.end));
+            let stream =
+                Stream::from_chunks(&load.chunks(), sample_rate, |n| self.channels.active(n));
+            // the whole range is one measurement
+            self.job = Some(JobHandle::spawn(JobRequest::Bands {
+                samples: stream.samples,
+                fraction: self.fraction,
+                weighting: self.weighting,
+                sample_rate,
+            }));
+            self.source = RtaSource::Range;
+        }
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        for update in job.poll() {
+            match update {
+                JobUpdate::Progress {
+                    partial: Some(JobOutput::Bands(levels)),
+                    ..
+                }
+                | JobUpdate::Done(Some(JobOutput::Bands(levels))) => self.offline = Some(levels),
+                JobUpdate::Failed(e) => Self::warn(&format!("analysis failed: {}", e)),
+                _ => {}
+            }
+        }
+        if job.is_finished() {
+            self.job = None;
+        }
+    }
+
+    /// Whether a range is being analyzed in the background.
+    pub fn is_busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    fn levels(&self) -> Option<Vec<BandLevel>> {
+        match self.source {
+            RtaSource::Live => self.live.as_ref().map(|a| a.levels()),
+            RtaSource::Range => self.offline.clone(),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, channels: usize, load: impl Fn(bool) -> RangeLoad) {
+        ui.horizontal(|ui| {
+            self.channels.picker(ui, "rta_channels", channels);
+            egui::ComboBox::from_id_source("rta_fraction")
+                .selected_text(format!("1/{} oct", self.fraction))
+                .show_ui(ui, |ui| {
+                    for fraction in Self::FRACTIONS {
+                        ui.selectable_value(
+                            &mut self.fraction,
+                            fraction,
+                            format!("1/{} oct", fraction),
+                        );
+                    }
+                });
+            egui::ComboBox::from_id_source("rta_weighting")
+                .selected_text(self.weighting.label())
+                .show_ui(ui, |ui| {
+                    for weighting in Weighting::ALL {
+                        ui.selectable_value(&mut self.weighting, weighting, weighting.label());
+                    }
+                });
+            egui::ComboBox::from_id_source("rta_averaging")
+                .selected_text(self.averaging.label())
+                .show_ui(ui, |ui| {
+                    for averaging in Averaging::ALL {
+                        ui.selectable_value(&mut self.averaging, averaging, averaging.label());
+                    }
+                });
+            if ui.button("Reset").clicked() {
+                if let Some(analyzer) = self.live.as_mut() {
+                    analyzer.reset();
+                }
+                self.last_time = None;
+            }
+            if ui
+                .button("Reset peaks")
+                .on_hover_text("Clear the peak hold markers")
+                .clicked()
+            {
+                if let Some(analyzer) = self.live.as_mut() {
+                    analyzer.reset_peaks();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.source, RtaSource::Live, "Live");
+            ui.add_enabled_ui(self.offline.is_some(), |ui| {
+                ui.selectable_value(&mut self.source, RtaSource::Range, "Range");
+            });
+            if ui.button("Analyze view").clicked() {
+                self.pending = Some(load(false));
+            }
+            if ui.button("Analyze session").clicked() {
+                self.pending = Some(load(true));
+            }
+            if let Some(pending) = &self.pending {
+                ui.add(egui::ProgressBar::new(pending.progress()).desired_width(100.0));
+            } else if let Some(job) = &self.job {
+                ui.add(egui::ProgressBar::new(job.progress()).desired_width(100.0));
+                if ui.button("Cancel").clicked() {
+                    self.job = None;
+                }
+            }
+        });
+        let levels = self.levels().unwrap_or_default();
+        if levels.is_empty() {
+            ui.weak("no signal");
+            return;
+        }
+        self.draw_bars(ui, &levels);
+        if ui.button("Export CSV").clicked() {
+            Export::csv("rta_bands.csv", &to_csv(&levels, self.weighting));
+        }
+    }
+
+    fn db_to_y(rect: Rect, db: f32) -> f32 {
+        let (low, high) = Self::RANGE;
+        let t = ((db - low) / (high - low)).clamp(0.0, 1.0);
+        rect.max.y - t * rect.height()
+    }
+
+    fn draw_bars(&self, ui: &mut egui::Ui, levels: &[BandLevel]) {
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(ui.available_width(), Self::HEIGHT), Sense::hover());
+        let vis = ui.style().noninteractive();
+        ui.painter().rect(
+            rect,
+            0.0,
+            vis.weak_bg_fill,
+            Stroke::new(1.0, vis.bg_stroke.color),
+        );
+        let painter = ui.painter_at(rect);
+        let text_color = vis.text_color();
+        let font_id = FontId::new(9.0, egui::FontFamily::Monospace);
+        let plot = Rect::from_min_max(rect.min + vec2(28.0, 4.0), rect.max - vec2(2.0, 14.0));
+        for db in (-90..=0).step_by(10) {
+            let y = Self::db_to_y(plot, db as f32);
+            painter.line_segment(
+                [pos2(plot.min.x, y), pos2(plot.max.x, y)],
+                Stroke::new(0.5, text_color.gamma_multiply(0.2)),
+            );
+            painter.text(
+                pos2(plot.min.x - 2.0, y),
+                Align2::RIGHT_CENTER,
+                format!("{}", db),
+                font_id.clone(),
+                text_color,
+            );
+        }
+        let slot = plot.width() / levels.len() as f32;
+        let label_every = (28.0 / slot).ceil().max(1.0) as usize;
+        let accent = ui.visuals().selection.stroke.color;
+        for (i, band) in levels.iter().enumerate() {
+            let x = plot.min.x + i as f32 * slot;
+            let bar = Rect::from_x_y_ranges(x + 1.0..=x + slot - 1.0, plot.y_range());
+            if band.level.is_finite() {
+                painter.rect_filled(
+                    Rect::from_x_y_ranges(
+                        bar.x_range(),
+                        Self::db_to_y(plot, band.level)..=plot.max.y,
+                    ),
+                    0.0,
+                    accent,
+                );
+            }
+            if band.peak.is_finite() {
+                let y = Self::db_to_y(plot, band.peak);
+                painter.line_segment(
+                    [pos2(bar.min.x, y), pos2(bar.max.x, y)],
+                    Stroke::new(2.0, Color32::from_rgb(230, 190, 40)),
+                );
+            }
+            if i % label_every == 0 {
+                painter.text(
+                    pos2(bar.center().x, rect.max.y),
+                    Align2::CENTER_BOTTOM,
+                    band_label(band.center),
+                    font_id.clone(),
+                    text_color,
+                );
+            }
+        }
+        if let Some(pos) = response.hover_pos() {
+            let i = ((pos.x - plot.min.x) / slot).floor();
+            if let Some(band) = (i >= 0.0).then(|| levels.get(i as usize)).flatten() {
+                response.on_hover_text(format!(
+                    "{} Hz: {:.1} dB{} (peak {:.1})",
+                    band_label(band.center),
+                    band.level,
+                    self.weighting.label(),
+                    band.peak
+                ));
+            }
+        }
+    }
+}
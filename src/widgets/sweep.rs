@@ -0,0 +1,378 @@
+use egui::{epaint::PathStroke, pos2, vec2, Align2, Color32, FontId, Sense, Stroke};
+
+use crate::{
+    buffer::RangeLoad,
+    channels::ChannelSelection,
+    dsp::{
+        sweep::{
+            deconvolve, frequency_response, harmonic_distortion, log_grid, Deconvolution,
+            FrequencyResponse, SweepSettings,
+        },
+        Stream,
+    },
+    export::Export,
+    Log,
+};
+
+/// Results of one sweep measurement at the current smoothing.
+struct SweepResult {
+    deconvolution: Deconvolution,
+    response: FrequencyResponse,
+    /// dB relative to the fundamental, one curve per harmonic from H2.
+    harmonics: Vec<Vec<f32>>,
+    smoothing: usize,
+}
+
+/// Log-sweep frequency response measurement.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct SweepPanel {
+    pub channels: ChannelSelection,
+    pub settings: SweepSettings,
+    /// Fractional-octave smoothing, 0 for none.
+    pub smoothing: usize,
+    pub show_phase: bool,
+    pub show_harmonics: bool,
+    /// First chunk captured after the sweep started playing.
+    #[serde(skip)]
+    recording_from: Option<usize>,
+    #[serde(skip)]
+    play_request: Option<SweepSettings>,
+    #[serde(skip)]
+    pending: Option<RangeLoad>,
+    #[serde(skip)]
+    result: Option<Result<SweepResult, String>>,
+}
+
+impl Log for SweepPanel {
+    fn name() -> &'static str {
+        "Sweep"
+    }
+}
+
+impl SweepPanel {
+    const SMOOTHING: [usize; 6] = [0, 1, 3, 6, 12, 24];
+    const POINTS: usize = 400;
+    const PLOT_HEIGHT: f32 = 160.0;
+    const MAGNITUDE_SPAN: f32 = 60.0;
+    const HARMONIC_COLORS: [Color32; 4] = [
+        Color32::from_rgb(230, 190, 40),
+        Color32::from_rgb(220, 90, 50),
+        Color32::from_rgb(160, 90, 200),
+        Color32::from_rgb(60, 180, 75),
+    ];
+
+    pub fn clear(&mut self) {
+        self.recording_from = None;
+        self.pending = None;
+    }
+
+    /// Sweep the capture loop should play, if one was requested.
+    pub fn take_play_request(&mut self) -> Option<SweepSettings> {
+        self.play_request.take()
+    }
+
+    /// Linear impulse response of the latest measurement and its rate.
+    pub fn impulse(&self) -> Option<(&[f32], f32)> {
+        match &self.result {
+            Some(Ok(result)) => Some((
+                &result.deconvolution.impulse,
+                result.deconvolution.sample_rate,
+            )),
+            _ => None,
+        }
+    }
+
+    fn smoothing_label(smoothing: usize) -> String {
+        if smoothing == 0 {
+            "none".to_owned()
+        } else {
+            format!("1/{} oct", smoothing)
+        }
+    }
+
+    /// Deconvolves a loaded range once its pages arrive.
+    pub fn poll(&mut self, sample_rate: f32) {
+        if let Some(load) = RangeLoad::take_ready(&mut self.pending) {
+            Self::log(&format!(
+                "deconvolving chunks {}..={}",
+                load.start, load.end
+            ));
+            let recorded =
+                Stream::from_chunks(&load.chunks(), sample_rate, |n| self.channels.active(n));
+            self.result = Some(
+                deconvolve(&recorded.samples, &self.settings, sample_rate)
+                    .map(|deconvolution| self.evaluate(deconvolution)),
+            );
+        }
+        let stale = matches!(&self.result, Some(Ok(r)) if r.smoothing != self.smoothing);
+        if stale {
+            if let Some(Ok(result)) = self.result.take() {
+                self.result = Some(Ok(self.evaluate(result.deconvolution)));
+            }
+        }
+    }
+
+    fn evaluate(&self, deconvolution: Deconvolution) -> SweepResult {
+        let fs = deconvolution.sample_rate;
+        let grid = log_grid(
+            self.settings.start_hz,
+            self.settings.end_hz.min(fs as f64 / 2.0),
+            Self::POINTS,
+        );
+        let response = frequency_response(
+            &deconvolution.impulse,
+            fs,
+            &grid,
+            self.smoothing,
+            deconvolution.pre_roll,
+        );
+        let harmonics = harmonic_distortion(&deconvolution, &grid, self.smoothing);
+        SweepResult {
+            deconvolution,
+            response,
+            harmonics,
+            smoothing: self.smoothing,
+        }
+    }
+
+    /// `load(start, end)` fetches chunk ids; `None` for the current view.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        channels: usize,
+        sample_rate: f32,
+        max_id: usize,
+        capturing: bool,
+        load: impl Fn(Option<(usize, usize)>) -> RangeLoad,
+    ) {
+        egui::CollapsingHeader::new("Sweep").show(ui, |ui| {
+            let settings = &mut self.settings;
+            let drag = |ui: &mut egui::Ui, value: &mut f64, label: &str, suffix: &str| {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    ui.add(egui::DragValue::new(value).speed(0.1).suffix(suffix));
+                });
+            };
+            drag(ui, &mut settings.start_hz, "Start", " Hz");
+            drag(ui, &mut settings.end_hz, "End", " Hz");
+            drag(ui, &mut settings.duration_s, "Duration", " s");
+            drag(ui, &mut settings.level_db, "Level", " dBFS");
+            drag(ui, &mut settings.tail_s, "Tail", " s");
+            drag(ui, &mut settings.ir_length_s, "IR length", " s");
+            ui.horizontal(|ui| {
+                ui.label("Harmonics up to");
+                ui.add(egui::DragValue::new(&mut settings.max_harmonic).range(2..=5));
+            });
+        });
+        let valid = self.settings.validate(sample_rate);
+        if let Err(error) = &valid {
+            ui.colored_label(ui.visuals().warn_fg_color, error);
+        }
+        ui.horizontal(|ui| {
+            self.channels.picker(ui, "sweep_channels", channels);
+            let play = ui
+                .add_enabled(valid.is_ok() && capturing, egui::Button::new("Play sweep"))
+                .on_disabled_hover_text("Start capture first");
+            if play.clicked() {
+                self.recording_from = Some(max_id);
+                self.play_request = Some(self.settings.clone());
+            }
+            if let Some(from) = self.recording_from {
+                let length = self.settings.duration_s + self.settings.tail_s;
+                if ui
+                    .button("Analyze recording")
+                    .on_hover_text(format!("Wait {:.1} s after playing", length))
+                    .clicked()
+                {
+                    self.pending = Some(load(Some((from, max_id))));
+                }
+            }
+            if ui.button("Analyze view").clicked() {
+                self.pending = Some(load(None));
+            }
+            if let Some(pending) = &self.pending {
+                ui.add(egui::ProgressBar::new(pending.progress()).desired_width(100.0));
+            }
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("sweep_smoothing")
+                .selected_text(Self::smoothing_label(self.smoothing))
+                .show_ui(ui, |ui| {
+                    for smoothing in Self::SMOOTHING {
+                        ui.selectable_value(
+                            &mut self.smoothing,
+                            smoothing,
+                            Self::smoothing_label(smoothing),
+                        );
+                    }
+                });
+            ui.checkbox(&mut self.show_phase, "Phase");
+            ui.checkbox(&mut self.show_harmonics, "Harmonics");
+        });
+        let result = match &self.result {
+            None => return,
+            Some(Err(error)) => {
+                ui.colored_label(ui.visuals().warn_fg_color, error);
+                return;
+            }
+            Some(Ok(result)) => result,
+        };
+        let deconvolution = &result.deconvolution;
+        ui.monospace(format!(
+            "latency {:.2} ms  IR {:.0} ms",
+            deconvolution.latency as f32 / deconvolution.sample_rate * 1000.0,
+            deconvolution.impulse.len() as f32 / deconvolution.sample_rate * 1000.0,
+        ));
+        self.draw_magnitude(ui, result);
+        if self.show_phase {
+            Self::draw_curve(
+                ui,
+                &result.response.frequency,
+                &[(
+                    &result.response.phase_deg,
+                    ui.visuals().selection.stroke.color,
+                )],
+                (-180.0, 180.0),
+                "phase (deg)",
+            );
+        }
+        if ui.button("Export CSV").clicked() {
+            Export::csv("frequency_response.csv", &Self::to_csv(result));
+        }
+    }
+
+    fn to_csv(result: &SweepResult) -> String {
+        let response = &result.response;
+        let mut csv = "frequency_hz,magnitude_db,phase_deg".to_owned();
+        for order in 0..result.harmonics.len() {
+            csv.push_str(&format!(",h{}_dbc", order + 2));
+        }
+        csv.push('\n');
+        for (i, frequency) in response.frequency.iter().enumerate() {
+            csv.push_str(&format!(
+                "{:.2},{:.2},{:.1}",
+                frequency, response.magnitude_db[i], response.phase_deg[i]
+            ));
+            for harmonic in &result.harmonics {
+                csv.push_str(&format!(",{:.2}", harmonic[i]));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn draw_magnitude(&self, ui: &mut egui::Ui, result: &SweepResult) {
+        let magnitude = &result.response.magnitude_db;
+        let top = magnitude
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(f32::NEG_INFINITY, f32::max);
+        let top = (top / 10.0).ceil() * 10.0 + 10.0;
+        let mut curves = vec![(magnitude, ui.visuals().selection.stroke.color)];
+        if self.show_harmonics {
+            curves.extend(result.harmonics.iter().zip(Self::HARMONIC_COLORS));
+        }
+        let span = if self.show_harmonics {
+            Self::MAGNITUDE_SPAN + 60.0
+        } else {
+            Self::MAGNITUDE_SPAN
+        };
+        Self::draw_curve(
+            ui,
+            &result.response.frequency,
+            &curves,
+            (top - span, top),
+            "magnitude (dB), harmonics (dBc)",
+        );
+    }
+
+    /// Plots `curves` against log frequency; NaN values break the line.
+    fn draw_curve(
+        ui: &mut egui::Ui,
+        frequency: &[f64],
+        curves: &[(&Vec<f32>, Color32)],
+        range: (f32, f32),
+        label: &str,
+    ) {
+        let (rect, _) = ui.allocate_exact_size(
+            vec2(ui.available_width(), Self::PLOT_HEIGHT),
+            Sense::hover(),
+        );
+        let vis = ui.style().noninteractive();
+        ui.painter().rect(
+            rect,
+            0.0,
+            vis.weak_bg_fill,
+            Stroke::new(1.0, vis.bg_stroke.color),
+        );
+        let painter = ui.painter_at(rect);
+        let text_color = vis.text_color();
+        let font_id = FontId::new(9.0, egui::FontFamily::Monospace);
+        let (Some(first), Some(last)) = (frequency.first(), frequency.last()) else {
+            return;
+        };
+        let (f_low, f_high) = (first.log10(), last.log10());
+        let x =
+            |f: f64| rect.min.x + ((f.log10() - f_low) / (f_high - f_low)) as f32 * rect.width();
+        let (low, high) = range;
+        let y = |v: f32| rect.max.y - ((v - low) / (high - low)).clamp(0.0, 1.0) * rect.height();
+        for decade in [100.0, 1000.0, 10000.0] {
+            if decade > *first && decade < *last {
+                painter.line_segment(
+                    [pos2(x(decade), rect.min.y), pos2(x(decade), rect.max.y)],
+                    Stroke::new(0.5, text_color.gamma_multiply(0.2)),
+                );
+                painter.text(
+                    pos2(x(decade), rect.max.y),
+                    Align2::CENTER_BOTTOM,
+                    if decade >= 1000.0 {
+                        format!("{}k", decade / 1000.0)
+                    } else {
+                        format!("{}", decade)
+                    },
+                    font_id.clone(),
+                    text_color,
+                );
+            }
+        }
+        let step = ((high - low) / 6.0 / 10.0).ceil().max(1.0) * 10.0;
+        let mut level = (low / step).ceil() * step;
+        while level <= high {
+            painter.line_segment(
+                [pos2(rect.min.x, y(level)), pos2(rect.max.x, y(level))],
+                Stroke::new(0.5, text_color.gamma_multiply(0.2)),
+            );
+            painter.text(
+                pos2(rect.min.x + 2.0, y(level)),
+                Align2::LEFT_BOTTOM,
+                format!("{}", level),
+                font_id.clone(),
+                text_color,
+            );
+            level += step;
+        }
+        for (values, color) in curves {
+            let mut segment = vec![];
+            for (f, v) in frequency.iter().zip(values.iter()) {
+                if v.is_finite() {
+                    segment.push(pos2(x(*f), y(*v)));
+                } else if !segment.is_empty() {
+                    painter.add(egui::Shape::line(
+                        std::mem::take(&mut segment),
+                        PathStroke::new(1.5, *color),
+                    ));
+                }
+            }
+            painter.add(egui::Shape::line(segment, PathStroke::new(1.5, *color)));
+        }
+        painter.text(
+            rect.right_top() + vec2(-4.0, 2.0),
+            Align2::RIGHT_TOP,
+            label,
+            font_id,
+            text_color,
+        );
+    }
+}